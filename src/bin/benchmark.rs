@@ -1,6 +1,11 @@
 use clap::Parser;
 use std::time::{Duration, Instant};
-use zkp_c_coloring::protocol::messages::Challenge;
+use zkp_c_coloring::crypto::hash::HashBackend;
+use zkp_c_coloring::crypto::merkle::MerkleArity;
+use zkp_c_coloring::graph::Color;
+use zkp_c_coloring::protocol::messages::{
+    BlankChallengeResponse, Challenge, SpotChallengeResponse,
+};
 use zkp_c_coloring::protocol::prover::{ProverConfig, ProverState};
 use zkp_c_coloring::protocol::verifier::{Verifier, VerifierConfig};
 use zkp_c_coloring::utils::random_graph::generate_hard_instance;
@@ -27,12 +32,35 @@ struct BenchmarkResult {
     proof_size_bytes: usize,
     spot_proof_size_bytes: usize,
     blank_proof_size_bytes: usize,
+    batched_spot_proof_size_bytes: usize,
+    batched_blank_proof_size_bytes: usize,
     communication_size_bytes: usize,
     spot_challenges: u32,
     blank_challenges: u32,
     total_edges_verified: u32,
 }
 
+/// Bytes of Merkle authentication in the responses to one challenge set, committed under
+/// one tree layout.
+struct LayoutSizes {
+    nodes: u32,
+    arity: MerkleArity,
+    row_per_chunk: bool,
+    spot_path_bytes: usize,
+    spot_multiproof_bytes: usize,
+    blank_path_bytes: usize,
+    blank_multiproof_bytes: usize,
+}
+
+/// Time to recommit after editing `edits` edges, from scratch and incrementally.
+struct RecommitTimes {
+    nodes: u32,
+    edits: usize,
+    flips_blanks: bool,
+    full_commit_ms: f64,
+    incremental_ms: f64,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum BlankStrategy {
     Sampling,
@@ -79,6 +107,30 @@ struct BenchmarkCli {
     /// Number of spot challenges per round (default: 4)
     #[arg(long)]
     spots_per_round: Option<u32>,
+    /// Hash backend: blake3, sha3-256 or shake256
+    #[arg(long, default_value_t = HashBackend::Blake3)]
+    hash: HashBackend,
+    /// Threads for building commitments (default: all cores)
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Children per Merkle node for the timed runs: 2, 4, 8 or 16
+    #[arg(long, default_value_t = MerkleArity::Two)]
+    merkle_arity: MerkleArity,
+    /// Give every adjacency row its own graph-tree chunk in the timed runs
+    #[arg(long)]
+    row_per_chunk: bool,
+    /// Comma-separated arities to compare proof sizes for (default: 2,4,8,16)
+    #[arg(long, value_delimiter = ',')]
+    layout_arities: Option<Vec<MerkleArity>>,
+    /// Skip the Merkle layout proof-size comparison
+    #[arg(long)]
+    skip_layout_comparison: bool,
+    /// Comma-separated numbers of edited edges to time re-commitment for (default: 1,16,256)
+    #[arg(long, value_delimiter = ',')]
+    recommit_edits: Option<Vec<usize>>,
+    /// Skip the incremental re-commitment comparison
+    #[arg(long)]
+    skip_recommit_comparison: bool,
 }
 
 fn main() {
@@ -99,11 +151,18 @@ fn main() {
         for &rounds in &round_counts {
             for &strategy in &blank_strategies {
                 println!(
-                    "Benchmarking n={} nodes, {} rounds, blank strategy: {}",
-                    nodes, rounds, strategy
+                    "Benchmarking n={} nodes, {} rounds, blank strategy: {}, hash: {}",
+                    nodes, rounds, strategy, cli.hash
                 );
 
-                match run_benchmark(nodes, rounds, strategy, spots_per_round) {
+                let prover_cfg = ProverConfig {
+                    hash: cli.hash,
+                    threads: cli.threads,
+                    merkle_arity: cli.merkle_arity,
+                    row_per_chunk: cli.row_per_chunk,
+                    ..ProverConfig::default()
+                };
+                match run_benchmark(nodes, rounds, strategy, spots_per_round, &prover_cfg) {
                     Ok(result) => {
                         print_result(&result);
                         all_results.push(result);
@@ -120,6 +179,42 @@ fn main() {
     println!("\n=== Summary Report ===\n");
     print_summary_table(&all_results);
     print_scaling_analysis(&all_results);
+
+    if !cli.skip_layout_comparison {
+        let arities = cli
+            .layout_arities
+            .unwrap_or_else(|| MerkleArity::ALL.to_vec());
+        let rounds = round_counts.iter().copied().max().unwrap_or(10);
+        let prover_cfg = ProverConfig {
+            hash: cli.hash,
+            threads: cli.threads,
+            ..ProverConfig::default()
+        };
+        let mut layouts = Vec::new();
+        for &nodes in &node_sizes {
+            match compare_layouts(nodes, rounds, spots_per_round, &arities, &prover_cfg) {
+                Ok(sizes) => layouts.extend(sizes),
+                Err(e) => eprintln!("  ✗ Layout comparison for n={} failed: {}", nodes, e),
+            }
+        }
+        print_layout_comparison(&layouts);
+    }
+
+    if !cli.skip_recommit_comparison {
+        let edit_counts = cli.recommit_edits.unwrap_or_else(|| vec![1, 16, 256]);
+        let prover_cfg = ProverConfig {
+            hash: cli.hash,
+            threads: cli.threads,
+            merkle_arity: cli.merkle_arity,
+            row_per_chunk: cli.row_per_chunk,
+            ..ProverConfig::default()
+        };
+        let times: Vec<RecommitTimes> = node_sizes
+            .iter()
+            .flat_map(|&nodes| compare_recommit(nodes, &edit_counts, &prover_cfg))
+            .collect();
+        print_recommit_comparison(&times);
+    }
 }
 
 fn run_benchmark(
@@ -127,7 +222,9 @@ fn run_benchmark(
     rounds: u32,
     blank_strategy: BlankStrategy,
     spots_per_round: u32,
+    prover_cfg: &ProverConfig,
 ) -> Result<BenchmarkResult, Box<dyn std::error::Error>> {
+    let hash = prover_cfg.hash;
     let (graph, coloration, _) = generate_hard_instance(nodes);
     let _instance = GraphInstance::new(graph.clone(), coloration.clone());
 
@@ -137,19 +234,19 @@ fn run_benchmark(
             spots_per_round,
             blank_checks_per_round: 2,
             spot_probability: 0.7,
+            permutation_probability: 0.0,
         },
         BlankStrategy::FullCheck => {
-            let total_edges = (nodes * nodes) as u32;
+            let total_edges = nodes * nodes;
             VerifierConfig {
                 rounds,
                 spots_per_round,
                 blank_checks_per_round: total_edges,
                 spot_probability: 0.3,
+                permutation_probability: 0.0,
             }
         }
     };
-    let prover_cfg = ProverConfig::default();
-
     let mem_before = get_memory_usage();
 
     let mut spot_response_time = Duration::ZERO;
@@ -158,13 +255,15 @@ fn run_benchmark(
     let mut blank_verify_time = Duration::ZERO;
     let mut spot_proof_size = 0usize;
     let mut blank_proof_size = 0usize;
+    let mut batched_spot_proof_size = 0usize;
+    let mut batched_blank_proof_size = 0usize;
 
     let prove_start = Instant::now();
     let mut prover = ProverState::new(graph.clone(), coloration.clone());
     let commit_start = Instant::now();
-    let commitments = prover.commit(&prover_cfg);
+    let commitments = prover.commit(prover_cfg);
     let commit_time = commit_start.elapsed();
-    let mut verifier = Verifier::new(coloration.clone(), verifier_cfg.clone());
+    let mut verifier = Verifier::new(coloration.clone(), verifier_cfg.clone()).with_hash(hash);
     verifier.receive_commitments(commitments.clone());
 
     let mut spot_count = 0u32;
@@ -172,8 +271,10 @@ fn run_benchmark(
     let mut total_edges = 0u32;
     let mut proof_size = 0usize;
 
+    let mut challenges = Vec::with_capacity(rounds as usize);
     for round in 0..rounds {
         let challenge = verifier.generate_challenge(round);
+        challenges.push(challenge.clone());
         match challenge {
             Challenge::Spot(ref ch) => {
                 spot_count += 1;
@@ -188,9 +289,9 @@ fn run_benchmark(
                     .iter()
                     .map(|r| r.edges.len() as u32)
                     .sum::<u32>();
-                if !verifier.verify_spot_response(ch, &response) {
-                    return Err("spot verification failed".into());
-                }
+                verifier
+                    .verify_spot_response(ch, &response)
+                    .map_err(|err| err.in_round(round))?;
             }
             Challenge::Blank(ref ch) => {
                 blank_count += 1;
@@ -201,17 +302,48 @@ fn run_benchmark(
                 proof_size += response_size;
                 blank_proof_size += response_size;
                 total_edges += response.edges.len() as u32;
-                if !verifier.verify_blank_response(ch, &response) {
-                    return Err("blank verification failed".into());
-                }
+                verifier
+                    .verify_blank_response(ch, &response)
+                    .map_err(|err| err.in_round(round))?;
+            }
+            Challenge::Permutation(ref ch) => {
+                let response = prover.respond_to_permutation_challenge(ch);
+                verifier
+                    .verify_permutation_response(ch, &response)
+                    .map_err(|err| err.in_round(round))?;
             }
         }
     }
 
     let prove_time = prove_start.elapsed();
 
+    // Answer the same challenges again with multiproofs, outside the timed passes, to
+    // report how much batching the Merkle openings saves.
+    prover.set_batch_openings(true);
+    for (round, challenge) in challenges.iter().enumerate() {
+        let round = round as u32;
+        match challenge {
+            Challenge::Spot(ch) => {
+                let response = prover.respond_to_spot_challenge(ch);
+                batched_spot_proof_size += estimate_spot_response_size(&response);
+                verifier
+                    .verify_spot_response(ch, &response)
+                    .map_err(|err| err.in_round(round))?;
+            }
+            Challenge::Blank(ch) => {
+                let response = prover.respond_to_blank_challenge(ch);
+                batched_blank_proof_size += estimate_blank_response_size(&response);
+                verifier
+                    .verify_blank_response(ch, &response)
+                    .map_err(|err| err.in_round(round))?;
+            }
+            Challenge::Permutation(_) => {}
+        }
+    }
+    prover.set_batch_openings(false);
+
     let verify_start = Instant::now();
-    let mut verifier = Verifier::new(coloration.clone(), verifier_cfg.clone());
+    let mut verifier = Verifier::new(coloration.clone(), verifier_cfg.clone()).with_hash(hash);
     verifier.receive_commitments(commitments.clone());
     for round in 0..rounds {
         let challenge = verifier.generate_challenge(round);
@@ -219,19 +351,25 @@ fn run_benchmark(
             Challenge::Spot(ref ch) => {
                 let response = prover.respond_to_spot_challenge(ch);
                 let verify_start = Instant::now();
-                if !verifier.verify_spot_response(ch, &response) {
-                    return Err("spot re-verification failed".into());
-                }
+                verifier
+                    .verify_spot_response(ch, &response)
+                    .map_err(|err| err.in_round(round))?;
                 spot_verify_time += verify_start.elapsed();
             }
             Challenge::Blank(ref ch) => {
                 let response = prover.respond_to_blank_challenge(ch);
                 let verify_start = Instant::now();
-                if !verifier.verify_blank_response(ch, &response) {
-                    return Err("blank re-verification failed".into());
-                }
+                verifier
+                    .verify_blank_response(ch, &response)
+                    .map_err(|err| err.in_round(round))?;
                 blank_verify_time += verify_start.elapsed();
             }
+            Challenge::Permutation(ref ch) => {
+                let response = prover.respond_to_permutation_challenge(ch);
+                verifier
+                    .verify_permutation_response(ch, &response)
+                    .map_err(|err| err.in_round(round))?;
+            }
         }
     }
     let verify_time = verify_start.elapsed();
@@ -258,6 +396,8 @@ fn run_benchmark(
         proof_size_bytes: proof_size,
         spot_proof_size_bytes: spot_proof_size,
         blank_proof_size_bytes: blank_proof_size,
+        batched_spot_proof_size_bytes: batched_spot_proof_size,
+        batched_blank_proof_size_bytes: batched_blank_proof_size,
         communication_size_bytes: communication_size,
         spot_challenges: spot_count,
         blank_challenges: blank_count,
@@ -265,10 +405,194 @@ fn run_benchmark(
     })
 }
 
+/// Commits to one instance under every arity in `arities`, with fixed-size and
+/// row-per-chunk graph chunks, and answers the same challenges under each layout with
+/// per-edge paths and with multiproofs.
+fn compare_layouts(
+    nodes: u32,
+    rounds: u32,
+    spots_per_round: u32,
+    arities: &[MerkleArity],
+    prover_cfg: &ProverConfig,
+) -> Result<Vec<LayoutSizes>, Box<dyn std::error::Error>> {
+    let (graph, coloration, _) = generate_hard_instance(nodes);
+    let verifier_cfg = VerifierConfig {
+        rounds,
+        spots_per_round,
+        blank_checks_per_round: 2,
+        spot_probability: 0.7,
+        permutation_probability: 0.0,
+    };
+    let mut challenges: Option<Vec<Challenge>> = None;
+    let mut sizes = Vec::new();
+    for &arity in arities {
+        for row_per_chunk in [false, true] {
+            let cfg = ProverConfig {
+                merkle_arity: arity,
+                row_per_chunk,
+                ..prover_cfg.clone()
+            };
+            let mut prover = ProverState::new(graph.clone(), coloration.clone());
+            let mut verifier =
+                Verifier::new(coloration.clone(), verifier_cfg.clone()).with_hash(cfg.hash);
+            verifier.receive_commitments(prover.commit(&cfg));
+            let challenges = challenges.get_or_insert_with(|| {
+                (0..rounds)
+                    .map(|round| verifier.generate_challenge(round))
+                    .collect()
+            });
+
+            let mut layout = LayoutSizes {
+                nodes,
+                arity,
+                row_per_chunk,
+                spot_path_bytes: 0,
+                spot_multiproof_bytes: 0,
+                blank_path_bytes: 0,
+                blank_multiproof_bytes: 0,
+            };
+            for batch_openings in [false, true] {
+                prover.set_batch_openings(batch_openings);
+                for (round, challenge) in challenges.iter().enumerate() {
+                    let round = round as u32;
+                    match challenge {
+                        Challenge::Spot(ch) => {
+                            let response = prover.respond_to_spot_challenge(ch);
+                            verifier
+                                .verify_spot_response(ch, &response)
+                                .map_err(|err| err.in_round(round))?;
+                            let bytes = spot_merkle_bytes(&response);
+                            if batch_openings {
+                                layout.spot_multiproof_bytes += bytes;
+                            } else {
+                                layout.spot_path_bytes += bytes;
+                            }
+                        }
+                        Challenge::Blank(ch) => {
+                            let response = prover.respond_to_blank_challenge(ch);
+                            verifier
+                                .verify_blank_response(ch, &response)
+                                .map_err(|err| err.in_round(round))?;
+                            let bytes = blank_merkle_bytes(&response);
+                            if batch_openings {
+                                layout.blank_multiproof_bytes += bytes;
+                            } else {
+                                layout.blank_path_bytes += bytes;
+                            }
+                        }
+                        Challenge::Permutation(_) => {}
+                    }
+                }
+            }
+            sizes.push(layout);
+        }
+    }
+    Ok(sizes)
+}
+
+/// Times [`ProverState::recommit_with_edits`] against a full [`ProverState::commit`] for
+/// each number of edited edges, once recoloring edges (the blank-count trace is kept) and
+/// once flipping blank bits (the trace and its STARK proof are rebuilt).
+fn compare_recommit(
+    nodes: u32,
+    edit_counts: &[usize],
+    prover_cfg: &ProverConfig,
+) -> Vec<RecommitTimes> {
+    let (graph, coloration, _) = generate_hard_instance(nodes);
+    let mut prover = ProverState::new(graph, coloration);
+    let mut times = Vec::new();
+    for &edits in edit_counts {
+        for flips_blanks in [false, true] {
+            let current = &prover.original_graph;
+            let (blanks, colored): (Vec<_>, Vec<_>) = (0..nodes)
+                .flat_map(|from| (0..nodes).map(move |to| (from, to, current.get_edge(from, to))))
+                .partition(|&(_, _, color)| color == Color::Blank);
+            // Blank flips color as many blank edges as they blank colored ones, so the
+            // edited witness stays within its blank budget.
+            let edited: Vec<(u32, u32, Color)> = if flips_blanks {
+                let blanked = edits / 2;
+                spread_edits(&blanks, edits - blanked)
+                    .map(|(from, to, _)| (from, to, Color::Red))
+                    .chain(
+                        spread_edits(&colored, blanked)
+                            .map(|(from, to, _)| (from, to, Color::Blank)),
+                    )
+                    .collect()
+            } else {
+                spread_edits(&colored, edits)
+                    .map(|(from, to, color)| {
+                        let color = match color {
+                            Color::Red => Color::Green,
+                            _ => Color::Red,
+                        };
+                        (from, to, color)
+                    })
+                    .collect()
+            };
+
+            let start = Instant::now();
+            prover.commit(prover_cfg);
+            let full_commit = start.elapsed();
+            let start = Instant::now();
+            prover
+                .recommit_with_edits(&edited)
+                .expect("edits keep the blank budget");
+            let incremental = start.elapsed();
+            times.push(RecommitTimes {
+                nodes,
+                edits: edited.len(),
+                flips_blanks,
+                full_commit_ms: full_commit.as_secs_f64() * 1000.0,
+                incremental_ms: incremental.as_secs_f64() * 1000.0,
+            });
+        }
+    }
+    times
+}
+
+/// Up to `count` of `edges`, spread over the whole matrix so they touch many chunks.
+fn spread_edits(
+    edges: &[(u32, u32, Color)],
+    count: usize,
+) -> impl Iterator<Item = (u32, u32, Color)> + '_ {
+    let stride = (edges.len() / count.max(1)).max(1);
+    edges.iter().copied().step_by(stride).take(count)
+}
+
+/// Merkle authentication carried by a spot response, without the opened contents.
+fn spot_merkle_bytes(response: &SpotChallengeResponse) -> usize {
+    response
+        .responses
+        .iter()
+        .flat_map(|spot| &spot.edges)
+        .filter_map(|edge| edge.proof.as_ref())
+        .map(estimate_merkle_proof_size)
+        .sum::<usize>()
+        + response
+            .multiproof
+            .as_ref()
+            .map_or(0, estimate_multiproof_size)
+}
+
+/// Merkle authentication carried by a blank response, without the openings or the STARK.
+fn blank_merkle_bytes(response: &BlankChallengeResponse) -> usize {
+    let paths: usize = response
+        .edges
+        .iter()
+        .flat_map(|edge| [&edge.edge_proof, &edge.blank_proof])
+        .filter_map(Option::as_ref)
+        .map(estimate_merkle_proof_size)
+        .sum();
+    let multiproofs = response.multiproofs.as_ref().map_or(0, |multiproofs| {
+        estimate_multiproof_size(&multiproofs.edges) + estimate_multiproof_size(&multiproofs.blanks)
+    });
+    paths + multiproofs
+}
+
 fn estimate_commitment_size(
     _commitments: &zkp_c_coloring::protocol::messages::Commitments,
 ) -> usize {
-    32 + 32 + 32 + 4 // graph_root + perm_root + blank_root + blank_count
+    32 + 32 + 32 + 32 + 4 // graph_root + perm_root + blank_root + blank_trace_root + blank_limit
 }
 
 fn estimate_spot_response_size(
@@ -278,10 +602,14 @@ fn estimate_spot_response_size(
     for spot in &response.responses {
         size += 12; // nodes array
         for edge in &spot.edges {
-            size += 8 + 1; // from + to + color
-            size += estimate_merkle_proof_size(&edge.proof);
+            size += 8 + 1 + 32 + 32; // from + to + color + color/blank salts
+            size += edge.proof.as_ref().map_or(0, estimate_merkle_proof_size);
         }
     }
+    size += response
+        .multiproof
+        .as_ref()
+        .map_or(0, estimate_multiproof_size);
     size
 }
 
@@ -290,25 +618,56 @@ fn estimate_blank_response_size(
 ) -> usize {
     let mut size = 0;
     for edge in &response.edges {
-        size += 8 + 8 + 1 + 1; // edge_index + from + to + color + is_blank
-        size += estimate_merkle_proof_size(&edge.color_proof);
-        size += estimate_merkle_proof_size(&edge.blank_proof);
+        size += 8 + 8 + 1 + 32 + 32; // edge_index + from + to + is_blank + color digest + blank salt
+        size += edge
+            .edge_proof
+            .as_ref()
+            .map_or(0, estimate_merkle_proof_size);
+        size += edge
+            .blank_proof
+            .as_ref()
+            .map_or(0, estimate_merkle_proof_size);
+    }
+    if let Some(multiproofs) = &response.multiproofs {
+        size += estimate_multiproof_size(&multiproofs.edges);
+        size += estimate_multiproof_size(&multiproofs.blanks);
     }
     size += estimate_stark_proof_size(&response.stark_proof);
     size
 }
 
 fn estimate_merkle_proof_size(proof: &zkp_c_coloring::crypto::merkle::ChunkedMerkleProof) -> usize {
-    let leaf_path_size = 32 + proof.leaf_proof.path.len() * (32 + 1);
-    let chunk_path_size = 32 + proof.chunk_proof.path.len() * (32 + 1);
+    let leaf_path_size = 32 + proof.leaf_proof.path.len() * 32;
+    let chunk_path_size = 32 + proof.chunk_proof.path.len() * 32;
     leaf_path_size + chunk_path_size
 }
 
+fn estimate_multiproof_size(
+    proof: &zkp_c_coloring::crypto::merkle::ChunkedMerkleMultiProof,
+) -> usize {
+    let subproofs = proof.chunk_proofs.len() + 1;
+    let indices = proof.top_proof.leaf_indices.len()
+        + proof
+            .chunk_proofs
+            .iter()
+            .map(|chunk| chunk.leaf_indices.len())
+            .sum::<usize>();
+    8 + subproofs * 8 + indices * 8 + proof.hash_count() * 32 // chunk size + leaf counts + indices + siblings
+}
+
 fn estimate_stark_proof_size(_proof: &zkp_c_coloring::stark::prover::BlankCountProof) -> usize {
     // Conservative estimate based on FRI layers, queries, and trace commitment
     1024 * 8 // ~8KB for typical STARK proof
 }
 
+fn size_reduction_percent(unbatched: usize, batched: usize) -> f64 {
+    if unbatched == 0 {
+        0.0
+    } else {
+        100.0 * (1.0 - batched as f64 / unbatched as f64)
+    }
+}
+
 #[cfg(target_os = "windows")]
 fn get_memory_usage() -> f64 {
     unsafe {
@@ -354,6 +713,13 @@ fn print_result(result: &BenchmarkResult) {
         "  ✓ Total proof size: {:.2} KB",
         result.proof_size_bytes as f64 / 1024.0
     );
+    let batched = result.batched_spot_proof_size_bytes + result.batched_blank_proof_size_bytes;
+    println!(
+        "      with multiproofs: spot {:.2} KB · blank {:.2} KB ({:.1}% smaller)",
+        result.batched_spot_proof_size_bytes as f64 / 1024.0,
+        result.batched_blank_proof_size_bytes as f64 / 1024.0,
+        size_reduction_percent(result.proof_size_bytes, batched)
+    );
     println!(
         "  ✓ Total communication: {:.2} KB",
         result.communication_size_bytes as f64 / 1024.0
@@ -365,7 +731,7 @@ fn print_result(result: &BenchmarkResult) {
 
 fn print_summary_table(results: &[BenchmarkResult]) {
     println!(
-        "{:<6} {:<7} {:<12} {:<12} {:<12} {:<12} {:<12} {:<12} {:<12} {:<12}",
+        "{:<6} {:<7} {:<12} {:<12} {:<12} {:<12} {:<12} {:<12} {:<12} {:<12} {:<12}",
         "Nodes",
         "Rounds",
         "Strategy",
@@ -375,13 +741,14 @@ fn print_summary_table(results: &[BenchmarkResult]) {
         "Prove(ms)",
         "Verify(ms)",
         "Proof(KB)",
+        "Batched(KB)",
         "Comm(KB)"
     );
-    println!("{}", "-".repeat(130));
+    println!("{}", "-".repeat(143));
 
     for result in results {
         println!(
-            "{:<6} {:<7} {:<12} {:<12.2} {:<12.2} {:<12.2} {:<12.2} {:<12.2} {:<12.2} {:<12.2}",
+            "{:<6} {:<7} {:<12} {:<12.2} {:<12.2} {:<12.2} {:<12.2} {:<12.2} {:<12.2} {:<12.2} {:<12.2}",
             result.nodes,
             result.rounds,
             result.blank_strategy.to_string(),
@@ -391,11 +758,68 @@ fn print_summary_table(results: &[BenchmarkResult]) {
             result.prove_time_ms,
             result.verify_time_ms,
             result.proof_size_bytes as f64 / 1024.0,
+            (result.batched_spot_proof_size_bytes + result.batched_blank_proof_size_bytes) as f64
+                / 1024.0,
             result.communication_size_bytes as f64 / 1024.0
         );
     }
 }
 
+fn print_layout_comparison(layouts: &[LayoutSizes]) {
+    println!("\n=== Merkle Layout Comparison ===\n");
+    println!(
+        "{:<6} {:<6} {:<14} {:<14} {:<14} {:<14} {:<14}",
+        "Nodes",
+        "Arity",
+        "Graph chunks",
+        "Spot(KB)",
+        "Spot multi(KB)",
+        "Blank(KB)",
+        "Blank multi(KB)"
+    );
+    println!("{}", "-".repeat(88));
+    for layout in layouts {
+        println!(
+            "{:<6} {:<6} {:<14} {:<14.2} {:<14.2} {:<14.2} {:<14.2}",
+            layout.nodes,
+            layout.arity.to_string(),
+            if layout.row_per_chunk {
+                "per row"
+            } else {
+                "fixed"
+            },
+            layout.spot_path_bytes as f64 / 1024.0,
+            layout.spot_multiproof_bytes as f64 / 1024.0,
+            layout.blank_path_bytes as f64 / 1024.0,
+            layout.blank_multiproof_bytes as f64 / 1024.0
+        );
+    }
+}
+
+fn print_recommit_comparison(times: &[RecommitTimes]) {
+    println!("\n=== Incremental Re-commitment ===\n");
+    println!(
+        "{:<6} {:<8} {:<12} {:<16} {:<16} {:<10}",
+        "Nodes", "Edits", "Kind", "Full commit(ms)", "Incremental(ms)", "Speedup"
+    );
+    println!("{}", "-".repeat(72));
+    for time in times {
+        println!(
+            "{:<6} {:<8} {:<12} {:<16.2} {:<16.2} {:<10.1}",
+            time.nodes,
+            time.edits,
+            if time.flips_blanks {
+                "blank flips"
+            } else {
+                "recolor"
+            },
+            time.full_commit_ms,
+            time.incremental_ms,
+            time.full_commit_ms / time.incremental_ms.max(f64::EPSILON)
+        );
+    }
+}
+
 fn print_scaling_analysis(results: &[BenchmarkResult]) {
     println!("\n=== Scaling Analysis ===\n");
 
@@ -1,3 +1,9 @@
+#![allow(
+    clippy::needless_range_loop,
+    clippy::explicit_counter_loop,
+    clippy::type_complexity
+)]
+
 use rand::prelude::*;
 use rand::rng;
 use std::collections::HashSet;
@@ -151,7 +157,7 @@ pub fn canonical_triad(t: &Triad) -> ([bool; 9], [char; 9]) {
         }
 
         let cand = (e2, c2);
-        if best.is_none() || cand < best.clone().unwrap() {
+        if best.is_none() || cand < best.unwrap() {
             best = Some(cand);
         }
     }
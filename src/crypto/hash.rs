@@ -1,7 +1,31 @@
 use blake3::Hasher as Blake3Hasher;
-use sha3::{Digest, Sha3_512};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use sha3::{Digest, Sha3_256, Shake256};
+use std::fmt;
+use std::str::FromStr;
+
+/// Per-leaf blinding value mixed into a Merkle leaf so that low-entropy leaves
+/// (edge colors, blank bits, permutation entries) cannot be brute-forced from the root.
+pub type LeafSalt = [u8; 32];
+
+pub fn random_salts(count: usize) -> Vec<LeafSalt> {
+    let mut rng = rand::rng();
+    (0..count).map(|_| rng.random::<LeafSalt>()).collect()
+}
+
+pub fn salted_leaf(data: &[u8], salt: &LeafSalt) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(salt.len() + data.len());
+    buf.extend_from_slice(salt);
+    buf.extend_from_slice(data);
+    buf
+}
 
 pub trait QuantumHash: Send + Sync {
+    /// Stable name of the hash function, bound into statement digests.
+    fn id(&self) -> &'static str;
+
     fn hash(&self, data: &[u8]) -> [u8; 32];
 
     fn hash_with_salt(&self, data: &[u8], salt: &[u8]) -> [u8; 32] {
@@ -31,6 +55,10 @@ pub fn hash_chain(hasher: &dyn QuantumHash, seed: &[u8], rounds: usize) -> [u8;
 pub struct Blake3QuantumHash;
 
 impl QuantumHash for Blake3QuantumHash {
+    fn id(&self) -> &'static str {
+        "blake3"
+    }
+
     fn hash(&self, data: &[u8]) -> [u8; 32] {
         let mut hasher = Blake3Hasher::new();
         hasher.update(data);
@@ -42,11 +70,93 @@ impl QuantumHash for Blake3QuantumHash {
 pub struct Sha3QuantumHash;
 
 impl QuantumHash for Sha3QuantumHash {
+    fn id(&self) -> &'static str {
+        "sha3-256"
+    }
+
     fn hash(&self, data: &[u8]) -> [u8; 32] {
-        let mut hasher = Sha3_512::new();
+        Sha3_256::digest(data).into()
+    }
+}
+
+/// SHAKE256 squeezed to 32 bytes.
+#[derive(Clone, Default)]
+pub struct Shake256QuantumHash;
+
+impl QuantumHash for Shake256QuantumHash {
+    fn id(&self) -> &'static str {
+        "shake256"
+    }
+
+    fn hash(&self, data: &[u8]) -> [u8; 32] {
+        let mut hasher = Shake256::default();
         hasher.update(data);
-        let result = hasher.finalize();
-        result[..32].try_into().expect("sha3 output length")
+        let mut output = [0u8; 32];
+        hasher.finalize_xof().read(&mut output);
+        output
+    }
+}
+
+/// Hash function a protocol session runs with. Provers and verifiers dispatch every
+/// Merkle, STARK and challenge hash through it, and `Commitments` records which one the
+/// prover used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HashBackend {
+    #[default]
+    Blake3,
+    Sha3_256,
+    Shake256,
+}
+
+impl HashBackend {
+    pub const ALL: [HashBackend; 3] = [
+        HashBackend::Blake3,
+        HashBackend::Sha3_256,
+        HashBackend::Shake256,
+    ];
+
+    /// The backend whose [`QuantumHash::id`] is `id`.
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|backend| backend.id() == id)
+    }
+}
+
+impl QuantumHash for HashBackend {
+    fn id(&self) -> &'static str {
+        match self {
+            HashBackend::Blake3 => Blake3QuantumHash.id(),
+            HashBackend::Sha3_256 => Sha3QuantumHash.id(),
+            HashBackend::Shake256 => Shake256QuantumHash.id(),
+        }
+    }
+
+    fn hash(&self, data: &[u8]) -> [u8; 32] {
+        match self {
+            HashBackend::Blake3 => Blake3QuantumHash.hash(data),
+            HashBackend::Sha3_256 => Sha3QuantumHash.hash(data),
+            HashBackend::Shake256 => Shake256QuantumHash.hash(data),
+        }
+    }
+}
+
+impl fmt::Display for HashBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+impl FromStr for HashBackend {
+    type Err = String;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        Self::from_id(id).ok_or_else(|| {
+            let known: Vec<&str> = Self::ALL.iter().map(|backend| backend.id()).collect();
+            format!(
+                "unknown hash '{}' (expected one of {})",
+                id,
+                known.join(", ")
+            )
+        })
     }
 }
 
@@ -1,111 +1,541 @@
-use crate::crypto::hash::QuantumHash;
-use crate::graph::Graph;
+use crate::crypto::hash::{LeafSalt, QuantumHash};
+use crate::crypto::merkle_storage::{LevelStore, MerkleStorage};
+use crate::crypto::proof_cache::{CacheStats, ProofCache, DEFAULT_CACHE_CAPACITY};
+use crate::graph::{Color, Graph};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::cell::RefCell;
-use std::collections::{HashMap, VecDeque};
-use std::env;
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::Arc;
 
 const DEFAULT_CHUNK_SIZE: usize = 1024;
-const DEFAULT_CACHE_SIZE: usize = 2048;
+
+/// Tree a Merkle node belongs to. Every node hash is prefixed with its kind (leaf,
+/// internal node, chunk root, padding or sized root) and this domain, so no node can be
+/// passed off as another kind of node or as part of another tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MerkleDomain {
+    Graph,
+    Blank,
+    Permutation,
+    Trace,
+    FriLayer,
+}
+
+/// Largest leaf count a tree, or a proof claiming to open one, may have: the padded
+/// count must still be a power of two that fits a `u64`.
+const MAX_LEAF_COUNT: u64 = 1 << 63;
+
+/// Children per internal node of a [`MerkleTree`]. Wider trees are shallower: a single
+/// opening carries fewer levels but `arity - 1` siblings on each of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MerkleArity {
+    #[default]
+    Two,
+    Four,
+    Eight,
+    Sixteen,
+}
+
+impl MerkleArity {
+    pub const ALL: [MerkleArity; 4] = [
+        MerkleArity::Two,
+        MerkleArity::Four,
+        MerkleArity::Eight,
+        MerkleArity::Sixteen,
+    ];
+
+    pub fn children(self) -> usize {
+        match self {
+            MerkleArity::Two => 2,
+            MerkleArity::Four => 4,
+            MerkleArity::Eight => 8,
+            MerkleArity::Sixteen => 16,
+        }
+    }
+
+    /// Number of levels above `leaf_count` leaves once padded to a power of the arity.
+    /// `leaf_count` must not exceed [`MAX_LEAF_COUNT`]; proofs check that first.
+    fn depth(self, leaf_count: u64) -> usize {
+        debug_assert!(leaf_count <= MAX_LEAF_COUNT);
+        let bits = leaf_count.max(1).next_power_of_two().trailing_zeros();
+        bits.div_ceil(self.children().trailing_zeros()) as usize
+    }
+}
+
+impl fmt::Display for MerkleArity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.children())
+    }
+}
+
+impl FromStr for MerkleArity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|arity| arity.to_string() == value.trim())
+            .ok_or_else(|| {
+                format!(
+                    "unsupported Merkle arity '{}' (expected 2, 4, 8 or 16)",
+                    value
+                )
+            })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum NodeKind {
+    Leaf = 0,
+    Internal = 1,
+    Chunk = 2,
+    Padding = 3,
+    Root = 4,
+}
+
+fn tagged_hash(
+    kind: NodeKind,
+    domain: MerkleDomain,
+    parts: &[&[u8]],
+    hasher: &dyn QuantumHash,
+) -> [u8; 32] {
+    let mut data = Vec::with_capacity(2 + parts.iter().map(|part| part.len()).sum::<usize>());
+    data.push(kind as u8);
+    data.push(domain as u8);
+    for part in parts {
+        data.extend_from_slice(part);
+    }
+    hasher.hash(&data)
+}
+
+/// Hash of the leaf holding `data` in a tree of `domain`, as found in
+/// [`MerkleProof::leaf_hash`].
+pub fn leaf_digest(domain: MerkleDomain, data: &[u8], hasher: &dyn QuantumHash) -> [u8; 32] {
+    tagged_hash(NodeKind::Leaf, domain, &[data], hasher)
+}
+
+fn node_digest(domain: MerkleDomain, children: &[[u8; 32]], hasher: &dyn QuantumHash) -> [u8; 32] {
+    tagged_hash(
+        NodeKind::Internal,
+        domain,
+        &[children.as_flattened()],
+        hasher,
+    )
+}
+
+/// Top-tree leaf standing for the chunk whose subtree root is `chunk_root`.
+fn chunk_digest(domain: MerkleDomain, chunk_root: &[u8; 32], hasher: &dyn QuantumHash) -> [u8; 32] {
+    tagged_hash(NodeKind::Chunk, domain, &[chunk_root], hasher)
+}
+
+fn sized_root(
+    domain: MerkleDomain,
+    leaf_count: u64,
+    arity: MerkleArity,
+    top: &[u8; 32],
+    hasher: &dyn QuantumHash,
+) -> [u8; 32] {
+    tagged_hash(
+        NodeKind::Root,
+        domain,
+        &[&leaf_count.to_be_bytes(), &[arity.children() as u8], top],
+        hasher,
+    )
+}
+
+/// Node `current` at position `index % arity` among its siblings, with `siblings` (in
+/// child order) filling the other positions.
+fn parent_digest(
+    domain: MerkleDomain,
+    index: u64,
+    current: [u8; 32],
+    siblings: &[[u8; 32]],
+    hasher: &dyn QuantumHash,
+) -> [u8; 32] {
+    let position = (index % (siblings.len() as u64 + 1)) as usize;
+    let mut children = siblings.to_vec();
+    children.insert(position, current);
+    node_digest(domain, &children, hasher)
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MerkleProof {
     pub leaf_index: u64,
+    /// Unpadded size of the tree, which its root commits to.
+    pub leaf_count: u64,
+    /// Arity of the tree, which its root commits to.
+    pub arity: MerkleArity,
     pub leaf_hash: [u8; 32],
-    pub path: Vec<([u8; 32], bool)>,
+    /// `arity - 1` siblings per level from the leaf up, each level's in child order. The
+    /// position of the path node among them follows from the leaf index.
+    pub path: Vec<[u8; 32]>,
 }
 
 impl MerkleProof {
-    pub fn verify(&self, root: &[u8; 32], hasher: &dyn QuantumHash) -> bool {
+    pub fn verify(&self, domain: MerkleDomain, root: &[u8; 32], hasher: &dyn QuantumHash) -> bool {
+        self.well_formed() && &self.computed_root(domain, hasher) == root
+    }
+
+    /// Whether the path opens leaf `index`. The path is walked along the digits of the
+    /// leaf index, so it cannot authenticate any other position.
+    pub fn opens_index(&self, index: u64) -> bool {
+        self.leaf_index == index
+    }
+
+    /// Whether the path has the shape of a real leaf of a `leaf_count`-leaf tree:
+    /// `arity - 1` siblings on each of its levels.
+    fn well_formed(&self) -> bool {
+        self.leaf_count <= MAX_LEAF_COUNT
+            && self.leaf_index < self.leaf_count
+            && self.path.len() == self.arity.depth(self.leaf_count) * (self.arity.children() - 1)
+    }
+
+    fn computed_root(&self, domain: MerkleDomain, hasher: &dyn QuantumHash) -> [u8; 32] {
+        let arity = self.arity.children();
+        let mut index = self.leaf_index;
         let mut current = self.leaf_hash;
-        for (sibling, is_right) in &self.path {
-            let mut data = Vec::with_capacity(64);
-            if *is_right {
-                data.extend_from_slice(&current);
-                data.extend_from_slice(sibling);
-            } else {
-                data.extend_from_slice(sibling);
-                data.extend_from_slice(&current);
-            }
-            current = hasher.hash(&data);
+        for siblings in self.path.chunks(arity - 1) {
+            current = parent_digest(domain, index, current, siblings, hasher);
+            index /= arity as u64;
         }
-        &current == root
+        sized_root(domain, self.leaf_count, self.arity, &current, hasher)
     }
 }
 
+/// Merkle tree of the given [`MerkleArity`] over leaves padded to a power of the arity
+/// with a padding node. The root hashes the top node together with the unpadded leaf
+/// count and the arity.
 #[derive(Debug, Clone)]
 pub struct MerkleTree {
+    domain: MerkleDomain,
+    arity: MerkleArity,
+    leaf_count: usize,
     levels: Vec<Vec<[u8; 32]>>,
+    root: [u8; 32],
 }
 
 impl MerkleTree {
-    pub fn new(data: &[Vec<u8>], hasher: &dyn QuantumHash) -> Self {
-        let mut current: Vec<[u8; 32]> = data.iter().map(|leaf| hasher.hash(leaf)).collect();
-        if current.is_empty() {
-            current.push(hasher.hash(&[]));
-        }
+    /// A binary tree over `data`.
+    pub fn new(domain: MerkleDomain, data: &[Vec<u8>], hasher: &dyn QuantumHash) -> Self {
+        Self::with_arity(domain, data, hasher, MerkleArity::Two)
+    }
 
-        let mut levels = vec![current.clone()];
-        while levels.last().unwrap().len() > 1 {
-            let prev = levels.last().unwrap();
-            let mut next = Vec::with_capacity((prev.len() + 1) / 2);
-            for chunk in prev.chunks(2) {
-                if chunk.len() == 2 {
-                    let mut buf = Vec::with_capacity(64);
-                    buf.extend_from_slice(&chunk[0]);
-                    buf.extend_from_slice(&chunk[1]);
-                    next.push(hasher.hash(&buf));
-                } else {
-                    next.push(chunk[0]);
-                }
-            }
-            levels.push(next);
-        }
+    pub fn with_arity(
+        domain: MerkleDomain,
+        data: &[Vec<u8>],
+        hasher: &dyn QuantumHash,
+        arity: MerkleArity,
+    ) -> Self {
+        let leaves = data
+            .par_iter()
+            .map(|leaf| leaf_digest(domain, leaf, hasher))
+            .collect();
+        Self::from_leaf_hashes(domain, leaves, hasher, arity)
+    }
+
+    fn from_leaf_hashes(
+        domain: MerkleDomain,
+        leaves: Vec<[u8; 32]>,
+        hasher: &dyn QuantumHash,
+        arity: MerkleArity,
+    ) -> Self {
+        let leaf_count = leaves.len();
+        let levels = build_levels(domain, leaves, arity, hasher);
+        let root = sized_root(
+            domain,
+            leaf_count as u64,
+            arity,
+            &levels.last().unwrap()[0],
+            hasher,
+        );
 
-        MerkleTree { levels }
+        MerkleTree {
+            domain,
+            arity,
+            leaf_count,
+            levels,
+            root,
+        }
     }
 
     pub fn root(&self) -> [u8; 32] {
-        self.levels
-            .last()
-            .and_then(|level| level.first().copied())
-            .unwrap_or([0u8; 32])
+        self.root
+    }
+
+    pub fn domain(&self) -> MerkleDomain {
+        self.domain
     }
 
     pub fn leaf_count(&self) -> usize {
-        self.levels.first().map(|lvl| lvl.len()).unwrap_or(0)
+        self.leaf_count
+    }
+
+    pub fn arity(&self) -> MerkleArity {
+        self.arity
     }
 
     pub fn get_proof(&self, index: usize) -> Option<MerkleProof> {
-        if index >= self.levels.first()?.len() {
+        open_leaf(&self.level_slices(), self.leaf_count, self.arity, index)
+    }
+
+    /// Opens every leaf in `indices` (in any order, repeats allowed) with one shared set
+    /// of sibling hashes. `None` if `indices` is empty or out of range.
+    pub fn get_multiproof(&self, indices: &[usize]) -> Option<MerkleMultiProof> {
+        open_leaves(&self.level_slices(), self.leaf_count, self.arity, indices)
+    }
+
+    /// Replaces leaf `index` with `data` and rehashes only its path to the root, which is
+    /// returned. `None` if `index` is out of range.
+    pub fn update_leaf(
+        &mut self,
+        index: usize,
+        data: &[u8],
+        hasher: &dyn QuantumHash,
+    ) -> Option<[u8; 32]> {
+        self.update_leaf_hash(index, leaf_digest(self.domain, data, hasher), hasher)
+    }
+
+    fn update_leaf_hash(
+        &mut self,
+        index: usize,
+        leaf_hash: [u8; 32],
+        hasher: &dyn QuantumHash,
+    ) -> Option<[u8; 32]> {
+        if index >= self.leaf_count {
             return None;
         }
+        let levels = self.levels.iter_mut().map(Vec::as_mut_slice);
+        let top = refresh_path(self.domain, levels, self.arity, index, leaf_hash, hasher);
+        self.root = sized_root(
+            self.domain,
+            self.leaf_count as u64,
+            self.arity,
+            &top,
+            hasher,
+        );
+        Some(self.root)
+    }
 
-        let mut proof_path = Vec::new();
-        let mut idx = index;
-        for level in &self.levels {
-            if level.len() == 1 {
-                break;
-            }
-            let sibling = idx ^ 1;
-            if sibling < level.len() {
-                let sibling_hash = level[sibling];
-                let is_right = sibling > idx;
-                proof_path.push((sibling_hash, is_right));
+    fn level_slices(&self) -> Vec<&[[u8; 32]]> {
+        self.levels.iter().map(Vec::as_slice).collect()
+    }
+}
+
+/// Every level of the tree over `leaves`, from the leaves (padded to a power of the
+/// arity) up to the single top node.
+fn build_levels(
+    domain: MerkleDomain,
+    mut leaves: Vec<[u8; 32]>,
+    arity: MerkleArity,
+    hasher: &dyn QuantumHash,
+) -> Vec<Vec<[u8; 32]>> {
+    let padding = tagged_hash(NodeKind::Padding, domain, &[], hasher);
+    let padded = arity
+        .children()
+        .pow(arity.depth(leaves.len() as u64) as u32);
+    leaves.resize(padded, padding);
+
+    let mut levels = vec![leaves];
+    while levels.last().unwrap().len() > 1 {
+        let next = levels
+            .last()
+            .unwrap()
+            .par_chunks(arity.children())
+            .map(|children| node_digest(domain, children, hasher))
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+/// Stores `leaf_hash` at `index` of the bottom level and rehashes every node above it,
+/// returning the new top node.
+fn refresh_path<'a>(
+    domain: MerkleDomain,
+    levels: impl IntoIterator<Item = &'a mut [[u8; 32]]>,
+    arity: MerkleArity,
+    mut index: usize,
+    leaf_hash: [u8; 32],
+    hasher: &dyn QuantumHash,
+) -> [u8; 32] {
+    let arity = arity.children();
+    let mut node = leaf_hash;
+    for level in levels {
+        level[index] = node;
+        if level.len() > 1 {
+            let first = index - index % arity;
+            node = node_digest(domain, &level[first..first + arity], hasher);
+            index /= arity;
+        }
+    }
+    node
+}
+
+/// Path of leaf `index` through `levels` as laid out by [`build_levels`].
+fn open_leaf(
+    levels: &[&[[u8; 32]]],
+    leaf_count: usize,
+    arity: MerkleArity,
+    index: usize,
+) -> Option<MerkleProof> {
+    if index >= leaf_count {
+        return None;
+    }
+
+    let children = arity.children();
+    let mut proof_path = Vec::new();
+    let mut idx = index;
+    for level in &levels[..levels.len() - 1] {
+        let first = idx - idx % children;
+        proof_path.extend_from_slice(&level[first..idx]);
+        proof_path.extend_from_slice(&level[idx + 1..first + children]);
+        idx /= children;
+    }
+
+    Some(MerkleProof {
+        leaf_index: index as u64,
+        leaf_count: leaf_count as u64,
+        arity,
+        leaf_hash: levels[0][index],
+        path: proof_path,
+    })
+}
+
+/// Multiproof of `indices` through `levels` as laid out by [`build_levels`].
+fn open_leaves(
+    levels: &[&[[u8; 32]]],
+    leaf_count: usize,
+    arity: MerkleArity,
+    indices: &[usize],
+) -> Option<MerkleMultiProof> {
+    let mut known = indices.to_vec();
+    known.sort_unstable();
+    known.dedup();
+    if known.is_empty() || *known.last()? >= leaf_count {
+        return None;
+    }
+    let leaf_indices = known.iter().map(|&index| index as u64).collect();
+
+    let children = arity.children();
+    let mut siblings = Vec::new();
+    for level in &levels[..levels.len() - 1] {
+        let mut parents = Vec::with_capacity(known.len());
+        let mut k = 0;
+        while k < known.len() {
+            let parent = known[k] / children;
+            let first = parent * children;
+            for (child, hash) in (first..).zip(&level[first..first + children]) {
+                if known.get(k) == Some(&child) {
+                    k += 1;
+                } else {
+                    siblings.push(*hash);
+                }
             }
-            idx /= 2;
+            parents.push(parent);
         }
+        known = parents;
+    }
 
-        Some(MerkleProof {
-            leaf_index: index as u64,
-            leaf_hash: self.levels[0][index],
-            path: proof_path,
-        })
+    Some(MerkleMultiProof {
+        leaf_indices,
+        leaf_count: leaf_count as u64,
+        arity,
+        siblings,
+    })
+}
+
+/// Opening of several leaves of one [`MerkleTree`] in which no hash is sent twice. The
+/// leaf hashes themselves are left out: the verifier computes them from the opened
+/// contents, and every node it can derive from those is left out as well.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerkleMultiProof {
+    /// Opened leaves, strictly increasing.
+    pub leaf_indices: Vec<u64>,
+    /// Unpadded size of the tree, which its root commits to.
+    pub leaf_count: u64,
+    /// Arity of the tree, which its root commits to.
+    pub arity: MerkleArity,
+    /// Siblings the verifier cannot derive, level by level from the leaves up and left to
+    /// right within a level.
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl MerkleMultiProof {
+    /// Whether the leaves at `leaf_indices`, hashing to `leaf_hashes`, sit under `root`.
+    pub fn verify(
+        &self,
+        domain: MerkleDomain,
+        root: &[u8; 32],
+        leaf_hashes: &[[u8; 32]],
+        hasher: &dyn QuantumHash,
+    ) -> bool {
+        self.computed_root(domain, leaf_hashes, hasher).as_ref() == Some(root)
+    }
+
+    /// Root of the tree the proof opens, or `None` if it does not have the shape of a
+    /// real opening: indices out of order or range, or siblings missing or left over.
+    fn computed_root(
+        &self,
+        domain: MerkleDomain,
+        leaf_hashes: &[[u8; 32]],
+        hasher: &dyn QuantumHash,
+    ) -> Option<[u8; 32]> {
+        let last = *self.leaf_indices.last()?;
+        if leaf_hashes.len() != self.leaf_indices.len()
+            || self.leaf_count > MAX_LEAF_COUNT
+            || last >= self.leaf_count
+            || self.leaf_indices.windows(2).any(|pair| pair[0] >= pair[1])
+        {
+            return None;
+        }
+
+        let mut level: Vec<(u64, [u8; 32])> = self
+            .leaf_indices
+            .iter()
+            .copied()
+            .zip(leaf_hashes.iter().copied())
+            .collect();
+        let arity = self.arity.children() as u64;
+        let mut siblings = self.siblings.iter();
+        for _ in 0..self.arity.depth(self.leaf_count) {
+            let mut parents = Vec::with_capacity(level.len());
+            let mut k = 0;
+            while k < level.len() {
+                let parent = level[k].0 / arity;
+                let mut children = Vec::with_capacity(arity as usize);
+                for child in parent * arity..(parent + 1) * arity {
+                    match level.get(k) {
+                        Some(&(index, hash)) if index == child => {
+                            children.push(hash);
+                            k += 1;
+                        }
+                        _ => children.push(*siblings.next()?),
+                    }
+                }
+                parents.push((parent, node_digest(domain, &children, hasher)));
+            }
+            level = parents;
+        }
+        if siblings.next().is_some() {
+            return None;
+        }
+        Some(sized_root(
+            domain,
+            self.leaf_count,
+            self.arity,
+            &level[0].1,
+            hasher,
+        ))
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChunkedMerkleProof {
+    /// Leaves per chunk, which maps the opened index to its chunk and offset.
+    pub chunk_size: u64,
     pub chunk_index: u64,
     pub leaf_index_within_chunk: u64,
     pub leaf_proof: MerkleProof,
@@ -113,70 +543,287 @@ pub struct ChunkedMerkleProof {
 }
 
 impl ChunkedMerkleProof {
-    pub fn verify(&self, root: &[u8; 32], hasher: &dyn QuantumHash) -> bool {
-        let chunk_root = compute_merkle_root(&self.leaf_proof, hasher);
-        let expected_chunk_leaf = hasher.hash(chunk_root.as_slice());
-        if self.chunk_proof.leaf_hash != expected_chunk_leaf {
-            debug_merkle("chunk leaf hash mismatch");
-            return false;
+    /// Checks that the proof opens leaf `index` of the `leaf_count`-leaf tree under
+    /// `root`. As for [`ChunkedMerkleMultiProof::verify`], the chunk size is pinned down by
+    /// the chunk count and the chunk sizes, which the roots commit to.
+    pub fn verify(
+        &self,
+        domain: MerkleDomain,
+        root: &[u8; 32],
+        leaf_count: u64,
+        index: u64,
+        hasher: &dyn QuantumHash,
+    ) -> Result<(), MerkleError> {
+        if self.chunk_size == 0
+            || self.chunk_proof.leaf_count != leaf_count.div_ceil(self.chunk_size)
+            || self.chunk_index != index / self.chunk_size
+        {
+            return Err(MerkleError::PathMismatch {
+                level: MerkleLevel::Chunk,
+            });
+        }
+        let chunk_leaf_count = self
+            .chunk_size
+            .min(leaf_count.saturating_sub(self.chunk_index * self.chunk_size));
+        if !self.leaf_proof.well_formed()
+            || self.leaf_index_within_chunk != index % self.chunk_size
+            || self.leaf_proof.leaf_index != self.leaf_index_within_chunk
+            || self.leaf_proof.leaf_count != chunk_leaf_count
+        {
+            return Err(MerkleError::PathMismatch {
+                level: MerkleLevel::Leaf,
+            });
+        }
+        let chunk_root = self.leaf_proof.computed_root(domain, hasher);
+        if self.chunk_proof.leaf_hash != chunk_digest(domain, &chunk_root, hasher) {
+            return Err(MerkleError::PathMismatch {
+                level: MerkleLevel::Leaf,
+            });
+        }
+        if self.chunk_proof.leaf_index != self.chunk_index
+            || !self.chunk_proof.verify(domain, root, hasher)
+        {
+            return Err(MerkleError::PathMismatch {
+                level: MerkleLevel::Chunk,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// [`MerkleMultiProof`]s opening several leaves of a [`ChunkedMerkleTree`]: one inside
+/// each touched chunk, in chunk order, and one over the touched chunks in the top tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkedMerkleMultiProof {
+    pub chunk_size: u64,
+    pub chunk_proofs: Vec<MerkleMultiProof>,
+    pub top_proof: MerkleMultiProof,
+}
+
+impl ChunkedMerkleMultiProof {
+    /// Checks that the proof opens exactly `leaves`, given as `(index, leaf hash)` pairs
+    /// in strictly increasing index order, of the `leaf_count`-leaf tree under `root`.
+    /// Chunk roots are recomputed rather than sent, so a wrong leaf hash surfaces as a
+    /// [`MerkleLevel::Chunk`] mismatch.
+    pub fn verify(
+        &self,
+        domain: MerkleDomain,
+        root: &[u8; 32],
+        leaf_count: u64,
+        leaves: &[(u64, [u8; 32])],
+        hasher: &dyn QuantumHash,
+    ) -> Result<(), MerkleError> {
+        let leaf_error = MerkleError::PathMismatch {
+            level: MerkleLevel::Leaf,
+        };
+        let chunk_error = MerkleError::PathMismatch {
+            level: MerkleLevel::Chunk,
+        };
+        // The chunk size only maps indices to chunks; it is pinned down by the chunk
+        // count and the chunk sizes, which the roots commit to.
+        if self.chunk_size == 0 || self.top_proof.leaf_count != leaf_count.div_ceil(self.chunk_size)
+        {
+            return Err(chunk_error);
+        }
+        if leaves.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(leaf_error);
+        }
+
+        let mut chunk_leaves: Vec<(u64, Vec<u64>, Vec<[u8; 32]>)> = Vec::new();
+        for &(index, hash) in leaves {
+            let chunk = index / self.chunk_size;
+            match chunk_leaves.last_mut() {
+                Some((current, offsets, hashes)) if *current == chunk => {
+                    offsets.push(index % self.chunk_size);
+                    hashes.push(hash);
+                }
+                _ => chunk_leaves.push((chunk, vec![index % self.chunk_size], vec![hash])),
+            }
+        }
+        if chunk_leaves.len() != self.chunk_proofs.len()
+            || chunk_leaves.iter().map(|(chunk, _, _)| *chunk).ne(self
+                .top_proof
+                .leaf_indices
+                .iter()
+                .copied())
+        {
+            return Err(chunk_error);
         }
-        if !self.chunk_proof.verify(root, hasher) {
-            debug_merkle("chunk proof path mismatch");
-            return false;
+
+        let mut chunk_digests = Vec::with_capacity(chunk_leaves.len());
+        for ((chunk, offsets, hashes), proof) in chunk_leaves.iter().zip(&self.chunk_proofs) {
+            let chunk_leaf_count = self
+                .chunk_size
+                .min(leaf_count.saturating_sub(chunk * self.chunk_size));
+            if proof.leaf_count != chunk_leaf_count || &proof.leaf_indices != offsets {
+                return Err(leaf_error);
+            }
+            let chunk_root = proof
+                .computed_root(domain, hashes, hasher)
+                .ok_or(leaf_error.clone())?;
+            chunk_digests.push(chunk_digest(domain, &chunk_root, hasher));
+        }
+        if !self.top_proof.verify(domain, root, &chunk_digests, hasher) {
+            return Err(chunk_error);
+        }
+        Ok(())
+    }
+
+    /// Number of sibling hashes the proof carries.
+    pub fn hash_count(&self) -> usize {
+        self.top_proof.siblings.len()
+            + self
+                .chunk_proofs
+                .iter()
+                .map(|proof| proof.siblings.len())
+                .sum::<usize>()
+    }
+}
+
+/// Level of a [`ChunkedMerkleProof`] that failed to authenticate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MerkleLevel {
+    /// The path from the leaf to its chunk root, or to the root of an unchunked tree.
+    Leaf,
+    /// The path from the chunk root to the commitment.
+    Chunk,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MerkleError {
+    /// The proof's leaf hash does not commit to the opened contents.
+    LeafHashMismatch,
+    /// The opening came with neither its own proof nor a batched one.
+    MissingProof,
+    PathMismatch {
+        level: MerkleLevel,
+    },
+}
+
+impl fmt::Display for MerkleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MerkleError::LeafHashMismatch => write!(f, "leaf hash does not match the opening"),
+            MerkleError::MissingProof => write!(f, "opening carries no Merkle proof"),
+            MerkleError::PathMismatch {
+                level: MerkleLevel::Leaf,
+            } => write!(f, "leaf path does not reach the chunk root"),
+            MerkleError::PathMismatch {
+                level: MerkleLevel::Chunk,
+            } => write!(f, "chunk path does not reach the committed root"),
         }
-        true
     }
 }
 
+impl std::error::Error for MerkleError {}
+
+/// Chunks hashed per worker thread before a batch of chunk subtrees is written out.
+const CHUNKS_PER_THREAD: usize = 4;
+
 #[derive(Debug, Clone)]
 pub struct ChunkedMerkleTree {
     chunk_size: usize,
-    chunk_trees: Vec<MerkleTree>,
-    top_tree: MerkleTree,
+    arity: MerkleArity,
     leaf_count: usize,
-    cache: RefCell<HashMap<usize, ChunkedMerkleProof>>,
-    cache_order: RefCell<VecDeque<usize>>,
-    cache_capacity: usize,
+    /// Levels of every chunk subtree, chunk after chunk, as laid out by [`build_levels`].
+    chunk_levels: Arc<dyn LevelStore>,
+    /// Nodes of a subtree over a full chunk; chunk `c` starts at `c * chunk_nodes`.
+    chunk_nodes: usize,
+    top_tree: MerkleTree,
+    cache: ProofCache,
 }
 
 impl ChunkedMerkleTree {
-    pub fn new(data: &[Vec<u8>], hasher: &dyn QuantumHash, chunk_size: usize) -> Self {
+    /// Splits `data` into binary subtrees of `chunk_size` leaves, whose roots become the
+    /// (chunk-tagged) leaves of a binary top tree.
+    pub fn new(
+        domain: MerkleDomain,
+        data: &[Vec<u8>],
+        hasher: &dyn QuantumHash,
+        chunk_size: usize,
+    ) -> Self {
+        Self::with_arity(domain, data, hasher, chunk_size, MerkleArity::Two)
+    }
+
+    /// Like [`Self::new`], with every subtree and the top tree of the given arity.
+    pub fn with_arity(
+        domain: MerkleDomain,
+        data: &[Vec<u8>],
+        hasher: &dyn QuantumHash,
+        chunk_size: usize,
+        arity: MerkleArity,
+    ) -> Self {
+        Self::from_fn(
+            domain,
+            data.len(),
+            |index| &data[index],
+            hasher,
+            chunk_size,
+            arity,
+            &MerkleStorage::Memory,
+        )
+        .expect("in-memory Merkle storage does not fail")
+    }
+
+    /// Commits to `leaf_count` leaves produced by `leaf` on demand. Chunks are hashed a
+    /// batch at a time and their levels handed to `storage`, so only one batch of leaves
+    /// is ever held in memory.
+    pub fn from_fn<L: AsRef<[u8]>>(
+        domain: MerkleDomain,
+        leaf_count: usize,
+        leaf: impl Fn(usize) -> L + Sync,
+        hasher: &dyn QuantumHash,
+        chunk_size: usize,
+        arity: MerkleArity,
+        storage: &MerkleStorage,
+    ) -> io::Result<Self> {
         let chunk_size = chunk_size.max(1);
-        let cache_capacity = DEFAULT_CACHE_SIZE;
-
-        if data.is_empty() {
-            let empty_tree = MerkleTree::new(&[Vec::new()], hasher);
-            return ChunkedMerkleTree {
-                chunk_size,
-                chunk_trees: vec![empty_tree.clone()],
-                top_tree: empty_tree,
-                leaf_count: 0,
-                cache: RefCell::new(HashMap::new()),
-                cache_order: RefCell::new(VecDeque::new()),
-                cache_capacity,
-            };
+        let chunk_count = leaf_count.div_ceil(chunk_size);
+        let batch = rayon::current_num_threads() * CHUNKS_PER_THREAD;
+        let mut writer = storage.writer()?;
+        let mut top_leaves = Vec::with_capacity(chunk_count);
+        for first in (0..chunk_count).step_by(batch) {
+            let built: Vec<(Vec<[u8; 32]>, [u8; 32])> = (first..chunk_count.min(first + batch))
+                .into_par_iter()
+                .map(|chunk| {
+                    let leaves = chunk * chunk_size..leaf_count.min((chunk + 1) * chunk_size);
+                    let chunk_leaf_count = leaves.len() as u64;
+                    let hashes = leaves
+                        .into_par_iter()
+                        .map(|index| leaf_digest(domain, leaf(index).as_ref(), hasher))
+                        .collect();
+                    let levels = build_levels(domain, hashes, arity, hasher);
+                    let top = levels.last().unwrap()[0];
+                    let root = sized_root(domain, chunk_leaf_count, arity, &top, hasher);
+                    (levels.concat(), chunk_digest(domain, &root, hasher))
+                })
+                .collect();
+            for (nodes, digest) in built {
+                writer.push(&nodes)?;
+                top_leaves.push(digest);
+            }
         }
 
-        let mut chunk_roots = Vec::new();
-        let mut chunk_trees = Vec::new();
-        for chunk in data.chunks(chunk_size) {
-            let tree = MerkleTree::new(chunk, hasher);
-            chunk_roots.push(tree.root());
-            chunk_trees.push(tree);
-        }
+        Ok(ChunkedMerkleTree {
+            chunk_size,
+            arity,
+            leaf_count,
+            chunk_levels: writer.finish()?,
+            chunk_nodes: level_lengths(chunk_size.min(leaf_count), arity).sum(),
+            top_tree: MerkleTree::from_leaf_hashes(domain, top_leaves, hasher, arity),
+            cache: ProofCache::new(DEFAULT_CACHE_CAPACITY),
+        })
+    }
 
-        let top_leaves: Vec<Vec<u8>> = chunk_roots.iter().map(|root| root.to_vec()).collect();
-        let top_tree = MerkleTree::new(&top_leaves, hasher);
+    /// Keeps up to `capacity` single-leaf proofs (least recently used evicted first);
+    /// `0` disables caching. Replaces any proofs cached so far.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache = ProofCache::new(capacity);
+        self
+    }
 
-        ChunkedMerkleTree {
-            chunk_size,
-            chunk_trees,
-            top_tree,
-            leaf_count: data.len(),
-            cache: RefCell::new(HashMap::new()),
-            cache_order: RefCell::new(VecDeque::new()),
-            cache_capacity,
-        }
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
     }
 
     pub fn root(&self) -> [u8; 32] {
@@ -187,108 +834,343 @@ impl ChunkedMerkleTree {
         self.leaf_count
     }
 
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn arity(&self) -> MerkleArity {
+        self.arity
+    }
+
     pub fn get_proof(&self, index: usize) -> Option<ChunkedMerkleProof> {
         if index >= self.leaf_count {
             return None;
         }
 
-        if let Some(proof) = self.cache.borrow().get(&index) {
-            return Some(proof.clone());
+        if let Some(proof) = self.cache.get(index) {
+            return Some(proof);
         }
 
         let chunk_index = index / self.chunk_size;
         let offset = index % self.chunk_size;
-        let leaf_tree = self.chunk_trees.get(chunk_index)?;
-        let leaf_proof = leaf_tree.get_proof(offset)?;
+        let leaf_proof = open_leaf(
+            &self.levels_of_chunk(chunk_index),
+            self.chunk_leaf_count(chunk_index),
+            self.arity,
+            offset,
+        )?;
         let chunk_proof = self.top_tree.get_proof(chunk_index)?;
 
         let proof = ChunkedMerkleProof {
+            chunk_size: self.chunk_size as u64,
             chunk_index: chunk_index as u64,
             leaf_index_within_chunk: offset as u64,
             leaf_proof,
             chunk_proof,
         };
-        self.insert_cache(index, proof.clone());
+        self.cache.insert(index, proof.clone());
         Some(proof)
     }
 
-    fn insert_cache(&self, index: usize, proof: ChunkedMerkleProof) {
-        let mut cache = self.cache.borrow_mut();
-        let mut order = self.cache_order.borrow_mut();
-        if cache.contains_key(&index) {
-            return;
+    /// Opens every leaf in `indices` (in any order, repeats allowed) with sibling hashes
+    /// shared across the leaves of each chunk and across the chunks.
+    pub fn get_multiproof(&self, indices: &[usize]) -> Option<ChunkedMerkleMultiProof> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if indices.is_empty() || *indices.last()? >= self.leaf_count {
+            return None;
         }
-        cache.insert(index, proof);
-        order.push_back(index);
-        if cache.len() > self.cache_capacity {
-            if let Some(oldest) = order.pop_front() {
-                cache.remove(&oldest);
+
+        let mut chunks: Vec<(usize, Vec<usize>)> = Vec::new();
+        for index in indices {
+            let chunk = index / self.chunk_size;
+            match chunks.last_mut() {
+                Some((current, offsets)) if *current == chunk => {
+                    offsets.push(index % self.chunk_size)
+                }
+                _ => chunks.push((chunk, vec![index % self.chunk_size])),
             }
         }
+        let chunk_proofs = chunks
+            .iter()
+            .map(|(chunk, offsets)| {
+                open_leaves(
+                    &self.levels_of_chunk(*chunk),
+                    self.chunk_leaf_count(*chunk),
+                    self.arity,
+                    offsets,
+                )
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let touched: Vec<usize> = chunks.iter().map(|(chunk, _)| *chunk).collect();
+
+        Some(ChunkedMerkleMultiProof {
+            chunk_size: self.chunk_size as u64,
+            chunk_proofs,
+            top_proof: self.top_tree.get_multiproof(&touched)?,
+        })
     }
-}
 
-fn debug_merkle(msg: &str) {
-    if env::var("ZKP_DEBUG_SPOT").is_ok() {
-        eprintln!("{}", msg);
+    /// Replaces leaf `index` with `data`, rehashing its path within the chunk and the
+    /// chunk's path in the top tree, and returns the new root. Cached proofs are dropped.
+    /// A tree sharing its levels with a clone first takes its own in-memory copy.
+    pub fn update_leaf(
+        &mut self,
+        index: usize,
+        data: &[u8],
+        hasher: &dyn QuantumHash,
+    ) -> Option<[u8; 32]> {
+        if index >= self.leaf_count {
+            return None;
+        }
+        let domain = self.top_tree.domain();
+        let chunk = index / self.chunk_size;
+        let chunk_leaf_count = self.chunk_leaf_count(chunk);
+        let range = self.chunk_range(chunk);
+        if Arc::get_mut(&mut self.chunk_levels).is_none() {
+            let copy = self.chunk_levels.nodes(0..self.node_count()).to_vec();
+            self.chunk_levels = Arc::new(copy);
+        }
+        let mut rest = Arc::get_mut(&mut self.chunk_levels)
+            .expect("levels are not shared")
+            .nodes_mut(range);
+        let levels = level_lengths(chunk_leaf_count, self.arity).map(|len| {
+            let (level, tail) = std::mem::take(&mut rest).split_at_mut(len);
+            rest = tail;
+            level
+        });
+        let leaf_hash = leaf_digest(domain, data, hasher);
+        let top = refresh_path(
+            domain,
+            levels,
+            self.arity,
+            index % self.chunk_size,
+            leaf_hash,
+            hasher,
+        );
+        let chunk_root = sized_root(domain, chunk_leaf_count as u64, self.arity, &top, hasher);
+        self.cache.clear();
+        self.top_tree
+            .update_leaf_hash(chunk, chunk_digest(domain, &chunk_root, hasher), hasher)
     }
-}
 
-fn compute_merkle_root(proof: &MerkleProof, hasher: &dyn QuantumHash) -> [u8; 32] {
-    let mut current = proof.leaf_hash;
-    for (sibling, is_right) in &proof.path {
-        let mut data = Vec::with_capacity(64);
-        if *is_right {
-            data.extend_from_slice(&current);
-            data.extend_from_slice(sibling);
-        } else {
-            data.extend_from_slice(sibling);
-            data.extend_from_slice(&current);
+    fn chunk_leaf_count(&self, chunk: usize) -> usize {
+        self.chunk_size
+            .min(self.leaf_count.saturating_sub(chunk * self.chunk_size))
+    }
+
+    /// Positions of the levels of chunk `chunk` in the level store.
+    fn chunk_range(&self, chunk: usize) -> Range<usize> {
+        let start = chunk * self.chunk_nodes;
+        start..start + level_lengths(self.chunk_leaf_count(chunk), self.arity).sum::<usize>()
+    }
+
+    fn node_count(&self) -> usize {
+        match self.leaf_count.div_ceil(self.chunk_size) {
+            0 => 0,
+            chunks => self.chunk_range(chunks - 1).end,
         }
-        current = hasher.hash(&data);
     }
-    current
+
+    fn levels_of_chunk(&self, chunk: usize) -> Vec<&[[u8; 32]]> {
+        let mut start = chunk * self.chunk_nodes;
+        level_lengths(self.chunk_leaf_count(chunk), self.arity)
+            .map(|len| {
+                start += len;
+                self.chunk_levels.nodes(start - len..start)
+            })
+            .collect()
+    }
+}
+
+/// Lengths of the levels [`build_levels`] produces for `leaf_count` leaves.
+fn level_lengths(leaf_count: usize, arity: MerkleArity) -> impl Iterator<Item = usize> {
+    let depth = arity.depth(leaf_count as u64) as u32;
+    (0..=depth).map(move |level| arity.children().pow(depth - level))
 }
 
 #[derive(Debug, Clone)]
 pub struct GraphMerkleTree {
     chunked: ChunkedMerkleTree,
-    edge_to_index: HashMap<(u32, u32), usize>,
+    n: u32,
 }
 
 impl GraphMerkleTree {
-    pub fn from_graph(graph: &Graph, hasher: &dyn QuantumHash) -> Self {
-        let mut data = Vec::with_capacity((graph.n as usize).pow(2));
-        let mut edge_to_index = HashMap::new();
-        let mut idx = 0;
+    /// Commits to every edge of `graph` in row-major order. Each leaf carries separately
+    /// salted digests of the color and of the blank bit, so either field can be opened
+    /// without revealing the other. The blank digest and the matching blank-tree leaf
+    /// commit to the same bit under the same salt when both use the same `blank_salts`.
+    pub fn from_graph(
+        graph: &Graph,
+        color_salts: &[LeafSalt],
+        blank_salts: &[LeafSalt],
+        hasher: &dyn QuantumHash,
+    ) -> Self {
+        Self::with_layout(
+            graph,
+            color_salts,
+            blank_salts,
+            hasher,
+            DEFAULT_CHUNK_SIZE,
+            MerkleArity::Two,
+        )
+    }
 
-        for i in 0..graph.n {
-            for j in 0..graph.n {
-                let color = graph.get_edge(i, j);
-                let mut serialized = Vec::with_capacity(9);
-                serialized.extend_from_slice(&i.to_be_bytes());
-                serialized.extend_from_slice(&j.to_be_bytes());
-                serialized.push(color.to_u8());
-                data.push(serialized);
-                edge_to_index.insert((i, j), idx);
-                idx += 1;
-            }
-        }
+    /// Like [`Self::from_graph`], with chunks of `chunk_size` edges and trees of the given
+    /// arity. A chunk size of `graph.n` puts each adjacency row in its own chunk, so all
+    /// edges leaving one node share a chunk path.
+    pub fn with_layout(
+        graph: &Graph,
+        color_salts: &[LeafSalt],
+        blank_salts: &[LeafSalt],
+        hasher: &dyn QuantumHash,
+        chunk_size: usize,
+        arity: MerkleArity,
+    ) -> Self {
+        Self::with_storage(
+            graph,
+            color_salts,
+            blank_salts,
+            hasher,
+            chunk_size,
+            arity,
+            &MerkleStorage::Memory,
+        )
+        .expect("in-memory Merkle storage does not fail")
+    }
+
+    /// Like [`Self::with_layout`], keeping the chunk levels in `storage`. Leaves are
+    /// encoded from the adjacency rows a chunk at a time and never held all at once.
+    pub fn with_storage(
+        graph: &Graph,
+        color_salts: &[LeafSalt],
+        blank_salts: &[LeafSalt],
+        hasher: &dyn QuantumHash,
+        chunk_size: usize,
+        arity: MerkleArity,
+        storage: &MerkleStorage,
+    ) -> io::Result<Self> {
+        let n = graph.n as usize;
+        let edge_count = n * n;
+        assert_eq!(color_salts.len(), edge_count, "one color salt per edge");
+        assert_eq!(blank_salts.len(), edge_count, "one blank salt per edge");
 
-        let chunked = ChunkedMerkleTree::new(&data, hasher, DEFAULT_CHUNK_SIZE);
+        let chunked = ChunkedMerkleTree::from_fn(
+            MerkleDomain::Graph,
+            edge_count,
+            |idx| {
+                let (i, j) = ((idx / n) as u32, (idx % n) as u32);
+                let color = graph.get_edge(i, j);
+                salted_edge_leaf(i, j, color, &color_salts[idx], &blank_salts[idx], hasher)
+            },
+            hasher,
+            chunk_size,
+            arity,
+            storage,
+        )?;
 
-        GraphMerkleTree {
+        Ok(GraphMerkleTree {
             chunked,
-            edge_to_index,
-        }
+            n: graph.n,
+        })
+    }
+
+    /// See [`ChunkedMerkleTree::with_cache_capacity`].
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.chunked = self.chunked.with_cache_capacity(capacity);
+        self
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.chunked.cache_stats()
     }
 
     pub fn root(&self) -> [u8; 32] {
         self.chunked.root()
     }
 
+    /// Row-major leaf index of edge `(from, to)`, if both endpoints are nodes.
+    pub fn edge_index(&self, from: u32, to: u32) -> Option<usize> {
+        (from < self.n && to < self.n).then(|| from as usize * self.n as usize + to as usize)
+    }
+
+    /// Recommits edge `(from, to)` to `color` under the given salts and returns the new
+    /// root. `None` if either endpoint is not a node.
+    pub fn update_edge(
+        &mut self,
+        from: u32,
+        to: u32,
+        color: Color,
+        color_salt: &LeafSalt,
+        blank_salt: &LeafSalt,
+        hasher: &dyn QuantumHash,
+    ) -> Option<[u8; 32]> {
+        let index = self.edge_index(from, to)?;
+        let leaf = salted_edge_leaf(from, to, color, color_salt, blank_salt, hasher);
+        self.chunked.update_leaf(index, &leaf, hasher)
+    }
+
     pub fn get_edge_proof(&self, from: u32, to: u32) -> Option<ChunkedMerkleProof> {
-        let index = *self.edge_to_index.get(&(from, to))?;
-        self.chunked.get_proof(index)
+        self.chunked.get_proof(self.edge_index(from, to)?)
+    }
+
+    /// One [`ChunkedMerkleMultiProof`] opening all of `edges`.
+    pub fn get_edges_multiproof(&self, edges: &[(u32, u32)]) -> Option<ChunkedMerkleMultiProof> {
+        let indices = edges
+            .iter()
+            .map(|&(from, to)| self.edge_index(from, to))
+            .collect::<Option<Vec<_>>>()?;
+        self.chunked.get_multiproof(&indices)
     }
 }
+
+/// Hiding digest `H(salt || value)` of a single-byte edge field (color or blank bit).
+pub fn edge_field_digest(value: u8, salt: &LeafSalt, hasher: &dyn QuantumHash) -> [u8; 32] {
+    hasher.hash_with_salt(&[value], salt)
+}
+
+/// Graph-tree leaf of edge `(from, to)` with its color and blank bit hidden under their
+/// salts.
+fn salted_edge_leaf(
+    from: u32,
+    to: u32,
+    color: Color,
+    color_salt: &LeafSalt,
+    blank_salt: &LeafSalt,
+    hasher: &dyn QuantumHash,
+) -> Vec<u8> {
+    let color_digest = edge_field_digest(color.to_u8(), color_salt, hasher);
+    let blank_digest = edge_field_digest((color == Color::Blank) as u8, blank_salt, hasher);
+    encode_edge_leaf(from, to, &color_digest, &blank_digest)
+}
+
+/// Blank-tree leaf of edge `index` (`from * n + to`) with blank bit `is_blank`, before it
+/// is salted.
+pub fn encode_blank_leaf(index: u64, is_blank: u8) -> Vec<u8> {
+    let mut serialized = Vec::with_capacity(9);
+    serialized.extend_from_slice(&index.to_be_bytes());
+    serialized.push(is_blank);
+    serialized
+}
+
+pub fn encode_edge_leaf(
+    from: u32,
+    to: u32,
+    color_digest: &[u8; 32],
+    blank_digest: &[u8; 32],
+) -> Vec<u8> {
+    let mut serialized = Vec::with_capacity(72);
+    serialized.extend_from_slice(&from.to_be_bytes());
+    serialized.extend_from_slice(&to.to_be_bytes());
+    serialized.extend_from_slice(color_digest);
+    serialized.extend_from_slice(blank_digest);
+    serialized
+}
+
+pub fn encode_permutation_leaf(index: u32, value: u32) -> Vec<u8> {
+    let mut serialized = Vec::with_capacity(8);
+    serialized.extend_from_slice(&index.to_be_bytes());
+    serialized.extend_from_slice(&value.to_be_bytes());
+    serialized
+}
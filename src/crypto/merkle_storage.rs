@@ -0,0 +1,152 @@
+use memmap2::MmapMut;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Node hashes of Merkle trees, addressed by their position in the order they were
+/// written.
+pub trait LevelStore: fmt::Debug + Send + Sync {
+    fn nodes(&self, range: Range<usize>) -> &[[u8; 32]];
+
+    /// The same nodes for in-place updates.
+    fn nodes_mut(&mut self, range: Range<usize>) -> &mut [[u8; 32]];
+}
+
+impl LevelStore for Vec<[u8; 32]> {
+    fn nodes(&self, range: Range<usize>) -> &[[u8; 32]] {
+        &self[range]
+    }
+
+    fn nodes_mut(&mut self, range: Range<usize>) -> &mut [[u8; 32]] {
+        &mut self[range]
+    }
+}
+
+/// Nodes written to a file and mapped back. Updates go straight to the mapped pages;
+/// the file is removed once the store is dropped.
+#[derive(Debug)]
+pub struct MmapLevelStore {
+    map: Option<MmapMut>,
+    path: PathBuf,
+}
+
+impl LevelStore for MmapLevelStore {
+    fn nodes(&self, range: Range<usize>) -> &[[u8; 32]] {
+        let bytes = self.map.as_deref().unwrap_or_default();
+        bytes[range.start * 32..range.end * 32].as_chunks::<32>().0
+    }
+
+    fn nodes_mut(&mut self, range: Range<usize>) -> &mut [[u8; 32]] {
+        let bytes = self.map.as_deref_mut().unwrap_or_default();
+        bytes[range.start * 32..range.end * 32]
+            .as_chunks_mut::<32>()
+            .0
+    }
+}
+
+impl Drop for MmapLevelStore {
+    fn drop(&mut self) {
+        // Unmap first: some platforms refuse to delete a mapped file.
+        self.map = None;
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Where [`crate::crypto::merkle::ChunkedMerkleTree`]s keep the levels of their chunk
+/// subtrees. The top tree over the chunk roots always stays in memory, as does
+/// everything outside the trees.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MerkleStorage {
+    #[default]
+    Memory,
+    /// One memory-mapped file per tree in this directory, so only the pages proofs
+    /// touch need to be resident.
+    Disk(PathBuf),
+}
+
+impl MerkleStorage {
+    pub fn writer(&self) -> io::Result<LevelWriter> {
+        static NEXT_FILE: AtomicU64 = AtomicU64::new(0);
+        let sink = match self {
+            MerkleStorage::Memory => Sink::Memory(Vec::new()),
+            MerkleStorage::Disk(dir) => {
+                let path = dir.join(format!(
+                    "merkle-{}-{}.levels",
+                    std::process::id(),
+                    NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+                ));
+                // Opened for reading too, since the finished file is mapped back.
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)?;
+                let file = BufWriter::new(file);
+                Sink::Disk { file, path }
+            }
+        };
+        Ok(LevelWriter {
+            sink: Some(sink),
+            written: 0,
+        })
+    }
+}
+
+/// Appends nodes to a [`LevelStore`] under construction.
+pub struct LevelWriter {
+    sink: Option<Sink>,
+    written: usize,
+}
+
+enum Sink {
+    Memory(Vec<[u8; 32]>),
+    Disk {
+        file: BufWriter<File>,
+        path: PathBuf,
+    },
+}
+
+impl LevelWriter {
+    /// Appends `nodes`, returning the position of the first one.
+    pub fn push(&mut self, nodes: &[[u8; 32]]) -> io::Result<usize> {
+        match self.sink.as_mut().expect("writer is not finished") {
+            Sink::Memory(store) => store.extend_from_slice(nodes),
+            Sink::Disk { file, .. } => file.write_all(nodes.as_flattened())?,
+        }
+        let start = self.written;
+        self.written += nodes.len();
+        Ok(start)
+    }
+
+    pub fn finish(mut self) -> io::Result<Arc<dyn LevelStore>> {
+        match self.sink.take().expect("writer is not finished") {
+            Sink::Memory(store) => Ok(Arc::new(store)),
+            Sink::Disk { file, path } => {
+                // From here on the store owns the file and removes it when dropped.
+                let mut store = MmapLevelStore { map: None, path };
+                let file = file.into_inner().map_err(io::IntoInnerError::into_error)?;
+                // An empty file cannot be mapped; it holds no nodes to read either.
+                if self.written > 0 {
+                    // SAFETY: the file was created by this writer under a unique name and
+                    // is only ever accessed through this mapping from now on.
+                    store.map = Some(unsafe { MmapMut::map_mut(&file)? });
+                }
+                Ok(Arc::new(store))
+            }
+        }
+    }
+}
+
+/// A writer abandoned before [`LevelWriter::finish`] removes its file.
+impl Drop for LevelWriter {
+    fn drop(&mut self) {
+        if let Some(Sink::Disk { path, .. }) = &self.sink {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
@@ -1,7 +1,17 @@
 pub mod hash;
 pub mod merkle;
+pub mod merkle_storage;
 pub mod polynomial;
+pub mod proof_cache;
 
-pub use hash::{default_quantum_hash, Blake3QuantumHash, QuantumHash, Sha3QuantumHash};
-pub use merkle::{ChunkedMerkleProof, ChunkedMerkleTree, GraphMerkleTree, MerkleProof, MerkleTree};
+pub use hash::{
+    default_quantum_hash, random_salts, salted_leaf, Blake3QuantumHash, HashBackend, LeafSalt,
+    QuantumHash, Sha3QuantumHash, Shake256QuantumHash,
+};
+pub use merkle::{
+    ChunkedMerkleProof, ChunkedMerkleTree, GraphMerkleTree, MerkleArity, MerkleDomain, MerkleError,
+    MerkleLevel, MerkleProof, MerkleTree,
+};
+pub use merkle_storage::{LevelStore, MerkleStorage};
 pub use polynomial::{BlankPolynomial, PolynomialCommitment};
+pub use proof_cache::{CacheStats, ProofCache, DEFAULT_CACHE_CAPACITY};
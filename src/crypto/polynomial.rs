@@ -26,6 +26,10 @@ impl BlankPolynomial {
         self.values.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
     pub fn evaluate(&self, index: usize) -> u8 {
         self.values.get(index).copied().unwrap_or(0)
     }
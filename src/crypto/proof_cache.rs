@@ -0,0 +1,176 @@
+use crate::crypto::merkle::ChunkedMerkleProof;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Add;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Proofs a [`crate::crypto::merkle::ChunkedMerkleTree`] keeps by default.
+pub const DEFAULT_CACHE_CAPACITY: usize = 2048;
+const SHARDS: usize = 16;
+
+/// Least-recently-used cache of chunked Merkle proofs, keyed by leaf index. Leaves are
+/// spread over independently locked shards, so concurrent lookups of different leaves
+/// rarely contend, and each shard evicts its own least recently used proof. The capacity
+/// is split exactly between the shards, with fewer shards than `SHARDS` when it is
+/// smaller, so the cache never holds more than `capacity` proofs.
+#[derive(Debug)]
+pub struct ProofCache {
+    capacity: usize,
+    shards: Vec<Mutex<Shard>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Shard {
+    entries: HashMap<usize, (ChunkedMerkleProof, u64)>,
+    /// Leaf index by last use; the first entry is evicted next.
+    recency: BTreeMap<u64, usize>,
+    clock: u64,
+}
+
+impl Shard {
+    fn touch(&mut self, index: usize) -> u64 {
+        self.clock += 1;
+        self.recency.insert(self.clock, index);
+        self.clock
+    }
+}
+
+impl ProofCache {
+    /// A cache holding up to `capacity` proofs; `0` disables caching.
+    pub fn new(capacity: usize) -> Self {
+        ProofCache {
+            capacity,
+            shards: (0..capacity.clamp(1, SHARDS))
+                .map(|_| Mutex::default())
+                .collect(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn get(&self, index: usize) -> Option<ChunkedMerkleProof> {
+        let mut shard = self.shard(index);
+        let Some(last_used) = shard.entries.get(&index).map(|(_, last_used)| *last_used) else {
+            drop(shard);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        shard.recency.remove(&last_used);
+        let now = shard.touch(index);
+        let (proof, last_used) = shard.entries.get_mut(&index).expect("entry present");
+        *last_used = now;
+        let proof = proof.clone();
+        drop(shard);
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(proof)
+    }
+
+    pub fn insert(&self, index: usize, proof: ChunkedMerkleProof) {
+        let shards = self.shards.len();
+        let shard_capacity =
+            self.capacity / shards + usize::from(index % shards < self.capacity % shards);
+        if shard_capacity == 0 {
+            return;
+        }
+        let mut shard = self.shard(index);
+        if shard.entries.contains_key(&index) {
+            return;
+        }
+        if shard.entries.len() >= shard_capacity {
+            if let Some((_, oldest)) = shard.recency.pop_first() {
+                shard.entries.remove(&oldest);
+            }
+        }
+        let now = shard.touch(index);
+        shard.entries.insert(index, (proof, now));
+    }
+
+    /// Drops every cached proof, keeping the hit and miss counts.
+    pub fn clear(&self) {
+        for shard in 0..self.shards.len() {
+            let mut shard = self.shard(shard);
+            shard.entries.clear();
+            shard.recency.clear();
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: (0..self.shards.len())
+                .map(|shard| self.shard(shard).entries.len())
+                .sum(),
+            capacity: self.capacity,
+        }
+    }
+
+    fn shard(&self, index: usize) -> std::sync::MutexGuard<'_, Shard> {
+        self.shards[index % self.shards.len()]
+            .lock()
+            .expect("proof cache shard lock is not poisoned")
+    }
+}
+
+/// Cloning starts an empty cache of the same capacity.
+impl Clone for ProofCache {
+    fn clone(&self) -> Self {
+        ProofCache::new(self.capacity)
+    }
+}
+
+/// Lookups served by a [`ProofCache`] since it was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    /// Share of lookups answered from the cache, or 0 before the first lookup.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// Totals over several caches.
+impl Add for CacheStats {
+    type Output = CacheStats;
+
+    fn add(self, other: CacheStats) -> CacheStats {
+        CacheStats {
+            hits: self.hits + other.hits,
+            misses: self.misses + other.misses,
+            entries: self.entries + other.entries,
+            capacity: self.capacity + other.capacity,
+        }
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses ({:.1}% hit rate), {}/{} proofs cached",
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0,
+            self.entries,
+            self.capacity
+        )
+    }
+}
@@ -1,7 +1,6 @@
 use crate::graph::{Color, Graph, Spot};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::env;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColorationSet {
@@ -32,7 +31,9 @@ impl ColorationSet {
         }
     }
 
-    fn spot_to_key(spot: &Spot) -> [u8; 9] {
+    /// Canonical pattern of `spot`: the lexicographically smallest color encoding over all
+    /// relabellings of its three nodes.
+    pub fn spot_to_key(spot: &Spot) -> [u8; 9] {
         let permutations = [
             [spot.nodes[0], spot.nodes[1], spot.nodes[2]],
             [spot.nodes[0], spot.nodes[2], spot.nodes[1]],
@@ -65,12 +66,7 @@ impl ColorationSet {
     }
 
     pub fn contains(&self, spot: &Spot) -> bool {
-        let key = Self::spot_to_key(spot);
-        if !self.allowed_spots.contains(&key) {
-            Self::debug_missing(&key);
-            return false;
-        }
-        true
+        self.allowed_spots.contains(&Self::spot_to_key(spot))
     }
 
     pub fn blank_limit(&self) -> u32 {
@@ -91,11 +87,3 @@ impl ColorationSet {
         keys
     }
 }
-
-impl ColorationSet {
-    fn debug_missing(key: &[u8; 9]) {
-        if env::var("ZKP_DEBUG_SPOT").is_ok() {
-            eprintln!("missing coloration pattern: {:?}", key);
-        }
-    }
-}
@@ -1,4 +1,5 @@
 use crate::crypto::hash::QuantumHash;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -90,17 +91,21 @@ impl Graph {
 
     pub fn apply_permutation(&self, permutation: &[u32]) -> Self {
         assert_eq!(permutation.len() as u32, self.n);
-        let mut permuted = Graph::new(self.n);
-
-        for i in 0..self.n {
-            for j in 0..self.n {
-                let src_i = permutation[i as usize];
-                let src_j = permutation[j as usize];
-                let color = self.get_edge(src_i, src_j);
-                permuted.set_edge(i, j, color);
-            }
-        }
-
+        let adjacency = permutation
+            .par_iter()
+            .map(|&src_i| {
+                permutation
+                    .iter()
+                    .map(|&src_j| self.get_edge(src_i, src_j))
+                    .collect()
+            })
+            .collect();
+        let mut permuted = Graph {
+            n: self.n,
+            edges: Vec::new(),
+            adjacency,
+        };
+        permuted.rebuild_edge_cache();
         permuted
     }
 
@@ -1,4 +1,5 @@
 pub mod coloring;
+#[allow(clippy::module_inception)]
 pub mod graph;
 pub mod tournament;
 
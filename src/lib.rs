@@ -8,15 +8,20 @@ pub mod utils;
 pub use crypto::*;
 pub use graph::*;
 pub use protocol::{
+    error::{ChallengeError, VerificationError},
+    fiat_shamir::FiatShamirTranscript,
     messages::{
         BlankChallenge, BlankChallengeResponse, BlankEdgeOpening, Challenge, Commitments,
-        SpotChallenge, SpotChallengeResponse, SpotEdgeOpening, SpotResponse,
+        PermutationChallenge, PermutationChallengeResponse, PermutationOpening, SpotChallenge,
+        SpotChallengeResponse, SpotEdgeOpening, SpotResponse,
     },
     prover::{ProverConfig, ProverState},
+    soundness::{spot_pattern_count, SoundnessEstimate},
     verifier::{Verifier, VerifierConfig},
 };
-pub use stark::constraints::{BlankCountConstraints, ConstraintViolation};
+pub use stark::constraints::{BlankCountConstraints, ConstraintViolation, SumBound};
 pub use stark::fri;
+pub use stark::params::{ParameterEngine, ParameterError, SecurityEstimate};
 pub use stark::prover::{generate_blank_count_proof, BlankCountProof, StarkParameters};
 pub use stark::StarkField;
 pub use ui::{
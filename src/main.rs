@@ -1,16 +1,28 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+use zkp_c_coloring::crypto::hash::HashBackend;
+use zkp_c_coloring::crypto::merkle::MerkleArity;
+use zkp_c_coloring::crypto::merkle_storage::MerkleStorage;
+use zkp_c_coloring::protocol::error::VerificationError;
+use zkp_c_coloring::protocol::fiat_shamir::FiatShamirTranscript;
 use zkp_c_coloring::protocol::messages::{
-    BlankChallenge, BlankChallengeResponse, Challenge, Commitments, SpotChallenge,
-    SpotChallengeResponse,
+    BlankChallenge, BlankChallengeResponse, Challenge, Commitments, PermutationChallenge,
+    PermutationChallengeResponse, SessionNonce, SpotChallenge, SpotChallengeResponse,
+};
+use zkp_c_coloring::protocol::network::{
+    run_remote_verifier, serve_prover_session, Connection, NetworkError,
 };
 use zkp_c_coloring::protocol::prover::{ProverConfig, ProverState};
+use zkp_c_coloring::protocol::soundness::SoundnessEstimate;
 use zkp_c_coloring::protocol::verifier::{Verifier, VerifierConfig};
 use zkp_c_coloring::utils::random_graph::generate_hard_instance;
 use zkp_c_coloring::utils::serialization::{
-    load_graph_instance, load_proof, save_graph_instance, save_proof, GraphInstance,
-    ProofTranscript, TranscriptResponse, TranscriptRound,
+    load_graph_instance, load_proof, load_public_statement, load_witness, save_graph_instance,
+    save_proof, save_public_statement, save_witness, GraphInstance, ProofTranscript,
+    PublicStatement, TranscriptMode, TranscriptResponse, TranscriptRound,
 };
 use zkp_c_coloring::{
     focus_from_blank_response, focus_from_spot_response, merkle_display_from_chunked,
@@ -35,21 +47,176 @@ enum Commands {
         #[arg(short, long, value_name = "FILE")]
         output: PathBuf,
     },
-    /// Run the interactive protocol locally and record a transcript
-    Prove {
+    /// Split an instance into its public statement and the prover's witness
+    Split {
         #[arg(short, long, value_name = "FILE")]
         instance: PathBuf,
         #[arg(short, long, value_name = "FILE")]
+        statement: PathBuf,
+        #[arg(short, long, value_name = "FILE")]
+        witness: PathBuf,
+        #[arg(long, value_name = "BACKEND", default_value_t = HashBackend::Blake3)]
+        hash: HashBackend,
+    },
+    /// Run the interactive protocol locally and record a transcript
+    Prove {
+        #[command(flatten)]
+        input: ProverInput,
+        #[arg(short, long, value_name = "FILE")]
         proof: PathBuf,
         #[arg(long, default_value_t = 8)]
         rounds: u32,
+        #[arg(
+            long,
+            help = "Recommit under a fresh permutation every N rounds (default: single commitment)"
+        )]
+        rounds_per_commitment: Option<u32>,
+        #[arg(long, help = "Open spot and blank edges with one multiproof per tree")]
+        batch_openings: bool,
+        #[arg(long, help = "Threads for building commitments (default: all cores)")]
+        threads: Option<usize>,
+        #[arg(
+            long,
+            value_name = "ARITY",
+            default_value_t = MerkleArity::Two,
+            help = "Children per Merkle node: 2, 4, 8 or 16"
+        )]
+        merkle_arity: MerkleArity,
+        #[arg(long, help = "Give every adjacency row its own graph-tree chunk")]
+        row_per_chunk: bool,
+        #[arg(
+            long,
+            value_name = "DIR",
+            help = "Memory-map the graph and blank Merkle trees from files in DIR"
+        )]
+        merkle_dir: Option<PathBuf>,
+        #[arg(
+            long,
+            default_value_t = 0.0,
+            help = "Probability of a permutation-opening round (taken from the blank share)"
+        )]
+        permutation_prob: f64,
+        #[arg(
+            long,
+            help = "Derive challenges via Fiat-Shamir instead of an in-process verifier"
+        )]
+        non_interactive: bool,
+        #[command(flatten)]
+        session: SessionArgs,
     },
-    /// Verify a stored transcript against a graph instance
+    /// Verify a stored transcript against a public statement
     Verify {
-        #[arg(short, long, value_name = "FILE")]
-        instance: PathBuf,
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            help = "Public statement written by `split`"
+        )]
+        statement: PathBuf,
         #[arg(short, long, value_name = "FILE")]
         proof: PathBuf,
+        #[arg(long, default_value_t = 8, help = "Rounds the proof must contain")]
+        rounds: u32,
+        #[command(flatten)]
+        session: SessionArgs,
+    },
+    /// Serve the prover's side of the interactive protocol over TCP
+    ServeProver {
+        #[command(flatten)]
+        input: ProverInput,
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        listen: String,
+        #[arg(
+            long,
+            help = "Recommit under a fresh permutation every N rounds (default: single commitment)"
+        )]
+        rounds_per_commitment: Option<u32>,
+        #[arg(long, help = "Open spot and blank edges with one multiproof per tree")]
+        batch_openings: bool,
+        #[arg(long, help = "Threads for building commitments (default: all cores)")]
+        threads: Option<usize>,
+        #[arg(
+            long,
+            value_name = "ARITY",
+            default_value_t = MerkleArity::Two,
+            help = "Children per Merkle node: 2, 4, 8 or 16"
+        )]
+        merkle_arity: MerkleArity,
+        #[arg(long, help = "Give every adjacency row its own graph-tree chunk")]
+        row_per_chunk: bool,
+        #[arg(
+            long,
+            value_name = "DIR",
+            help = "Memory-map the graph and blank Merkle trees from files in DIR"
+        )]
+        merkle_dir: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "PROOFS",
+            help = "Edge proofs cached per commitment tree (default: 2048, 0 disables)"
+        )]
+        proof_cache: Option<usize>,
+        #[arg(long, help = "Exit after this many sessions (default: serve forever)")]
+        sessions: Option<u32>,
+        #[arg(long, default_value_t = 30)]
+        timeout_secs: u64,
+        #[arg(
+            long,
+            value_name = "DIR",
+            help = "Save the transcript of every accepted session into DIR"
+        )]
+        transcript_dir: Option<PathBuf>,
+    },
+    /// Run the verifier's side of the interactive protocol against a remote prover
+    VerifyRemote {
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            help = "Public statement written by `split`"
+        )]
+        statement: PathBuf,
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        connect: String,
+        #[arg(long, default_value_t = 8)]
+        rounds: u32,
+        #[arg(long, default_value_t = 0.0)]
+        permutation_prob: f64,
+        #[arg(long, default_value_t = 30)]
+        timeout_secs: u64,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Save the verified transcript to FILE"
+        )]
+        transcript: Option<PathBuf>,
+        #[command(flatten)]
+        session: SessionArgs,
+    },
+    /// Estimate a cheating prover's escape probability for a public statement
+    Soundness {
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            help = "Public statement written by `split`"
+        )]
+        statement: PathBuf,
+        #[arg(long, default_value_t = 8)]
+        rounds: u32,
+        #[arg(long, default_value_t = 4)]
+        spots_per_round: u32,
+        #[arg(long, default_value_t = 2)]
+        blank_checks_per_round: u32,
+        #[arg(long, default_value_t = 0.8)]
+        spot_prob: f64,
+        #[arg(long, default_value_t = 0.0)]
+        permutation_prob: f64,
+        #[arg(
+            long,
+            help = "Also report the verifier configuration reaching this many bits"
+        )]
+        target_bits: Option<u32>,
     },
     /// Benchmark proof generation and verification for placeholder graphs
     Benchmark {
@@ -68,6 +235,19 @@ enum Commands {
         spots_per_round: Option<u32>,
         #[arg(long, help = "Blank checks per round (overrides sampling)")]
         blank_checks_per_round: Option<u32>,
+        #[arg(long, value_name = "BACKEND", default_value_t = HashBackend::Blake3)]
+        hash: HashBackend,
+        #[arg(long, help = "Threads for building commitments (default: all cores)")]
+        threads: Option<usize>,
+        #[arg(
+            long,
+            value_name = "ARITY",
+            default_value_t = MerkleArity::Two,
+            help = "Children per Merkle node: 2, 4, 8 or 16"
+        )]
+        merkle_arity: MerkleArity,
+        #[arg(long, help = "Give every adjacency row its own graph-tree chunk")]
+        row_per_chunk: bool,
     },
     /// Run the protocol with a live terminal UI that visualizes each round
     Visualize {
@@ -87,6 +267,87 @@ enum Commands {
     },
 }
 
+/// What the prover works from: a full instance, or a statement and its witness.
+#[derive(Args)]
+struct ProverInput {
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        required_unless_present = "witness",
+        conflicts_with_all = ["statement", "witness"]
+    )]
+    instance: Option<PathBuf>,
+    #[arg(long, value_name = "FILE", requires = "witness")]
+    statement: Option<PathBuf>,
+    #[arg(long, value_name = "FILE", requires = "statement")]
+    witness: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "BACKEND",
+        help = "Hash backend: blake3, sha3-256 or shake256 (default: the statement's, else blake3)"
+    )]
+    hash: Option<HashBackend>,
+}
+
+impl ProverInput {
+    /// The instance and the hash backend to commit with.
+    fn load(&self) -> CliResult<(GraphInstance, HashBackend)> {
+        match (&self.instance, &self.statement, &self.witness) {
+            (Some(instance), _, _) => Ok((
+                load_graph_instance(instance)?,
+                self.hash.unwrap_or_default(),
+            )),
+            (None, Some(statement), Some(witness)) => {
+                let statement = load_public_statement(statement)?;
+                let hash = statement.hash()?;
+                if self.hash.is_some_and(|requested| requested != hash) {
+                    return Err(format!("the statement requires hash {hash}").into());
+                }
+                Ok((load_witness(witness)?.into_instance(&statement)?, hash))
+            }
+            _ => Err("pass --instance, or --statement with --witness".into()),
+        }
+    }
+}
+
+/// Session nonce the verifier issues (`prove`, `verify-remote`) or expects (`verify`).
+#[derive(Args)]
+struct SessionArgs {
+    #[arg(
+        long,
+        value_name = "HEX",
+        help = "32-byte session nonce (required by verify; random otherwise)"
+    )]
+    nonce: Option<String>,
+    #[arg(long, help = "Optional context string bound into the session")]
+    context: Option<String>,
+}
+
+impl SessionArgs {
+    /// The session given on the command line. A verifier checking a transcript needs the
+    /// nonce it issued: the transcript's own nonce says nothing about who picked it.
+    fn parse(self) -> CliResult<SessionNonce> {
+        let Some(hex_nonce) = self.nonce else {
+            return Err("pass the --nonce issued to the prover".into());
+        };
+        let nonce = hex::decode(hex_nonce.trim())?
+            .try_into()
+            .map_err(|_| "session nonce must be 32 bytes")?;
+        Ok(SessionNonce {
+            nonce,
+            context: self.context,
+        })
+    }
+
+    fn parse_or_random(self) -> CliResult<SessionNonce> {
+        if self.nonce.is_none() {
+            return Ok(SessionNonce::random(self.context));
+        }
+        self.parse()
+    }
+}
+
 fn main() {
     if let Err(err) = run() {
         eprintln!("Error: {err}");
@@ -98,12 +359,125 @@ fn run() -> CliResult<()> {
     let cli = Cli::parse();
     match cli.command {
         Commands::Generate { nodes, output } => run_generate(nodes, output)?,
-        Commands::Prove {
+        Commands::Split {
             instance,
+            statement,
+            witness,
+            hash,
+        } => run_split(instance, statement, witness, hash)?,
+        Commands::Prove {
+            input,
+            proof,
+            rounds,
+            rounds_per_commitment,
+            batch_openings,
+            threads,
+            merkle_arity,
+            row_per_chunk,
+            merkle_dir,
+            permutation_prob,
+            non_interactive,
+            session,
+        } => run_prove(
+            input,
+            proof,
+            rounds,
+            ProverConfig {
+                rounds_per_commitment,
+                batch_openings,
+                threads,
+                merkle_arity,
+                row_per_chunk,
+                merkle_storage: merkle_dir.map(MerkleStorage::Disk).unwrap_or_default(),
+                ..ProverConfig::default()
+            },
+            permutation_prob,
+            non_interactive,
+            session.parse_or_random()?,
+        )?,
+        Commands::Verify {
+            statement,
+            proof,
+            rounds,
+            session,
+        } => run_verify(
+            statement,
             proof,
+            VerifierConfig {
+                rounds,
+                ..VerifierConfig::default()
+            },
+            session.parse()?,
+        )?,
+        Commands::ServeProver {
+            input,
+            listen,
+            rounds_per_commitment,
+            batch_openings,
+            threads,
+            merkle_arity,
+            row_per_chunk,
+            merkle_dir,
+            proof_cache,
+            sessions,
+            timeout_secs,
+            transcript_dir,
+        } => run_serve_prover(
+            input,
+            listen,
+            ProverConfig {
+                rounds_per_commitment,
+                batch_openings,
+                threads,
+                proof_cache_capacity: proof_cache,
+                merkle_arity,
+                row_per_chunk,
+                merkle_storage: merkle_dir.map(MerkleStorage::Disk).unwrap_or_default(),
+                ..ProverConfig::default()
+            },
+            sessions,
+            Duration::from_secs(timeout_secs),
+            transcript_dir,
+        )?,
+        Commands::VerifyRemote {
+            statement,
+            connect,
             rounds,
-        } => run_prove(instance, proof, rounds)?,
-        Commands::Verify { instance, proof } => run_verify(instance, proof)?,
+            permutation_prob,
+            timeout_secs,
+            transcript,
+            session,
+        } => run_verify_remote(
+            statement,
+            connect,
+            session.parse_or_random()?,
+            VerifierConfig {
+                rounds,
+                permutation_probability: permutation_prob,
+                ..VerifierConfig::default()
+            },
+            Duration::from_secs(timeout_secs),
+            transcript,
+        )?,
+        Commands::Soundness {
+            statement,
+            rounds,
+            spots_per_round,
+            blank_checks_per_round,
+            spot_prob,
+            permutation_prob,
+            target_bits,
+        } => run_soundness(
+            statement,
+            VerifierConfig {
+                rounds,
+                spots_per_round,
+                blank_checks_per_round,
+                spot_probability: spot_prob,
+                permutation_probability: permutation_prob,
+            },
+            target_bits,
+        )?,
         Commands::Benchmark {
             nodes,
             rounds,
@@ -111,6 +485,10 @@ fn run() -> CliResult<()> {
             blank_sampling,
             spots_per_round,
             blank_checks_per_round,
+            hash,
+            threads,
+            merkle_arity,
+            row_per_chunk,
         } => run_benchmark(
             nodes,
             rounds,
@@ -118,6 +496,13 @@ fn run() -> CliResult<()> {
             blank_sampling,
             spots_per_round,
             blank_checks_per_round,
+            ProverConfig {
+                hash,
+                threads,
+                merkle_arity,
+                row_per_chunk,
+                ..ProverConfig::default()
+            },
         )?,
         Commands::Visualize { instance, rounds } => run_visualize(instance, rounds)?,
         Commands::VisualizeWeb {
@@ -142,33 +527,257 @@ fn run_generate(nodes: u32, output: PathBuf) -> CliResult<()> {
     Ok(())
 }
 
-fn run_prove(instance_path: PathBuf, proof_path: PathBuf, rounds: u32) -> CliResult<()> {
+fn run_split(
+    instance_path: PathBuf,
+    statement_path: PathBuf,
+    witness_path: PathBuf,
+    hash: HashBackend,
+) -> CliResult<()> {
     let instance = load_graph_instance(&instance_path)?;
-    let transcript = construct_transcript(&instance, rounds)?;
+    let statement = instance.statement().with_hash(hash);
+    save_public_statement(&statement_path, &statement)?;
+    save_witness(&witness_path, &instance.witness())?;
+    println!(
+        "Statement (n = {}, blank limit {}, hash {}, {}-bit STARK) saved to {}",
+        statement.n,
+        statement.blank_limit,
+        statement.hash_id,
+        statement.security_level,
+        statement_path.display()
+    );
+    println!("Witness saved to {}", witness_path.display());
+    Ok(())
+}
+
+fn run_prove(
+    input: ProverInput,
+    proof_path: PathBuf,
+    rounds: u32,
+    mut prover_cfg: ProverConfig,
+    permutation_prob: f64,
+    non_interactive: bool,
+    session: SessionNonce,
+) -> CliResult<()> {
+    if prover_cfg.rounds_per_commitment == Some(0) {
+        return Err("rounds-per-commitment must be greater than zero".into());
+    }
+    let defaults = VerifierConfig::default();
+    if !(0.0..=1.0 - defaults.spot_probability).contains(&permutation_prob) {
+        return Err(format!(
+            "permutation-prob must lie in [0, {:.2}]",
+            1.0 - defaults.spot_probability
+        )
+        .into());
+    }
+    // A Fiat-Shamir prover could otherwise retry each recommitment until the challenge
+    // it derives is one it can answer.
+    if non_interactive && prover_cfg.rounds_per_commitment.is_some() {
+        return Err("--non-interactive proofs use a single commitment; \
+                    drop --rounds-per-commitment"
+            .into());
+    }
+    if non_interactive && permutation_prob > 0.0 {
+        return Err("permutation rounds need a commitment per round, \
+                    which --non-interactive proofs cannot have"
+            .into());
+    }
+    let (instance, hash) = input.load()?;
+    prover_cfg.hash = hash;
+    if let MerkleStorage::Disk(dir) = &prover_cfg.merkle_storage {
+        std::fs::create_dir_all(dir)?;
+    }
+    let verifier_cfg = VerifierConfig {
+        rounds,
+        permutation_probability: permutation_prob,
+        ..defaults
+    };
+    verifier_cfg.check_schedule(prover_cfg.rounds_per_commitment)?;
+    let mode = if non_interactive {
+        TranscriptMode::FiatShamir
+    } else {
+        TranscriptMode::Interactive
+    };
+    let transcript =
+        construct_transcript_with_config(&instance, &verifier_cfg, &prover_cfg, mode, &session)?;
     save_proof(&proof_path, &transcript)?;
     println!(
-        "Proof transcript with {} rounds saved to {}",
+        "{:?} proof transcript with {} rounds ({} commitments) saved to {}",
+        transcript.mode,
         transcript.rounds.len(),
+        transcript.commitment_count(),
         proof_path.display()
     );
+    println!("Session nonce: {}", hex::encode(session.nonce));
     Ok(())
 }
 
-fn run_verify(instance_path: PathBuf, proof_path: PathBuf) -> CliResult<()> {
-    let instance = load_graph_instance(&instance_path)?;
+fn run_verify(
+    statement_path: PathBuf,
+    proof_path: PathBuf,
+    verifier_cfg: VerifierConfig,
+    session: SessionNonce,
+) -> CliResult<()> {
+    let statement = load_public_statement(&statement_path)?;
     let transcript = load_proof(&proof_path)?;
-    replay_transcript(&instance, &transcript)?;
+    replay_transcript(&statement, &transcript, &verifier_cfg, session)?;
     println!(
         "Transcript verified successfully against {}",
-        instance_path.display()
+        statement_path.display()
+    );
+    Ok(())
+}
+
+fn run_serve_prover(
+    input: ProverInput,
+    listen: String,
+    mut prover_cfg: ProverConfig,
+    sessions: Option<u32>,
+    timeout: Duration,
+    transcript_dir: Option<PathBuf>,
+) -> CliResult<()> {
+    if prover_cfg.rounds_per_commitment == Some(0) {
+        return Err("rounds-per-commitment must be greater than zero".into());
+    }
+    let (instance, hash) = input.load()?;
+    prover_cfg.hash = hash;
+    if let MerkleStorage::Disk(dir) = &prover_cfg.merkle_storage {
+        std::fs::create_dir_all(dir)?;
+    }
+    if let Some(dir) = &transcript_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut prover = ProverState::new(instance.graph, instance.coloration);
+    Runtime::new()?.block_on(async {
+        let listener = TcpListener::bind(&listen).await?;
+        println!("Prover listening on {}", listener.local_addr()?);
+        let mut served = 0u32;
+        while sessions.is_none_or(|limit| served < limit) {
+            served += 1;
+            let (stream, peer) = listener.accept().await?;
+            let mut connection = Connection::new(stream, timeout);
+            match serve_prover_session(&mut connection, &mut prover, &prover_cfg).await {
+                Ok(transcript) => {
+                    println!(
+                        "Session {served} with {peer}: accepted after {} rounds ({} commitments)",
+                        transcript.rounds.len(),
+                        transcript.commitment_count()
+                    );
+                    println!("  proof cache: {}", prover.proof_cache_stats());
+                    if let Some(dir) = &transcript_dir {
+                        let path = dir.join(format!("session-{served}.transcript"));
+                        save_proof(&path, &transcript)?;
+                        println!("  transcript saved to {}", path.display());
+                    }
+                }
+                Err(err) => eprintln!("Session {served} with {peer}: {err}"),
+            }
+        }
+        Ok(())
+    })
+}
+
+fn run_verify_remote(
+    statement_path: PathBuf,
+    connect: String,
+    session: SessionNonce,
+    verifier_cfg: VerifierConfig,
+    timeout: Duration,
+    transcript_path: Option<PathBuf>,
+) -> CliResult<()> {
+    let defaults = VerifierConfig::default();
+    if !(0.0..=1.0 - defaults.spot_probability).contains(&verifier_cfg.permutation_probability) {
+        return Err(format!(
+            "permutation-prob must lie in [0, {:.2}]",
+            1.0 - defaults.spot_probability
+        )
+        .into());
+    }
+    let statement = load_public_statement(&statement_path)?;
+    let rounds = verifier_cfg.rounds;
+    let mut verifier = statement_verifier(&statement, verifier_cfg)?.with_session(session);
+    let transcript = Runtime::new()?.block_on(async {
+        let stream = tokio::time::timeout(timeout, TcpStream::connect(&connect))
+            .await
+            .map_err(|_| NetworkError::Timeout)??;
+        println!("Connected to prover at {connect}");
+        let mut connection = Connection::new(stream, timeout);
+        run_remote_verifier(&mut connection, &mut verifier, rounds).await
+    })?;
+    println!(
+        "Remote proof accepted after {} rounds ({} commitments)",
+        transcript.rounds.len(),
+        transcript.commitment_count()
+    );
+    if let Some(path) = transcript_path {
+        save_proof(&path, &transcript)?;
+        println!("Transcript saved to {}", path.display());
+    }
+    Ok(())
+}
+
+fn run_soundness(
+    statement_path: PathBuf,
+    config: VerifierConfig,
+    target_bits: Option<u32>,
+) -> CliResult<()> {
+    if !(0.0..=1.0).contains(&config.spot_probability)
+        || !(0.0..=1.0 - config.spot_probability).contains(&config.permutation_probability)
+    {
+        return Err(
+            "spot-prob and permutation-prob must be probabilities summing to at most 1".into(),
+        );
+    }
+    let statement = load_public_statement(&statement_path)?;
+    let coloration = &statement.coloration;
+    println!(
+        "Statement: n = {}, {} allowed patterns, blank limit {}",
+        statement.n,
+        coloration.pattern_count(),
+        statement.blank_limit
     );
+    print_soundness(&config, &config.soundness(coloration));
+    if let Some(bits) = target_bits {
+        println!();
+        println!("Configuration for {} bits:", bits);
+        let tuned = VerifierConfig::for_security_bits(coloration, bits)?;
+        print_soundness(&tuned, &tuned.soundness(coloration));
+    }
     Ok(())
 }
 
+fn print_soundness(config: &VerifierConfig, estimate: &SoundnessEstimate) {
+    println!(
+        "  rounds = {}, spots/round = {}, blank checks/round = {}, spot prob = {:.4}, permutation prob = {:.4}",
+        config.rounds,
+        config.spots_per_round,
+        config.blank_checks_per_round,
+        config.spot_probability,
+        config.permutation_probability
+    );
+    println!(
+        "  triads = {}, pattern coverage = {:.6}",
+        estimate.triads, estimate.pattern_coverage
+    );
+    println!(
+        "  detection per spot = {:.3e} (worst case), {:.3e} (random coloring); per blank check = {:.3e}",
+        estimate.spot_detection, estimate.random_spot_detection, estimate.blank_detection
+    );
+    println!(
+        "  escape per round = {:.6} (worst case), {:.6} (random coloring)",
+        estimate.round_escape, estimate.random_round_escape
+    );
+    println!(
+        "  soundness error = {:.3e}, security = {:.2} bits (worst case), {:.2} bits (random coloring)",
+        estimate.soundness_error, estimate.security_bits, estimate.random_security_bits
+    );
+}
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone)]
 enum RoundRecord {
     Spot(SpotChallenge, SpotChallengeResponse),
     Blank(BlankChallenge, BlankChallengeResponse),
+    Permutation(PermutationChallenge, PermutationChallengeResponse),
 }
 
 struct SampleMetrics {
@@ -182,6 +791,7 @@ struct SampleMetrics {
     commitment_bytes: usize,
     spot_proof_bytes: usize,
     blank_proof_bytes: usize,
+    batched_proof_bytes: usize,
     spot_rounds: u32,
     blank_rounds: u32,
     spot_edges: u64,
@@ -210,6 +820,7 @@ struct AggregateMetrics {
     commitment_bytes: u128,
     spot_proof_bytes: u128,
     blank_proof_bytes: u128,
+    batched_proof_bytes: u128,
     communication_bytes: u128,
     spot_rounds: u64,
     blank_rounds: u64,
@@ -229,6 +840,7 @@ impl AggregateMetrics {
         self.commitment_bytes += sample.commitment_bytes as u128;
         self.spot_proof_bytes += sample.spot_proof_bytes as u128;
         self.blank_proof_bytes += sample.blank_proof_bytes as u128;
+        self.batched_proof_bytes += sample.batched_proof_bytes as u128;
         self.communication_bytes += sample.communication_bytes() as u128;
         self.spot_rounds += sample.spot_rounds as u64;
         self.blank_rounds += sample.blank_rounds as u64;
@@ -244,6 +856,7 @@ fn run_benchmark(
     blank_sampling: bool,
     spots_per_round: Option<u32>,
     blank_checks_per_round: Option<u32>,
+    prover_cfg: ProverConfig,
 ) -> CliResult<()> {
     if samples == 0 {
         return Err("samples must be greater than zero".into());
@@ -255,14 +868,15 @@ fn run_benchmark(
         blank_checks_per_round: blank_checks_per_round.unwrap_or(if blank_sampling {
             2
         } else {
-            (nodes * nodes) as u32
+            nodes * nodes
         }),
         spot_probability: if blank_sampling { 0.7 } else { 0.3 },
+        permutation_probability: 0.0,
     };
 
     println!(
-        "Benchmarking with {} nodes, {} rounds, {} samples",
-        nodes, rounds, samples
+        "Benchmarking with {} nodes, {} rounds, {} samples, hash {}",
+        nodes, rounds, samples, prover_cfg.hash
     );
     println!(
         "Strategy: {} (spots={}/round, blanks={}/round, spot_prob={:.2})",
@@ -279,7 +893,7 @@ fn run_benchmark(
 
     let mut aggregate = AggregateMetrics::default();
     for sample in 0..samples {
-        let metrics = execute_benchmark_sample(nodes, &verifier_cfg)?;
+        let metrics = execute_benchmark_sample(nodes, &verifier_cfg, &prover_cfg)?;
         print_sample_metrics(sample + 1, &metrics);
         aggregate.add_sample(&metrics);
         println!();
@@ -290,16 +904,20 @@ fn run_benchmark(
     Ok(())
 }
 
-fn execute_benchmark_sample(nodes: u32, verifier_cfg: &VerifierConfig) -> CliResult<SampleMetrics> {
+fn execute_benchmark_sample(
+    nodes: u32,
+    verifier_cfg: &VerifierConfig,
+    prover_cfg: &ProverConfig,
+) -> CliResult<SampleMetrics> {
     let (graph, coloration, _) = generate_hard_instance(nodes);
+    let hash = prover_cfg.hash;
     let mut prover = ProverState::new(graph, coloration.clone());
-    let mut verifier = Verifier::new(coloration.clone(), verifier_cfg.clone());
-    let prover_cfg = ProverConfig::default();
+    let mut verifier = Verifier::new(coloration.clone(), verifier_cfg.clone()).with_hash(hash);
     let mut round_records = Vec::with_capacity(verifier_cfg.rounds as usize);
 
     let prove_start = Instant::now();
     let commit_start = Instant::now();
-    let commitments = prover.commit(&prover_cfg);
+    let commitments = prover.commit(prover_cfg);
     let commit_time = commit_start.elapsed();
     verifier.receive_commitments(commitments.clone());
     let commitment_bytes = estimate_commitment_size(&commitments);
@@ -340,33 +958,61 @@ fn execute_benchmark_sample(nodes: u32, verifier_cfg: &VerifierConfig) -> CliRes
                 blank_proof_bytes += estimate_blank_response_size(&response);
                 round_records.push(RoundRecord::Blank(challenge_data, response));
             }
+            Challenge::Permutation(challenge_data) => {
+                // Benchmark configs never sample permutation rounds; keep them verifiable
+                // but out of the spot/blank metrics.
+                let response = prover.respond_to_permutation_challenge(&challenge_data);
+                round_records.push(RoundRecord::Permutation(challenge_data, response));
+            }
         }
     }
 
     let total_prove_time = prove_start.elapsed();
 
+    // The same challenges answered with multiproofs, outside the timed passes.
+    prover.set_batch_openings(true);
+    let batched_proof_bytes = round_records
+        .iter()
+        .map(|record| match record {
+            RoundRecord::Spot(challenge, _) => {
+                estimate_spot_response_size(&prover.respond_to_spot_challenge(challenge))
+            }
+            RoundRecord::Blank(challenge, _) => {
+                estimate_blank_response_size(&prover.respond_to_blank_challenge(challenge))
+            }
+            RoundRecord::Permutation(..) => 0,
+        })
+        .sum();
+    prover.set_batch_openings(false);
+
     let verify_start = Instant::now();
-    let mut replay_verifier = Verifier::new(coloration, verifier_cfg.clone());
+    let mut replay_verifier = Verifier::new(coloration, verifier_cfg.clone()).with_hash(hash);
     replay_verifier.receive_commitments(commitments);
     let mut verify_spot_time = Duration::ZERO;
     let mut verify_blank_time = Duration::ZERO;
 
-    for record in &round_records {
+    for (round, record) in round_records.iter().enumerate() {
+        let round = round as u32;
         match record {
             RoundRecord::Spot(challenge, response) => {
                 let start = Instant::now();
-                if !replay_verifier.verify_spot_response(challenge, response) {
-                    return Err("spot verification failed".into());
-                }
+                replay_verifier
+                    .verify_spot_response(challenge, response)
+                    .map_err(|err| err.in_round(round))?;
                 verify_spot_time += start.elapsed();
             }
             RoundRecord::Blank(challenge, response) => {
                 let start = Instant::now();
-                if !replay_verifier.verify_blank_response(challenge, response) {
-                    return Err("blank verification failed".into());
-                }
+                replay_verifier
+                    .verify_blank_response(challenge, response)
+                    .map_err(|err| err.in_round(round))?;
                 verify_blank_time += start.elapsed();
             }
+            RoundRecord::Permutation(challenge, response) => {
+                replay_verifier
+                    .verify_permutation_response(challenge, response)
+                    .map_err(|err| err.in_round(round))?;
+            }
         }
     }
 
@@ -383,6 +1029,7 @@ fn execute_benchmark_sample(nodes: u32, verifier_cfg: &VerifierConfig) -> CliRes
         commitment_bytes,
         spot_proof_bytes,
         blank_proof_bytes,
+        batched_proof_bytes,
         spot_rounds,
         blank_rounds,
         spot_edges,
@@ -420,6 +1067,14 @@ fn print_sample_metrics(sample_index: u32, metrics: &SampleMetrics) {
         format_bytes_usize(metrics.proof_bytes()),
         format_bytes_usize(metrics.communication_bytes())
     );
+    println!(
+        "  Batched     → proof {} with multiproofs ({:.1}% smaller)",
+        format_bytes_usize(metrics.batched_proof_bytes),
+        size_reduction_percent(
+            metrics.proof_bytes() as f64,
+            metrics.batched_proof_bytes as f64
+        )
+    );
 }
 
 fn print_average_metrics(samples: u32, aggregate: &AggregateMetrics) {
@@ -455,6 +1110,22 @@ fn print_average_metrics(samples: u32, aggregate: &AggregateMetrics) {
         ),
         format_bytes_f64(aggregate.communication_bytes as f64 / samples_f)
     );
+    println!(
+        "  Batched     → proof {} with multiproofs ({:.1}% smaller)",
+        format_bytes_f64(aggregate.batched_proof_bytes as f64 / samples_f),
+        size_reduction_percent(
+            (aggregate.spot_proof_bytes + aggregate.blank_proof_bytes) as f64,
+            aggregate.batched_proof_bytes as f64
+        )
+    );
+}
+
+fn size_reduction_percent(unbatched: f64, batched: f64) -> f64 {
+    if unbatched == 0.0 {
+        0.0
+    } else {
+        100.0 * (1.0 - batched / unbatched)
+    }
 }
 
 fn duration_ms(duration: Duration) -> f64 {
@@ -489,7 +1160,7 @@ fn format_bytes_f64(bytes: f64) -> String {
 }
 
 fn estimate_commitment_size(_commitments: &Commitments) -> usize {
-    32 + 32 + 32 + 4
+    32 + 32 + 32 + 32 + 4
 }
 
 fn estimate_spot_response_size(response: &SpotChallengeResponse) -> usize {
@@ -497,30 +1168,57 @@ fn estimate_spot_response_size(response: &SpotChallengeResponse) -> usize {
     for spot in &response.responses {
         size += 12;
         for edge in &spot.edges {
-            size += 8 + 1;
-            size += estimate_merkle_proof_size(&edge.proof);
+            size += 8 + 1 + 32 + 32;
+            size += edge.proof.as_ref().map_or(0, estimate_merkle_proof_size);
         }
     }
+    size += response
+        .multiproof
+        .as_ref()
+        .map_or(0, estimate_multiproof_size);
     size
 }
 
 fn estimate_blank_response_size(response: &BlankChallengeResponse) -> usize {
     let mut size = 0;
     for edge in &response.edges {
-        size += 8 + 8 + 1 + 1;
-        size += estimate_merkle_proof_size(&edge.color_proof);
-        size += estimate_merkle_proof_size(&edge.blank_proof);
+        size += 8 + 8 + 1 + 32 + 32;
+        size += edge
+            .edge_proof
+            .as_ref()
+            .map_or(0, estimate_merkle_proof_size);
+        size += edge
+            .blank_proof
+            .as_ref()
+            .map_or(0, estimate_merkle_proof_size);
+    }
+    if let Some(multiproofs) = &response.multiproofs {
+        size += estimate_multiproof_size(&multiproofs.edges);
+        size += estimate_multiproof_size(&multiproofs.blanks);
     }
     size += estimate_stark_proof_size(&response.stark_proof);
     size
 }
 
 fn estimate_merkle_proof_size(proof: &zkp_c_coloring::crypto::merkle::ChunkedMerkleProof) -> usize {
-    let leaf_path_size = 32 + proof.leaf_proof.path.len() * (32 + 1);
-    let chunk_path_size = 32 + proof.chunk_proof.path.len() * (32 + 1);
+    let leaf_path_size = 32 + proof.leaf_proof.path.len() * 32;
+    let chunk_path_size = 32 + proof.chunk_proof.path.len() * 32;
     leaf_path_size + chunk_path_size
 }
 
+fn estimate_multiproof_size(
+    proof: &zkp_c_coloring::crypto::merkle::ChunkedMerkleMultiProof,
+) -> usize {
+    let subproofs = proof.chunk_proofs.len() + 1;
+    let indices = proof.top_proof.leaf_indices.len()
+        + proof
+            .chunk_proofs
+            .iter()
+            .map(|chunk| chunk.leaf_indices.len())
+            .sum::<usize>();
+    8 + subproofs * 8 + indices * 8 + proof.hash_count() * 32
+}
+
 fn estimate_stark_proof_size(_proof: &zkp_c_coloring::stark::prover::BlankCountProof) -> usize {
     1024 * 8
 }
@@ -562,12 +1260,12 @@ fn run_visualize(instance_path: PathBuf, rounds: u32) -> CliResult<()> {
                     .join(", ");
                 let response = prover.respond_to_spot_challenge(&challenge);
                 let verified = verifier.verify_spot_response(&challenge, &response);
-                let status = if verified { "verified" } else { "rejected" };
+                let status = round_status(&verified);
                 visualizer.update_round(RoundSnapshot {
                     round: Some(round),
                     phase: "spot challenge".to_string(),
                     detail: format!("triads: {detail}"),
-                    status: status.to_string(),
+                    status: status.clone(),
                 })?;
                 let focus = focus_from_spot_response(&challenge_label, &challenge.spots, &response);
                 visualizer.set_focus(Some(focus))?;
@@ -579,28 +1277,29 @@ fn run_visualize(instance_path: PathBuf, rounds: u32) -> CliResult<()> {
                     .iter()
                     .flat_map(|spot| spot.edges.iter())
                     .next()
-                    .map(|opening| {
-                        merkle_display_from_chunked(
+                    .and_then(|opening| {
+                        let proof = opening.proof.as_ref()?;
+                        Some(merkle_display_from_chunked(
                             &format!("edge {}→{}", opening.from, opening.to),
-                            &opening.proof,
-                        )
+                            proof,
+                        ))
                     });
                 visualizer.set_merkle(merkle)?;
                 visualizer.log(format!("Round {}: spot challenge {status}", round + 1))?;
-                if !verified {
+                if let Err(err) = verified {
                     visualizer.finish().ok();
-                    return Err(format!("spot response rejected in round {round}").into());
+                    return Err(err.in_round(round).into());
                 }
             }
             Challenge::Blank(challenge) => {
                 let response = prover.respond_to_blank_challenge(&challenge);
                 let verified = verifier.verify_blank_response(&challenge, &response);
-                let status = if verified { "verified" } else { "rejected" };
+                let status = round_status(&verified);
                 visualizer.update_round(RoundSnapshot {
                     round: Some(round),
                     phase: "blank challenge".to_string(),
                     detail: format!("edges checked: {}", challenge.edge_indices.len()),
-                    status: status.to_string(),
+                    status: status.clone(),
                 })?;
                 let focus = focus_from_blank_response(
                     &format!("#{:02}", round + 1),
@@ -608,11 +1307,12 @@ fn run_visualize(instance_path: PathBuf, rounds: u32) -> CliResult<()> {
                     &response,
                 );
                 visualizer.set_focus(Some(focus))?;
-                let merkle = response.edges.first().map(|opening| {
-                    merkle_display_from_chunked(
-                        &format!("edge {}→{} (color)", opening.from, opening.to),
-                        &opening.color_proof,
-                    )
+                let merkle = response.edges.first().and_then(|opening| {
+                    let proof = opening.edge_proof.as_ref()?;
+                    Some(merkle_display_from_chunked(
+                        &format!("edge {}→{}", opening.from, opening.to),
+                        proof,
+                    ))
                 });
                 visualizer.set_merkle(merkle)?;
                 visualizer.log(format!(
@@ -621,9 +1321,30 @@ fn run_visualize(instance_path: PathBuf, rounds: u32) -> CliResult<()> {
                     status,
                     challenge.edge_indices.len()
                 ))?;
-                if !verified {
+                if let Err(err) = verified {
+                    visualizer.finish().ok();
+                    return Err(err.in_round(round).into());
+                }
+            }
+            Challenge::Permutation(challenge) => {
+                let response = prover.respond_to_permutation_challenge(&challenge);
+                let verified = verifier.verify_permutation_response(&challenge, &response);
+                let status = round_status(&verified);
+                visualizer.update_round(RoundSnapshot {
+                    round: Some(round),
+                    phase: "permutation challenge".to_string(),
+                    detail: format!("entries opened: {}", response.entries.len()),
+                    status: status.clone(),
+                })?;
+                visualizer.set_focus(None)?;
+                visualizer.set_merkle(None)?;
+                visualizer.log(format!(
+                    "Round {}: permutation challenge {status}",
+                    round + 1
+                ))?;
+                if let Err(err) = verified {
                     visualizer.finish().ok();
-                    return Err(format!("blank response rejected in round {round}").into());
+                    return Err(err.in_round(round).into());
                 }
             }
         }
@@ -677,12 +1398,12 @@ fn run_visualize_web(instance_path: PathBuf, rounds: u32, port: u16) -> CliResul
                     .join(", ");
                 let response = prover.respond_to_spot_challenge(&challenge);
                 let verified = verifier.verify_spot_response(&challenge, &response);
-                let status = if verified { "verified" } else { "rejected" };
+                let status = round_status(&verified);
                 visualizer.update_round(RoundSnapshot {
                     round: Some(round),
                     phase: "spot challenge".to_string(),
                     detail: format!("triads: {detail}"),
-                    status: status.to_string(),
+                    status: status.clone(),
                 })?;
                 let focus = focus_from_spot_response(&challenge_label, &challenge.spots, &response);
                 visualizer.set_focus(Some(focus))?;
@@ -694,28 +1415,29 @@ fn run_visualize_web(instance_path: PathBuf, rounds: u32, port: u16) -> CliResul
                     .iter()
                     .flat_map(|spot| spot.edges.iter())
                     .next()
-                    .map(|opening| {
-                        merkle_display_from_chunked(
+                    .and_then(|opening| {
+                        let proof = opening.proof.as_ref()?;
+                        Some(merkle_display_from_chunked(
                             &format!("edge {}→{}", opening.from, opening.to),
-                            &opening.proof,
-                        )
+                            proof,
+                        ))
                     });
                 visualizer.set_merkle(merkle)?;
                 visualizer.log(format!("Round {}: spot challenge {status}", round + 1))?;
-                if !verified {
+                if let Err(err) = verified {
                     visualizer.finish().ok();
-                    return Err(format!("spot response rejected in round {round}").into());
+                    return Err(err.in_round(round).into());
                 }
             }
             Challenge::Blank(challenge) => {
                 let response = prover.respond_to_blank_challenge(&challenge);
                 let verified = verifier.verify_blank_response(&challenge, &response);
-                let status = if verified { "verified" } else { "rejected" };
+                let status = round_status(&verified);
                 visualizer.update_round(RoundSnapshot {
                     round: Some(round),
                     phase: "blank challenge".to_string(),
                     detail: format!("edges checked: {}", challenge.edge_indices.len()),
-                    status: status.to_string(),
+                    status: status.clone(),
                 })?;
                 let focus = focus_from_blank_response(
                     &format!("#{:02}", round + 1),
@@ -723,11 +1445,12 @@ fn run_visualize_web(instance_path: PathBuf, rounds: u32, port: u16) -> CliResul
                     &response,
                 );
                 visualizer.set_focus(Some(focus))?;
-                let merkle = response.edges.first().map(|opening| {
-                    merkle_display_from_chunked(
-                        &format!("edge {}→{} (color)", opening.from, opening.to),
-                        &opening.color_proof,
-                    )
+                let merkle = response.edges.first().and_then(|opening| {
+                    let proof = opening.edge_proof.as_ref()?;
+                    Some(merkle_display_from_chunked(
+                        &format!("edge {}→{}", opening.from, opening.to),
+                        proof,
+                    ))
                 });
                 visualizer.set_merkle(merkle)?;
                 visualizer.log(format!(
@@ -736,9 +1459,30 @@ fn run_visualize_web(instance_path: PathBuf, rounds: u32, port: u16) -> CliResul
                     status,
                     challenge.edge_indices.len()
                 ))?;
-                if !verified {
+                if let Err(err) = verified {
+                    visualizer.finish().ok();
+                    return Err(err.in_round(round).into());
+                }
+            }
+            Challenge::Permutation(challenge) => {
+                let response = prover.respond_to_permutation_challenge(&challenge);
+                let verified = verifier.verify_permutation_response(&challenge, &response);
+                let status = round_status(&verified);
+                visualizer.update_round(RoundSnapshot {
+                    round: Some(round),
+                    phase: "permutation challenge".to_string(),
+                    detail: format!("entries opened: {}", response.entries.len()),
+                    status: status.clone(),
+                })?;
+                visualizer.set_focus(None)?;
+                visualizer.set_merkle(None)?;
+                visualizer.log(format!(
+                    "Round {}: permutation challenge {status}",
+                    round + 1
+                ))?;
+                if let Err(err) = verified {
                     visualizer.finish().ok();
-                    return Err(format!("blank response rejected in round {round}").into());
+                    return Err(err.in_round(round).into());
                 }
             }
         }
@@ -751,90 +1495,161 @@ fn run_visualize_web(instance_path: PathBuf, rounds: u32, port: u16) -> CliResul
     Ok(())
 }
 
-fn construct_transcript(instance: &GraphInstance, rounds: u32) -> CliResult<ProofTranscript> {
-    let verifier_cfg = VerifierConfig {
-        rounds,
-        ..Default::default()
-    };
-    construct_transcript_with_config(instance, &verifier_cfg)
+fn round_status(result: &Result<(), VerificationError>) -> String {
+    match result {
+        Ok(()) => "verified".to_string(),
+        Err(err) => format!("rejected ({err})"),
+    }
 }
 
 fn construct_transcript_with_config(
     instance: &GraphInstance,
     verifier_cfg: &VerifierConfig,
+    prover_cfg: &ProverConfig,
+    mode: TranscriptMode,
+    session: &SessionNonce,
 ) -> CliResult<ProofTranscript> {
     let mut prover = ProverState::new(instance.graph.clone(), instance.coloration.clone());
-    let mut verifier = Verifier::new(instance.coloration.clone(), verifier_cfg.clone());
+    let mut verifier = Verifier::new(instance.coloration.clone(), verifier_cfg.clone())
+        .with_session(session.clone())
+        .with_hash(prover_cfg.hash);
+    let hasher = prover_cfg.hash;
 
-    let config = ProverConfig::default();
-    let commitments = prover.commit(&config);
+    let commitments = prover.try_commit(prover_cfg)?;
     verifier.receive_commitments(commitments.clone());
+    let mut fiat_shamir = (mode == TranscriptMode::FiatShamir)
+        .then(|| FiatShamirTranscript::new(session, &commitments, &hasher));
 
     let mut records = Vec::with_capacity(verifier_cfg.rounds as usize);
     for round_idx in 0..verifier_cfg.rounds {
-        let challenge = verifier.generate_challenge(round_idx);
+        let recommitment = if prover_cfg.recommits_before(round_idx) {
+            let fresh = prover.try_commit(prover_cfg)?;
+            verifier.receive_commitments(fresh.clone());
+            Some(fresh)
+        } else {
+            None
+        };
+        let challenge = match &fiat_shamir {
+            Some(fs) => verifier.derive_challenge(&fs.challenge_seed(round_idx, &hasher)),
+            None => verifier.generate_challenge(round_idx),
+        };
         let response = match &challenge {
             Challenge::Spot(ch) => {
                 let resp = prover.respond_to_spot_challenge(ch);
-                if !verifier.verify_spot_response(ch, &resp) {
-                    return Err(format!("spot response rejected in round {round_idx}").into());
-                }
+                verifier
+                    .verify_spot_response(ch, &resp)
+                    .map_err(|err| err.in_round(round_idx))?;
                 TranscriptResponse::Spot(resp)
             }
             Challenge::Blank(ch) => {
                 let resp = prover.respond_to_blank_challenge(ch);
-                if !verifier.verify_blank_response(ch, &resp) {
-                    return Err(format!("blank response rejected in round {round_idx}").into());
-                }
+                verifier
+                    .verify_blank_response(ch, &resp)
+                    .map_err(|err| err.in_round(round_idx))?;
                 TranscriptResponse::Blank(resp)
             }
+            Challenge::Permutation(ch) => {
+                let resp = prover.respond_to_permutation_challenge(ch);
+                verifier
+                    .verify_permutation_response(ch, &resp)
+                    .map_err(|err| err.in_round(round_idx))?;
+                TranscriptResponse::Permutation(resp)
+            }
         };
+        if let Some(fs) = fiat_shamir.as_mut() {
+            fs.absorb_response(&response, &hasher);
+        }
         records.push(TranscriptRound {
+            recommitment,
             challenge: challenge.clone(),
             response,
         });
     }
 
     Ok(ProofTranscript {
+        mode,
+        session: session.clone(),
+        hash: prover_cfg.hash,
         commitments,
         rounds: records,
     })
 }
 
-fn replay_transcript(instance: &GraphInstance, transcript: &ProofTranscript) -> CliResult<()> {
-    let verifier_cfg = VerifierConfig {
-        rounds: transcript.rounds.len() as u32,
-        ..Default::default()
-    };
-    replay_transcript_with_config(instance, transcript, &verifier_cfg)
+/// A verifier for `statement`, using the hash and STARK parameters it names.
+fn statement_verifier(
+    statement: &PublicStatement,
+    verifier_cfg: VerifierConfig,
+) -> CliResult<Verifier> {
+    Ok(Verifier::new(statement.coloration.clone(), verifier_cfg)
+        .with_stark_parameters(statement.stark_parameters()?)
+        .with_hash(statement.hash()?))
 }
 
-fn replay_transcript_with_config(
-    instance: &GraphInstance,
+/// Checks a Fiat–Shamir transcript offline as the verifier of `session`. The round count
+/// comes from the verifier, and every challenge is re-derived: an interactive transcript
+/// only records challenges its author chose, so it proves nothing to a third party.
+/// Recommitments are rejected, since a prover could retry each one until the challenge
+/// derived after it is one it can answer.
+fn replay_transcript(
+    statement: &PublicStatement,
     transcript: &ProofTranscript,
     verifier_cfg: &VerifierConfig,
+    session: SessionNonce,
 ) -> CliResult<()> {
-    let mut verifier = Verifier::new(instance.coloration.clone(), verifier_cfg.clone());
+    if transcript.mode != TranscriptMode::FiatShamir {
+        return Err(
+            "only Fiat-Shamir transcripts can be verified offline (prove with --non-interactive)"
+                .into(),
+        );
+    }
+    if transcript.rounds.len() != verifier_cfg.rounds as usize {
+        return Err(format!(
+            "transcript has {} rounds, expected {}",
+            transcript.rounds.len(),
+            verifier_cfg.rounds
+        )
+        .into());
+    }
+    if let Some(idx) = transcript
+        .rounds
+        .iter()
+        .position(|round| round.recommitment.is_some())
+    {
+        return Err(format!(
+            "recommitment before round {idx}: Fiat-Shamir transcripts use a single commitment"
+        )
+        .into());
+    }
+    let mut verifier = statement_verifier(statement, verifier_cfg.clone())?.with_session(session);
+    verifier.check_session(&transcript.session)?;
+    verifier.check_statement(&transcript.commitments)?;
     verifier.receive_commitments(transcript.commitments.clone());
+    let hasher = verifier.hash();
+    let mut fiat_shamir =
+        FiatShamirTranscript::new(&transcript.session, &transcript.commitments, &hasher);
 
     for (idx, round) in transcript.rounds.iter().enumerate() {
-        match (&round.challenge, &round.response) {
+        let expected = verifier.derive_challenge(&fiat_shamir.challenge_seed(idx as u32, &hasher));
+        if expected != round.challenge {
+            return Err(format!(
+                "challenge in round {idx} does not match the Fiat-Shamir derivation"
+            )
+            .into());
+        }
+        fiat_shamir.absorb_response(&round.response, &hasher);
+        let verified = match (&round.challenge, &round.response) {
             (Challenge::Spot(ch), TranscriptResponse::Spot(resp)) => {
-                if !verifier.verify_spot_response(ch, resp) {
-                    return Err(format!("spot verification failed in round {idx}").into());
-                }
+                verifier.verify_spot_response(ch, resp)
             }
             (Challenge::Blank(ch), TranscriptResponse::Blank(resp)) => {
-                if !verifier.verify_blank_response(ch, resp) {
-                    return Err(format!("blank verification failed in round {idx}").into());
-                }
+                verifier.verify_blank_response(ch, resp)
             }
-            _ => {
-                return Err(
-                    format!("challenge/response mismatch encountered in round {idx}").into(),
-                );
+            (Challenge::Permutation(ch), TranscriptResponse::Permutation(resp)) => {
+                verifier.verify_permutation_response(ch, resp)
             }
-        }
+            _ => Err(VerificationError::ResponseKindMismatch),
+        };
+        verified.map_err(|err| err.in_round(idx as u32))?;
     }
 
     Ok(())
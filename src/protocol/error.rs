@@ -0,0 +1,348 @@
+use crate::crypto::hash::HashBackend;
+use crate::crypto::merkle::MerkleError;
+use crate::stark::verifier::StarkError;
+use std::fmt;
+
+/// Why a [`crate::protocol::verifier::Verifier`] rejected a response. Edges are
+/// `(from, to)` pairs in the committed (permuted) graph.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationError {
+    /// A response arrived before any commitments.
+    MissingCommitments,
+    /// The transcript was recorded under another verifier's session nonce.
+    SessionMismatch,
+    /// The commitments were made for a different statement or parameter set.
+    StatementMismatch,
+    /// The prover committed with another hash backend.
+    HashMismatch {
+        expected: HashBackend,
+        actual: HashBackend,
+    },
+    /// The response answers a different kind of challenge.
+    ResponseKindMismatch,
+    ResponseCountMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    SpotNodesMismatch {
+        expected: [u32; 3],
+        actual: [u32; 3],
+    },
+    /// A spot opening answers another edge than the next pair of the spot's nodes.
+    SpotEdgeMismatch {
+        nodes: [u32; 3],
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    SpotEdgeOpening {
+        nodes: [u32; 3],
+        edge: (u32, u32),
+        source: MerkleError,
+    },
+    /// The batched opening of the spot edges does not authenticate against `graph_root`.
+    SpotMultiproof {
+        source: MerkleError,
+    },
+    /// The opened spot is not an allowed pattern of the coloration set.
+    PatternNotAllowed {
+        nodes: [u32; 3],
+        /// Canonical color encoding, see [`crate::graph::ColorationSet::spot_to_key`].
+        pattern: [u8; 9],
+    },
+    BlankEdgeIndexMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    BlankEdgeOutOfBounds {
+        edge_index: u64,
+    },
+    BlankEndpointsMismatch {
+        edge_index: u64,
+        edge: (u32, u32),
+    },
+    /// The opening does not authenticate against `graph_root`.
+    BlankEdgeOpening {
+        edge_index: u64,
+        edge: (u32, u32),
+        source: MerkleError,
+    },
+    /// The opening does not authenticate against `blank_root`.
+    BlankBitOpening {
+        edge_index: u64,
+        source: MerkleError,
+    },
+    /// The batched graph-tree opening of the challenged edges was rejected.
+    BlankEdgeMultiproof {
+        source: MerkleError,
+    },
+    /// The batched blank-tree opening of the challenged edges was rejected.
+    BlankBitMultiproof {
+        source: MerkleError,
+    },
+    BlankLimitMismatch {
+        committed: u32,
+        expected: u32,
+    },
+    TraceRootMismatch,
+    BlankCountProof(StarkError),
+    /// The committed blank-count trace disagrees with the opened blank bits.
+    TraceValues(StarkError),
+    PermutationEntryOutOfOrder {
+        position: usize,
+    },
+    PermutationValueInvalid {
+        position: usize,
+        value: u32,
+    },
+    PermutationOpening {
+        position: usize,
+        source: MerkleError,
+    },
+    /// Permutation rounds were asked for, but the prover answers several rounds per
+    /// commitment, so an opened permutation would relabel the spots of other rounds.
+    SharedPermutationCommitment {
+        rounds_per_commitment: Option<u32>,
+    },
+    /// Any of the above, in protocol round `round`.
+    Round {
+        round: u32,
+        source: Box<VerificationError>,
+    },
+}
+
+impl VerificationError {
+    pub fn in_round(self, round: u32) -> Self {
+        VerificationError::Round {
+            round,
+            source: Box::new(self),
+        }
+    }
+
+    pub fn round(&self) -> Option<u32> {
+        match self {
+            VerificationError::Round { round, .. } => Some(*round),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationError::MissingCommitments => write!(f, "no commitments received"),
+            VerificationError::SessionMismatch => {
+                write!(f, "transcript belongs to a different session")
+            }
+            VerificationError::StatementMismatch => {
+                write!(f, "commitments were made for a different statement")
+            }
+            VerificationError::HashMismatch { expected, actual } => {
+                write!(f, "commitments use hash {}, expected {}", actual, expected)
+            }
+            VerificationError::ResponseKindMismatch => {
+                write!(f, "response does not match the challenge type")
+            }
+            VerificationError::ResponseCountMismatch { expected, actual } => write!(
+                f,
+                "expected {} openings in the response, got {}",
+                expected, actual
+            ),
+            VerificationError::SpotNodesMismatch { expected, actual } => {
+                write!(f, "spot {:?} answered with nodes {:?}", expected, actual)
+            }
+            VerificationError::SpotEdgeMismatch {
+                nodes,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "spot {:?}: expected edge ({}, {}), got ({}, {})",
+                nodes, expected.0, expected.1, actual.0, actual.1
+            ),
+            VerificationError::SpotEdgeOpening {
+                nodes,
+                edge,
+                source,
+            } => write!(
+                f,
+                "spot {:?}: edge ({}, {}) rejected: {}",
+                nodes, edge.0, edge.1, source
+            ),
+            VerificationError::SpotMultiproof { source } => {
+                write!(f, "batched spot opening rejected: {}", source)
+            }
+            VerificationError::PatternNotAllowed { nodes, pattern } => write!(
+                f,
+                "spot {:?} has pattern {:?}, which is not in the coloration set",
+                nodes, pattern
+            ),
+            VerificationError::BlankEdgeIndexMismatch { expected, actual } => write!(
+                f,
+                "blank opening for edge {} answers edge {}",
+                expected, actual
+            ),
+            VerificationError::BlankEdgeOutOfBounds { edge_index } => {
+                write!(f, "blank edge index {} is out of bounds", edge_index)
+            }
+            VerificationError::BlankEndpointsMismatch { edge_index, edge } => write!(
+                f,
+                "endpoints ({}, {}) do not match edge {}",
+                edge.0, edge.1, edge_index
+            ),
+            VerificationError::BlankEdgeOpening {
+                edge_index,
+                edge,
+                source,
+            } => write!(
+                f,
+                "graph opening for edge {} ({}, {}) rejected: {}",
+                edge_index, edge.0, edge.1, source
+            ),
+            VerificationError::BlankBitOpening { edge_index, source } => write!(
+                f,
+                "blank-tree opening for edge {} rejected: {}",
+                edge_index, source
+            ),
+            VerificationError::BlankEdgeMultiproof { source } => {
+                write!(
+                    f,
+                    "batched graph opening of the blank edges rejected: {}",
+                    source
+                )
+            }
+            VerificationError::BlankBitMultiproof { source } => {
+                write!(f, "batched blank-tree opening rejected: {}", source)
+            }
+            VerificationError::BlankLimitMismatch {
+                committed,
+                expected,
+            } => write!(
+                f,
+                "committed blank limit {} differs from the public {}",
+                committed, expected
+            ),
+            VerificationError::TraceRootMismatch => {
+                write!(f, "STARK trace root differs from the committed one")
+            }
+            VerificationError::BlankCountProof(err) => {
+                write!(f, "blank-count STARK rejected: {}", err)
+            }
+            VerificationError::TraceValues(err) => {
+                write!(
+                    f,
+                    "blank-count trace disagrees with the opened bits: {}",
+                    err
+                )
+            }
+            VerificationError::PermutationEntryOutOfOrder { position } => write!(
+                f,
+                "permutation entry {} is out of order or outside an n-leaf tree",
+                position
+            ),
+            VerificationError::PermutationValueInvalid { position, value } => write!(
+                f,
+                "permutation entry {} maps to {}, repeated or out of range",
+                position, value
+            ),
+            VerificationError::PermutationOpening { position, source } => {
+                write!(f, "permutation entry {} rejected: {}", position, source)
+            }
+            VerificationError::SharedPermutationCommitment {
+                rounds_per_commitment,
+            } => match rounds_per_commitment {
+                Some(every) => write!(
+                    f,
+                    "permutation rounds need a commitment per round, not one per {} rounds",
+                    every
+                ),
+                None => write!(f, "permutation rounds need a commitment per round, not one"),
+            },
+            VerificationError::Round { round, source } => {
+                write!(f, "round {}: {}", round, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VerificationError::SpotEdgeOpening { source, .. }
+            | VerificationError::BlankEdgeOpening { source, .. }
+            | VerificationError::BlankBitOpening { source, .. }
+            | VerificationError::SpotMultiproof { source }
+            | VerificationError::BlankEdgeMultiproof { source }
+            | VerificationError::BlankBitMultiproof { source }
+            | VerificationError::PermutationOpening { source, .. } => Some(source),
+            VerificationError::BlankCountProof(err) | VerificationError::TraceValues(err) => {
+                Some(err)
+            }
+            VerificationError::Round { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Why a [`crate::protocol::prover::ProverState`] refuses to answer a challenge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChallengeError {
+    /// Nothing has been committed yet.
+    NotCommitted,
+    /// The permutation of a commitment that already answered other rounds was asked for.
+    /// Opening it would relabel the spots and edges those rounds revealed.
+    CommitmentInUse,
+    /// The commitment's permutation has been opened, so any further opening under it
+    /// would be revealed in the original labelling.
+    PermutationRevealed,
+    /// The challenge asks for more spots or blank checks than one round may hold.
+    TooManyChecks {
+        requested: usize,
+        limit: u32,
+    },
+    NodeOutOfRange {
+        node: u32,
+        nodes: u32,
+    },
+    /// A spot must name three distinct nodes.
+    RepeatedSpotNode {
+        spot: [u32; 3],
+    },
+    EdgeOutOfRange {
+        edge_index: u64,
+        edges: u64,
+    },
+}
+
+impl fmt::Display for ChallengeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChallengeError::NotCommitted => write!(f, "no commitment to answer against"),
+            ChallengeError::CommitmentInUse => write!(
+                f,
+                "the commitment already answered other rounds, so its permutation stays closed"
+            ),
+            ChallengeError::PermutationRevealed => write!(
+                f,
+                "the commitment's permutation was opened, so it answers no further rounds"
+            ),
+            ChallengeError::TooManyChecks { requested, limit } => write!(
+                f,
+                "{} checks requested, at most {} per round",
+                requested, limit
+            ),
+            ChallengeError::NodeOutOfRange { node, nodes } => {
+                write!(f, "node {} is outside the {}-node graph", node, nodes)
+            }
+            ChallengeError::RepeatedSpotNode { spot } => {
+                write!(f, "spot {:?} repeats a node", spot)
+            }
+            ChallengeError::EdgeOutOfRange { edge_index, edges } => write!(
+                f,
+                "edge index {} is outside the {} edge slots",
+                edge_index, edges
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChallengeError {}
@@ -0,0 +1,62 @@
+use crate::crypto::hash::QuantumHash;
+use crate::protocol::messages::{Commitments, SessionNonce};
+use serde::Serialize;
+
+const DOMAIN_TAG: &[u8] = b"zkp-c-coloring/fiat-shamir/v1";
+
+/// Running hash over everything the prover has sent so far. Each round's challenge seed
+/// is squeezed from the current state, so changing any earlier commitment or response
+/// changes every later challenge.
+#[derive(Debug, Clone)]
+pub struct FiatShamirTranscript {
+    state: [u8; 32],
+}
+
+impl FiatShamirTranscript {
+    /// Starts from the verifier's `session` so that a proof is only valid for it.
+    pub fn new(
+        session: &SessionNonce,
+        commitments: &Commitments,
+        hasher: &dyn QuantumHash,
+    ) -> Self {
+        let mut transcript = FiatShamirTranscript {
+            state: hasher.hash(DOMAIN_TAG),
+        };
+        transcript.absorb(b"session", &session.encode(), hasher);
+        transcript.absorb_commitments(commitments, hasher);
+        transcript
+    }
+
+    fn absorb_commitments(&mut self, commitments: &Commitments, hasher: &dyn QuantumHash) {
+        let mut data = Vec::with_capacity(164);
+        data.extend_from_slice(&commitments.statement_digest);
+        data.extend_from_slice(&commitments.graph_root);
+        data.extend_from_slice(&commitments.permutation_root);
+        data.extend_from_slice(&commitments.blank_root);
+        data.extend_from_slice(&commitments.blank_trace_root);
+        data.extend_from_slice(&commitments.blank_limit.to_be_bytes());
+        self.absorb(b"commitments", &data, hasher);
+    }
+
+    pub fn absorb_response<T: Serialize>(&mut self, response: &T, hasher: &dyn QuantumHash) {
+        let bytes = bincode::serialize(response).expect("protocol responses serialize");
+        self.absorb(b"response", &bytes, hasher);
+    }
+
+    pub fn challenge_seed(&self, round: u32, hasher: &dyn QuantumHash) -> [u8; 32] {
+        let mut data = Vec::with_capacity(32 + 9 + 4);
+        data.extend_from_slice(&self.state);
+        data.extend_from_slice(b"challenge");
+        data.extend_from_slice(&round.to_be_bytes());
+        hasher.hash(&data)
+    }
+
+    fn absorb(&mut self, label: &[u8], bytes: &[u8], hasher: &dyn QuantumHash) {
+        let mut data = Vec::with_capacity(32 + label.len() + 8 + bytes.len());
+        data.extend_from_slice(&self.state);
+        data.extend_from_slice(label);
+        data.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+        data.extend_from_slice(bytes);
+        self.state = hasher.hash(&data);
+    }
+}
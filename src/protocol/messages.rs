@@ -1,34 +1,111 @@
-use crate::crypto::merkle::ChunkedMerkleProof;
-use crate::graph::Color;
-use crate::stark::prover::BlankCountProof;
+use crate::crypto::hash::{HashBackend, LeafSalt, QuantumHash};
+use crate::crypto::merkle::{ChunkedMerkleMultiProof, ChunkedMerkleProof, MerkleProof};
+use crate::graph::{Color, ColorationSet};
+use crate::stark::prover::{BlankCountProof, ColumnValueProof, StarkParameters};
+use crate::stark::StarkField;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
+/// Verifier-chosen freshness for one protocol session, sent to the prover before it
+/// commits. Every challenge seed absorbs it, so challenges cannot be precomputed and
+/// transcripts of different sessions cannot be swapped. `context` optionally names the
+/// session (an application or request identifier).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionNonce {
+    pub nonce: [u8; 32],
+    pub context: Option<String>,
+}
+
+impl SessionNonce {
+    pub fn random(context: Option<String>) -> Self {
+        SessionNonce {
+            nonce: rand::rng().random(),
+            context,
+        }
+    }
+
+    /// Unambiguous byte encoding absorbed into challenge seeds.
+    pub fn encode(&self) -> Vec<u8> {
+        let context = self.context.as_deref().unwrap_or_default().as_bytes();
+        let mut data = Vec::with_capacity(32 + 9 + context.len());
+        data.extend_from_slice(&self.nonce);
+        data.push(self.context.is_some() as u8);
+        data.extend_from_slice(&(context.len() as u64).to_be_bytes());
+        data.extend_from_slice(context);
+        data
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Commitments {
     pub graph_root: [u8; 32],
     pub permutation_root: [u8; 32],
     pub blank_root: [u8; 32],
-    pub blank_count: u32,
+    /// Root of the blank-count STARK trace. Blank rounds show that its value column
+    /// agrees with the bits opened under `blank_root`.
+    pub blank_trace_root: [u8; 32],
+    /// Public blank budget. The STARK proves the committed graph has at most this many
+    /// blank edges without revealing the actual count.
+    pub blank_limit: u32,
+    /// [`statement_digest`] of the statement the prover committed against.
+    pub statement_digest: [u8; 32],
+    /// Hash the roots and openings were computed with.
+    pub hash: HashBackend,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+const STATEMENT_DOMAIN_TAG: &[u8] = b"zkp-c-coloring/statement/v1";
+
+/// Canonical digest of a public statement: graph size, blank limit, the sorted allowed
+/// patterns, the blank-count STARK parameters and the id of `hasher`, which also
+/// computes it.
+pub fn statement_digest(
+    coloration: &ColorationSet,
+    stark: &StarkParameters,
+    hasher: &dyn QuantumHash,
+) -> [u8; 32] {
+    let patterns = coloration.patterns();
+    let stark = bincode::serialize(stark).expect("STARK parameters serialize");
+    let hash_id = hasher.id().as_bytes();
+    let mut data = STATEMENT_DOMAIN_TAG.to_vec();
+    data.extend_from_slice(&(hash_id.len() as u64).to_be_bytes());
+    data.extend_from_slice(hash_id);
+    data.extend_from_slice(&coloration.graph_size().to_be_bytes());
+    data.extend_from_slice(&coloration.blank_limit().to_be_bytes());
+    data.extend_from_slice(&(patterns.len() as u64).to_be_bytes());
+    for pattern in &patterns {
+        data.extend_from_slice(pattern);
+    }
+    data.extend_from_slice(&(stark.len() as u64).to_be_bytes());
+    data.extend_from_slice(&stark);
+    hasher.hash(&data)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Challenge {
     Spot(SpotChallenge),
     Blank(BlankChallenge),
+    Permutation(PermutationChallenge),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SpotChallenge {
     pub spots: Vec<[u32; 3]>,
     pub seed: [u8; 32],
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BlankChallenge {
     pub edge_indices: Vec<u64>,
     pub seed: [u8; 32],
 }
 
+/// Asks the prover to open the full permutation behind `permutation_root`, showing that
+/// the committed graph is a relabelling of the public structure.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermutationChallenge {
+    pub seed: [u8; 32],
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpotResponse {
     pub nodes: [u32; 3],
@@ -40,27 +117,74 @@ pub struct SpotEdgeOpening {
     pub from: u32,
     pub to: u32,
     pub color: Color,
-    pub proof: ChunkedMerkleProof,
+    pub color_salt: LeafSalt,
+    pub blank_salt: LeafSalt,
+    /// `None` when the response authenticates its edges with a batched multiproof.
+    pub proof: Option<ChunkedMerkleProof>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpotChallengeResponse {
     pub responses: Vec<SpotResponse>,
+    /// Opens every edge of every spot against `graph_root` at once, in place of the
+    /// per-edge proofs.
+    pub multiproof: Option<ChunkedMerkleMultiProof>,
 }
 
+/// Opens the challenged edges, in challenge order.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlankChallengeResponse {
     pub edges: Vec<BlankEdgeOpening>,
     pub stark_proof: BlankCountProof,
+    /// Opens the STARK trace's value column at every challenged edge index, so the
+    /// opened blank bits are the ones the STARK counted. Only the challenged edges are
+    /// tied to `blank_root` this way; elsewhere the trace is bound by the STARK alone.
+    pub trace_values: ColumnValueProof,
+    /// Batched openings of every opened edge, in place of the per-edge proofs.
+    pub multiproofs: Option<BlankMultiproofs>,
+}
+
+/// The graph-tree and blank-tree halves of a batched [`BlankChallengeResponse`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlankMultiproofs {
+    pub edges: ChunkedMerkleMultiProof,
+    pub blanks: ChunkedMerkleMultiProof,
 }
 
+/// Opens only the blank field of an edge leaf. The color stays hidden behind
+/// `color_digest`, so non-blank edges reveal nothing beyond "not blank".
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlankEdgeOpening {
     pub edge_index: u64,
     pub from: u32,
     pub to: u32,
-    pub color: Color,
     pub is_blank: bool,
-    pub color_proof: ChunkedMerkleProof,
-    pub blank_proof: ChunkedMerkleProof,
+    pub color_digest: [u8; 32],
+    pub blank_salt: LeafSalt,
+    /// `None` (as is `blank_proof`) when the response carries [`BlankMultiproofs`].
+    pub edge_proof: Option<ChunkedMerkleProof>,
+    pub blank_proof: Option<ChunkedMerkleProof>,
+}
+
+impl BlankEdgeOpening {
+    /// The `(row, value)` this opening claims for the STARK trace's value column.
+    pub fn value_claim(&self) -> (usize, StarkField) {
+        (
+            self.edge_index as usize,
+            StarkField::from(self.is_blank as u64),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermutationChallengeResponse {
+    pub entries: Vec<PermutationOpening>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermutationOpening {
+    pub index: u32,
+    pub value: u32,
+    pub salt: LeafSalt,
+    pub proof: MerkleProof,
 }
@@ -1,7 +1,14 @@
+pub mod error;
+pub mod fiat_shamir;
 pub mod messages;
+pub mod network;
 pub mod prover;
+pub mod soundness;
 pub mod verifier;
 
-pub use messages::{BlankChallenge, Challenge, Commitments, SpotChallenge};
+pub use error::{ChallengeError, VerificationError};
+pub use fiat_shamir::FiatShamirTranscript;
+pub use messages::{BlankChallenge, Challenge, Commitments, PermutationChallenge, SpotChallenge};
 pub use prover::{ProverConfig, ProverState};
+pub use soundness::SoundnessEstimate;
 pub use verifier::Verifier;
@@ -0,0 +1,452 @@
+use crate::protocol::error::{ChallengeError, VerificationError};
+use crate::protocol::messages::{Challenge, Commitments, SessionNonce};
+use crate::protocol::prover::{ProverConfig, ProverState};
+use crate::protocol::soundness::MAX_ROUNDS;
+use crate::protocol::verifier::Verifier;
+use crate::utils::serialization::{
+    ProofTranscript, TranscriptMode, TranscriptResponse, TranscriptRound,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+/// Wire protocol version spoken by this build. Version 2 added the session nonce to
+/// `Start`, version 3 the statement digest and version 4 the hash backend to
+/// `Commitments`, version 5 optional multiproofs to spot and blank responses, version
+/// 6 Merkle proofs of any arity and version 7 dropped the permutation commitment and its
+/// opening round, version 8 added query-bound edges to blank responses, version 9 the
+/// chunk size to single-leaf Merkle proofs and the edge index to blank-tree leaves,
+/// version 10 restored the permutation commitment and its opening round and version 11
+/// dropped the query-bound edges again; older peers are no longer accepted.
+pub const PROTOCOL_VERSION: u32 = 11;
+/// Versions this build can still talk to, newest last.
+pub const SUPPORTED_VERSIONS: &[u32] = &[PROTOCOL_VERSION];
+/// Frames above this size are refused before their payload is read.
+pub const MAX_FRAME_BYTES: u32 = 64 * 1024 * 1024;
+
+/// One frame of the interactive protocol: a big-endian `u32` length followed by the
+/// bincode encoding of this enum.
+///
+/// The verifier opens with `Hello`, the prover answers `Welcome` with the negotiated
+/// version and its recommitment schedule (or `Abort`), the verifier sends `Start` with
+/// its session nonce, and only then does the prover send its `Commitments`. Each round
+/// the prover first sends fresh `Commitments` if its schedule recommits, then the
+/// verifier sends a `Challenge` and the prover its `Response`. The verifier ends the
+/// session with a `Verdict`.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WireMessage {
+    Hello {
+        versions: Vec<u32>,
+    },
+    Welcome {
+        version: u32,
+        rounds_per_commitment: Option<u32>,
+    },
+    Start {
+        rounds: u32,
+        session: SessionNonce,
+    },
+    Commitments(Commitments),
+    Challenge {
+        round: u32,
+        challenge: Challenge,
+    },
+    Response(TranscriptResponse),
+    Verdict {
+        accepted: bool,
+        reason: Option<String>,
+    },
+    Abort {
+        reason: String,
+    },
+}
+
+impl WireMessage {
+    fn kind(&self) -> &'static str {
+        match self {
+            WireMessage::Hello { .. } => "Hello",
+            WireMessage::Welcome { .. } => "Welcome",
+            WireMessage::Start { .. } => "Start",
+            WireMessage::Commitments(_) => "Commitments",
+            WireMessage::Challenge { .. } => "Challenge",
+            WireMessage::Response(_) => "Response",
+            WireMessage::Verdict { .. } => "Verdict",
+            WireMessage::Abort { .. } => "Abort",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(io::Error),
+    Timeout,
+    Codec(String),
+    FrameTooLarge(u32),
+    /// The peers share no protocol version.
+    VersionMismatch {
+        offered: Vec<u32>,
+        supported: Vec<u32>,
+    },
+    UnexpectedMessage {
+        expected: &'static str,
+        actual: &'static str,
+    },
+    /// The peer aborted the session.
+    Aborted(String),
+    /// The verifier rejected a response or the prover's schedule.
+    Verification(VerificationError),
+    /// The verifier asked for more rounds than a session allows.
+    TooManyRounds(u32),
+    /// The prover refused to answer a challenge.
+    Refused(ChallengeError),
+    /// The remote verifier rejected the session.
+    Rejected(String),
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Io(err) => write!(f, "connection error: {}", err),
+            NetworkError::Timeout => write!(f, "peer timed out"),
+            NetworkError::Codec(err) => write!(f, "malformed frame: {}", err),
+            NetworkError::FrameTooLarge(len) => {
+                write!(f, "frame of {} bytes exceeds {}", len, MAX_FRAME_BYTES)
+            }
+            NetworkError::VersionMismatch { offered, supported } => write!(
+                f,
+                "no common protocol version (offered {:?}, supported {:?})",
+                offered, supported
+            ),
+            NetworkError::UnexpectedMessage { expected, actual } => {
+                write!(f, "expected {}, received {}", expected, actual)
+            }
+            NetworkError::Aborted(reason) => write!(f, "peer aborted: {}", reason),
+            NetworkError::Verification(err) => write!(f, "{}", err),
+            NetworkError::TooManyRounds(rounds) => write!(
+                f,
+                "{} rounds requested, at most {} per session",
+                rounds, MAX_ROUNDS
+            ),
+            NetworkError::Refused(err) => write!(f, "challenge refused: {}", err),
+            NetworkError::Rejected(reason) => write!(f, "verifier rejected the proof: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NetworkError::Io(err) => Some(err),
+            NetworkError::Verification(err) => Some(err),
+            NetworkError::Refused(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for NetworkError {
+    fn from(err: io::Error) -> Self {
+        NetworkError::Io(err)
+    }
+}
+
+/// Framed, timed connection to the other party.
+pub struct Connection {
+    stream: TcpStream,
+    timeout: Duration,
+}
+
+impl Connection {
+    /// Every read and write fails with [`NetworkError::Timeout`] after `timeout`.
+    pub fn new(stream: TcpStream, timeout: Duration) -> Self {
+        Connection { stream, timeout }
+    }
+
+    pub async fn send(&mut self, message: &WireMessage) -> Result<(), NetworkError> {
+        let payload =
+            bincode::serialize(message).map_err(|err| NetworkError::Codec(err.to_string()))?;
+        if payload.len() > MAX_FRAME_BYTES as usize {
+            let len = u32::try_from(payload.len()).unwrap_or(u32::MAX);
+            return Err(NetworkError::FrameTooLarge(len));
+        }
+        let len = payload.len() as u32;
+        let write = async {
+            self.stream.write_all(&len.to_be_bytes()).await?;
+            self.stream.write_all(&payload).await?;
+            self.stream.flush().await
+        };
+        timeout(self.timeout, write)
+            .await
+            .map_err(|_| NetworkError::Timeout)??;
+        Ok(())
+    }
+
+    pub async fn receive(&mut self) -> Result<WireMessage, NetworkError> {
+        let read = async {
+            let mut header = [0u8; 4];
+            self.stream.read_exact(&mut header).await?;
+            let len = u32::from_be_bytes(header);
+            if len > MAX_FRAME_BYTES {
+                return Err(NetworkError::FrameTooLarge(len));
+            }
+            let mut payload = vec![0u8; len as usize];
+            self.stream.read_exact(&mut payload).await?;
+            bincode::deserialize(&payload).map_err(|err| NetworkError::Codec(err.to_string()))
+        };
+        match timeout(self.timeout, read).await {
+            Ok(Ok(WireMessage::Abort { reason })) => Err(NetworkError::Aborted(reason)),
+            Ok(result) => result,
+            Err(_) => Err(NetworkError::Timeout),
+        }
+    }
+
+    /// Tells the peer why the session ends, ignoring failures to do so.
+    async fn abort(&mut self, reason: String) {
+        let _ = self.send(&WireMessage::Abort { reason }).await;
+    }
+}
+
+/// Highest version both sides support.
+pub fn negotiate_version(offered: &[u32], supported: &[u32]) -> Option<u32> {
+    offered
+        .iter()
+        .copied()
+        .filter(|version| supported.contains(version))
+        .max()
+}
+
+fn unexpected(expected: &'static str, actual: &WireMessage) -> NetworkError {
+    NetworkError::UnexpectedMessage {
+        expected,
+        actual: actual.kind(),
+    }
+}
+
+/// Runs the prover's side of one session and returns the transcript it saw. The prover
+/// commits afresh for every session. The verifier is not trusted: a session of more than
+/// `MAX_ROUNDS` rounds, or a challenge [`ProverState::check_challenge`] refuses, is
+/// aborted instead of answered.
+pub async fn serve_prover_session(
+    connection: &mut Connection,
+    prover: &mut ProverState,
+    config: &ProverConfig,
+) -> Result<ProofTranscript, NetworkError> {
+    let offered = match connection.receive().await? {
+        WireMessage::Hello { versions } => versions,
+        other => return Err(unexpected("Hello", &other)),
+    };
+    let Some(version) = negotiate_version(&offered, SUPPORTED_VERSIONS) else {
+        let err = NetworkError::VersionMismatch {
+            offered,
+            supported: SUPPORTED_VERSIONS.to_vec(),
+        };
+        connection.abort(err.to_string()).await;
+        return Err(err);
+    };
+    connection
+        .send(&WireMessage::Welcome {
+            version,
+            rounds_per_commitment: config.rounds_per_commitment,
+        })
+        .await?;
+    let (rounds, session) = match connection.receive().await? {
+        WireMessage::Start { rounds, session } => (rounds, session),
+        other => return Err(unexpected("Start", &other)),
+    };
+    if rounds > MAX_ROUNDS {
+        let err = NetworkError::TooManyRounds(rounds);
+        connection.abort(err.to_string()).await;
+        return Err(err);
+    }
+
+    let commitments = prover.try_commit(config)?;
+    connection
+        .send(&WireMessage::Commitments(commitments.clone()))
+        .await?;
+    let mut records = Vec::new();
+    for round in 0..rounds {
+        let recommitment = if config.recommits_before(round) {
+            let fresh = prover.try_commit(config)?;
+            connection
+                .send(&WireMessage::Commitments(fresh.clone()))
+                .await?;
+            Some(fresh)
+        } else {
+            None
+        };
+        let challenge = match connection.receive().await? {
+            WireMessage::Challenge {
+                round: received,
+                challenge,
+            } if received == round => challenge,
+            other => return Err(unexpected("Challenge", &other)),
+        };
+        if let Err(err) = prover.check_challenge(&challenge) {
+            connection.abort(err.to_string()).await;
+            return Err(NetworkError::Refused(err));
+        }
+        let response = match &challenge {
+            Challenge::Spot(ch) => TranscriptResponse::Spot(prover.respond_to_spot_challenge(ch)),
+            Challenge::Blank(ch) => {
+                TranscriptResponse::Blank(prover.respond_to_blank_challenge(ch))
+            }
+            Challenge::Permutation(ch) => {
+                TranscriptResponse::Permutation(prover.respond_to_permutation_challenge(ch))
+            }
+        };
+        connection
+            .send(&WireMessage::Response(response.clone()))
+            .await?;
+        records.push(TranscriptRound {
+            recommitment,
+            challenge,
+            response,
+        });
+    }
+
+    match connection.receive().await? {
+        WireMessage::Verdict { accepted: true, .. } => Ok(ProofTranscript {
+            mode: TranscriptMode::Interactive,
+            session,
+            hash: config.hash,
+            commitments,
+            rounds: records,
+        }),
+        WireMessage::Verdict { reason, .. } => Err(NetworkError::Rejected(
+            reason.unwrap_or_else(|| "no reason given".to_string()),
+        )),
+        other => Err(unexpected("Verdict", &other)),
+    }
+}
+
+/// Runs `rounds` rounds of the verifier's side against a remote prover, checking each
+/// response as it arrives, and returns the transcript once every round verified. A
+/// rejection is reported to the prover before it is returned. Challenges come from
+/// [`Verifier::generate_challenge`], so the prover cannot know them before it commits.
+pub async fn run_remote_verifier(
+    connection: &mut Connection,
+    verifier: &mut Verifier,
+    rounds: u32,
+) -> Result<ProofTranscript, NetworkError> {
+    connection
+        .send(&WireMessage::Hello {
+            versions: SUPPORTED_VERSIONS.to_vec(),
+        })
+        .await?;
+    let schedule = match connection.receive().await? {
+        WireMessage::Welcome {
+            version,
+            rounds_per_commitment,
+        } if SUPPORTED_VERSIONS.contains(&version) => ProverConfig {
+            rounds_per_commitment,
+            ..ProverConfig::default()
+        },
+        WireMessage::Welcome { version, .. } => {
+            let err = NetworkError::VersionMismatch {
+                offered: vec![version],
+                supported: SUPPORTED_VERSIONS.to_vec(),
+            };
+            connection.abort(err.to_string()).await;
+            return Err(err);
+        }
+        other => return Err(unexpected("Welcome", &other)),
+    };
+    if let Err(err) = verifier
+        .configuration()
+        .check_schedule(schedule.rounds_per_commitment)
+    {
+        connection.abort(err.to_string()).await;
+        return Err(NetworkError::Verification(err));
+    }
+    connection
+        .send(&WireMessage::Start {
+            rounds,
+            session: verifier.session().clone(),
+        })
+        .await?;
+
+    let commitments = receive_commitments(connection).await?;
+    if let Err(err) = verifier.check_statement(&commitments) {
+        return Err(reject(connection, err).await);
+    }
+    verifier.receive_commitments(commitments.clone());
+    let mut records = Vec::with_capacity(rounds as usize);
+    for round in 0..rounds {
+        let recommitment = if schedule.recommits_before(round) {
+            let fresh = receive_commitments(connection).await?;
+            verifier.receive_commitments(fresh.clone());
+            Some(fresh)
+        } else {
+            None
+        };
+        let challenge = verifier.generate_challenge(round);
+        connection
+            .send(&WireMessage::Challenge {
+                round,
+                challenge: challenge.clone(),
+            })
+            .await?;
+        let response = match connection.receive().await? {
+            WireMessage::Response(response) => response,
+            other => return Err(unexpected("Response", &other)),
+        };
+        let verified = match (&challenge, &response) {
+            (Challenge::Spot(ch), TranscriptResponse::Spot(resp)) => {
+                verifier.verify_spot_response(ch, resp)
+            }
+            (Challenge::Blank(ch), TranscriptResponse::Blank(resp)) => {
+                verifier.verify_blank_response(ch, resp)
+            }
+            (Challenge::Permutation(ch), TranscriptResponse::Permutation(resp)) => {
+                verifier.verify_permutation_response(ch, resp)
+            }
+            _ => Err(VerificationError::ResponseKindMismatch),
+        };
+        if let Err(err) = verified {
+            return Err(reject(connection, err.in_round(round)).await);
+        }
+        records.push(TranscriptRound {
+            recommitment,
+            challenge,
+            response,
+        });
+    }
+
+    connection
+        .send(&WireMessage::Verdict {
+            accepted: true,
+            reason: None,
+        })
+        .await?;
+    Ok(ProofTranscript {
+        mode: TranscriptMode::Interactive,
+        session: verifier.session().clone(),
+        hash: verifier.hash(),
+        commitments,
+        rounds: records,
+    })
+}
+
+/// Sends the prover a rejecting verdict for `err`, which is returned unless the send fails.
+async fn reject(connection: &mut Connection, err: VerificationError) -> NetworkError {
+    let verdict = WireMessage::Verdict {
+        accepted: false,
+        reason: Some(err.to_string()),
+    };
+    match connection.send(&verdict).await {
+        Ok(()) => NetworkError::Verification(err),
+        Err(send_err) => send_err,
+    }
+}
+
+async fn receive_commitments(connection: &mut Connection) -> Result<Commitments, NetworkError> {
+    match connection.receive().await? {
+        WireMessage::Commitments(commitments) => Ok(commitments),
+        other => Err(unexpected("Commitments", &other)),
+    }
+}
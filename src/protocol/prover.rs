@@ -1,43 +1,147 @@
-use crate::crypto::hash::{default_quantum_hash, Blake3QuantumHash, QuantumHash};
-use crate::crypto::merkle::{ChunkedMerkleTree, GraphMerkleTree, MerkleTree};
+use crate::crypto::hash::{random_salts, salted_leaf, HashBackend, LeafSalt};
+use crate::crypto::merkle::{
+    edge_field_digest, encode_blank_leaf, encode_permutation_leaf, ChunkedMerkleTree,
+    GraphMerkleTree, MerkleArity, MerkleDomain, MerkleTree,
+};
+use crate::crypto::merkle_storage::MerkleStorage;
 use crate::crypto::polynomial::BlankPolynomial;
+use crate::crypto::proof_cache::{CacheStats, DEFAULT_CACHE_CAPACITY};
 use crate::graph::{Color, ColorationSet, Graph};
+use crate::protocol::error::ChallengeError;
 use crate::protocol::messages::{
-    BlankChallenge, BlankChallengeResponse, BlankEdgeOpening, Commitments, SpotChallenge,
-    SpotChallengeResponse, SpotEdgeOpening, SpotResponse,
+    statement_digest, BlankChallenge, BlankChallengeResponse, BlankEdgeOpening, BlankMultiproofs,
+    Challenge, Commitments, PermutationChallenge, PermutationChallengeResponse, PermutationOpening,
+    SpotChallenge, SpotChallengeResponse, SpotEdgeOpening, SpotResponse,
+};
+use crate::protocol::soundness::MAX_CHECKS_PER_ROUND;
+use crate::stark::air::Air;
+use crate::stark::constraints::{BlankCountConstraints, ConstraintViolation};
+use crate::stark::params::ParameterError;
+use crate::stark::prover::{
+    check_air_security, check_zero_knowledge_openings, prove_committed, BlankCountProof,
+    BlankProofError, StarkParameters, TraceCommitment,
 };
-use crate::stark::constraints::BlankCountConstraints;
-use crate::stark::prover::{generate_blank_count_proof, BlankCountProof, StarkParameters};
 use crate::stark::StarkField;
 use crate::utils::permutation::random_permutation;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::io;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ProverConfig {
     pub stark: StarkParameters,
+    /// Re-permute and recommit every `k` rounds. `None` keeps a single commitment for
+    /// the whole session; `Some(1)` gives a fresh permutation per round.
+    pub rounds_per_commitment: Option<u32>,
+    /// Hash behind every commitment and opening; the verifier must use the same one.
+    pub hash: HashBackend,
+    /// Authenticate spot and blank openings with one multiproof per tree instead of a
+    /// path per edge. The verifier accepts either form.
+    pub batch_openings: bool,
+    /// Worker threads for building commitments. `None` uses rayon's global pool; the
+    /// commitments do not depend on the thread count.
+    pub threads: Option<usize>,
+    /// Single-edge proofs each commitment tree keeps for repeated openings. `None` uses
+    /// [`DEFAULT_CACHE_CAPACITY`]; `Some(0)` disables the cache.
+    pub proof_cache_capacity: Option<usize>,
+    /// Arity of the graph, blank and permutation trees. The roots commit to it, so the
+    /// verifier needs no matching setting.
+    pub merkle_arity: MerkleArity,
+    /// Chunk the graph tree by adjacency row instead of by
+    /// [`StarkParameters::chunk_size`] edges, so the edges of a spot row share one chunk
+    /// path.
+    pub row_per_chunk: bool,
+    /// Where the graph and blank trees keep their chunk levels. [`MerkleStorage::Disk`]
+    /// memory-maps them from files in the given directory. Only those levels leave
+    /// memory: both graph copies, the salts, the blank vector and the STARK trace with its
+    /// extensions stay in RAM either way, so memory still grows with `n^2`.
+    pub merkle_storage: MerkleStorage,
 }
 
-impl Default for ProverConfig {
-    fn default() -> Self {
-        ProverConfig {
-            stark: StarkParameters::default(),
+impl ProverConfig {
+    /// Whether a fresh commitment must be issued before `round` (rounds are 0-based and
+    /// round 0 always uses the initial commitment).
+    pub fn recommits_before(&self, round: u32) -> bool {
+        match self.rounds_per_commitment {
+            Some(every) if every > 0 => round > 0 && round.is_multiple_of(every),
+            _ => false,
+        }
+    }
+
+    /// Edges per chunk of the graph tree of an `n`-node graph.
+    pub fn graph_chunk_size(&self, n: u32) -> usize {
+        if self.row_per_chunk {
+            n as usize
+        } else {
+            self.stark.chunk_size
+        }
+    }
+
+    fn cache_capacity(&self) -> usize {
+        self.proof_cache_capacity.unwrap_or(DEFAULT_CACHE_CAPACITY)
+    }
+
+    /// The pool of [`Self::threads`] workers, or `None` for rayon's global pool. Each
+    /// pool is started on first use and shared by every config with the same `threads`.
+    pub fn thread_pool(&self) -> Option<Arc<rayon::ThreadPool>> {
+        let threads = self.threads?;
+        let mut pools = THREAD_POOLS
+            .get_or_init(Mutex::default)
+            .lock()
+            .expect("thread pool lock is not poisoned");
+        let pool = pools.entry(threads).or_insert_with(|| {
+            Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .expect("prover thread pool starts"),
+            )
+        });
+        Some(pool.clone())
+    }
+
+    /// Runs `work` on a pool of [`Self::threads`] workers, or on the global pool.
+    fn install<R: Send>(&self, work: impl FnOnce() -> R + Send) -> R {
+        match self.thread_pool() {
+            Some(pool) => pool.install(work),
+            None => work(),
         }
     }
 }
 
+/// Worker pools behind [`ProverConfig::thread_pool`], by thread count.
+type ThreadPools = Mutex<HashMap<usize, Arc<rayon::ThreadPool>>>;
+static THREAD_POOLS: OnceLock<ThreadPools> = OnceLock::new();
+
+/// What the current commitment has opened: nothing yet, spot or blank rounds, or its
+/// permutation. The last two never mix, so opened labels never meet opened edges.
+const COMMITMENT_FRESH: u8 = 0;
+const COMMITMENT_ANSWERED: u8 = 1;
+const COMMITMENT_REVEALED: u8 = 2;
+
 pub struct ProverState {
     pub original_graph: Graph,
     pub coloration_set: ColorationSet,
-    hasher: Blake3QuantumHash,
+    hasher: HashBackend,
+    batch_openings: bool,
     permutation: Vec<u32>,
     permuted_graph: Graph,
     blank_polynomial: Option<BlankPolynomial>,
     stark_proof: Option<BlankCountProof>,
+    blank_trace: Option<TraceCommitment>,
+    stark_parameters: StarkParameters,
     commitments: Option<Commitments>,
     graph_tree: Option<GraphMerkleTree>,
     permutation_tree: Option<MerkleTree>,
     blank_tree: Option<ChunkedMerkleTree>,
+    color_salts: Vec<LeafSalt>,
+    permutation_salts: Vec<LeafSalt>,
+    blank_salts: Vec<LeafSalt>,
+    exposure: AtomicU8,
 }
 
 impl ProverState {
@@ -46,84 +150,402 @@ impl ProverState {
         ProverState {
             original_graph: graph,
             coloration_set,
-            hasher: default_quantum_hash(),
+            hasher: HashBackend::default(),
+            batch_openings: false,
             permutation: Vec::new(),
             permuted_graph,
             blank_polynomial: None,
             stark_proof: None,
+            blank_trace: None,
+            stark_parameters: StarkParameters::default(),
             commitments: None,
             graph_tree: None,
             permutation_tree: None,
             blank_tree: None,
+            color_salts: Vec::new(),
+            permutation_salts: Vec::new(),
+            blank_salts: Vec::new(),
+            exposure: AtomicU8::new(COMMITMENT_FRESH),
         }
     }
 
+    /// Samples a fresh permutation and salts and commits to the relabelled graph. Calling
+    /// this again between rounds discards the previous commitment entirely.
+    ///
+    /// Panics if [`ProverConfig::merkle_storage`] cannot be written, the witness exceeds
+    /// its blank budget or the zero-knowledge budget is exceeded; use [`Self::try_commit`]
+    /// to handle that.
     pub fn commit(&mut self, config: &ProverConfig) -> Commitments {
+        self.try_commit(config).expect("witness is committable")
+    }
+
+    /// Like [`Self::commit`], reporting failures to write the Merkle storage. A witness
+    /// with more blanks than [`ColorationSet::blank_limit`] is refused with
+    /// [`io::ErrorKind::InvalidInput`] before anything is committed, since no proof of its
+    /// blank budget would verify. With [`StarkParameters::zero_knowledge`], every round of
+    /// a commitment may be a blank round opening the blank trace once, so parameters
+    /// whose [`ProverConfig::rounds_per_commitment`] (which must then be set) would
+    /// exhaust the trace mask are refused the same way.
+    pub fn try_commit(&mut self, config: &ProverConfig) -> io::Result<Commitments> {
+        self.check_blank_budget(self.original_graph.blank_count() as u64)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        self.check_zero_knowledge_budget(config)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        config.install(|| self.commit_in_pool(config))
+    }
+
+    /// Constraints proving that the committed blank vector stays within the budget.
+    fn blank_constraints(&self) -> BlankCountConstraints<StarkField> {
+        BlankCountConstraints::at_most(
+            self.original_graph.n,
+            self.coloration_set.blank_limit() as u64,
+        )
+    }
+
+    fn check_blank_budget(&self, blanks: u64) -> Result<(), BlankProofError> {
+        let limit = self.coloration_set.blank_limit() as u64;
+        if blanks > limit {
+            return Err(BlankProofError::Unsatisfied(
+                ConstraintViolation::BudgetExceeded {
+                    limit,
+                    actual: blanks,
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_zero_knowledge_budget(&self, config: &ProverConfig) -> Result<(), ParameterError> {
+        if !config.stark.zero_knowledge {
+            return Ok(());
+        }
+        let trace_length = self.blank_constraints().trace_length();
+        // A single commitment for the whole session answers an unbounded number of rounds.
+        let rounds = config.rounds_per_commitment.unwrap_or(u32::MAX) as usize;
+        check_zero_knowledge_openings(&config.stark, trace_length, 1, rounds)
+    }
+
+    fn commit_in_pool(&mut self, config: &ProverConfig) -> io::Result<Commitments> {
         let n = self.original_graph.n;
+        self.hasher = config.hash;
+        self.batch_openings = config.batch_openings;
+        *self.exposure.get_mut() = COMMITMENT_FRESH;
         self.permutation = random_permutation(n as usize);
         self.permuted_graph = self.original_graph.apply_permutation(&self.permutation);
+        let edge_count = (n as usize).pow(2);
+        self.color_salts = random_salts(edge_count);
+        self.blank_salts = random_salts(edge_count);
+        self.permutation_salts = random_salts(n as usize);
 
-        let graph_merkle = GraphMerkleTree::from_graph(&self.permuted_graph, &self.hasher);
+        let graph_merkle = GraphMerkleTree::with_storage(
+            &self.permuted_graph,
+            &self.color_salts,
+            &self.blank_salts,
+            &self.hasher,
+            config.graph_chunk_size(n),
+            config.merkle_arity,
+            &config.merkle_storage,
+        )?
+        .with_cache_capacity(config.cache_capacity());
         let graph_root = graph_merkle.root();
         self.graph_tree = Some(graph_merkle);
 
         let perm_data: Vec<Vec<u8>> = self
             .permutation
             .iter()
-            .map(|value| value.to_be_bytes().to_vec())
+            .zip(&self.permutation_salts)
+            .enumerate()
+            .map(|(index, (&value, salt))| {
+                salted_leaf(&encode_permutation_leaf(index as u32, value), salt)
+            })
             .collect();
-        let perm_tree = MerkleTree::new(&perm_data, &self.hasher);
+        let perm_tree = MerkleTree::with_arity(
+            MerkleDomain::Permutation,
+            &perm_data,
+            &self.hasher,
+            config.merkle_arity,
+        );
         let permutation_root = perm_tree.root();
         self.permutation_tree = Some(perm_tree);
 
         let blank_vector = self.build_blank_vector();
-        let blank_chunks: Vec<Vec<u8>> = blank_vector.iter().map(|&bit| vec![bit]).collect();
-        let blank_tree =
-            ChunkedMerkleTree::new(&blank_chunks, &self.hasher, config.stark.chunk_size);
+        let blank_tree = ChunkedMerkleTree::from_fn(
+            MerkleDomain::Blank,
+            blank_vector.len(),
+            |index| {
+                salted_leaf(
+                    &encode_blank_leaf(index as u64, blank_vector[index]),
+                    &self.blank_salts[index],
+                )
+            },
+            &self.hasher,
+            config.stark.chunk_size,
+            config.merkle_arity,
+            &config.merkle_storage,
+        )?
+        .with_cache_capacity(config.cache_capacity());
         let blank_root = blank_tree.root();
         self.blank_tree = Some(blank_tree);
 
-        let polynomial = BlankPolynomial::new(blank_vector);
-        let constraints =
-            BlankCountConstraints::<StarkField>::new(n, self.coloration_set.blank_limit() as u64);
-        let proof =
-            generate_blank_count_proof(&polynomial, &constraints, &config.stark, &self.hasher);
-
-        self.blank_polynomial = Some(polynomial);
-        self.stark_proof = Some(proof.clone());
+        self.stark_parameters = config.stark.clone();
+        let blank_trace_root = self
+            .prove_blank_budget(blank_vector)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
 
         let commitments = Commitments {
             graph_root,
             permutation_root,
             blank_root,
-            blank_count: self.coloration_set.blank_limit(),
+            blank_trace_root,
+            blank_limit: self.coloration_set.blank_limit(),
+            statement_digest: statement_digest(&self.coloration_set, &config.stark, &self.hasher),
+            hash: config.hash,
         };
 
         self.commitments = Some(commitments.clone());
-        commitments
+        Ok(commitments)
+    }
+
+    /// Applies `edits` (edges of the original graph and their new colors) to the current
+    /// commitment in place. Only the edited graph and blank leaves and their paths are
+    /// rehashed; the blank-count trace and its STARK proof are rebuilt only if a blank bit
+    /// changed, or always with zero knowledge so each commitment gets a fresh mask. Edited
+    /// edges get fresh salts, but the permutation and all other salts are kept, so unlike
+    /// [`Self::commit`] the result is linkable to the previous commitment. Edits that
+    /// would exceed the blank budget are refused before any is applied.
+    ///
+    /// Panics if nothing has been committed yet or an edit lies outside the graph.
+    pub fn recommit_with_edits(
+        &mut self,
+        edits: &[(u32, u32, Color)],
+    ) -> Result<Commitments, BlankProofError> {
+        let n = self.original_graph.n;
+        let committed_blanks = self
+            .blank_polynomial
+            .as_ref()
+            .expect("commit before recommitting")
+            .sum();
+        let mut edited = HashMap::new();
+        for &(from, to, color) in edits {
+            assert!(
+                from < n && to < n,
+                "edge ({from}, {to}) lies outside the graph"
+            );
+            edited.insert((from, to), color == Color::Blank);
+        }
+        let blanks = edited
+            .iter()
+            .fold(committed_blanks, |blanks, (&(from, to), &is_blank)| {
+                let was_blank = self.original_graph.get_edge(from, to) == Color::Blank;
+                blanks + is_blank as u64 - was_blank as u64
+            });
+        self.check_blank_budget(blanks)?;
+
+        let graph_tree = self
+            .graph_tree
+            .as_mut()
+            .expect("commit before recommitting");
+        let blank_tree = self
+            .blank_tree
+            .as_mut()
+            .expect("commit before recommitting");
+        let mut position = vec![0u32; n as usize];
+        for (slot, &node) in self.permutation.iter().enumerate() {
+            position[node as usize] = slot as u32;
+        }
+
+        let fresh_salts = random_salts(2 * edits.len());
+        let mut blanks_changed = false;
+        for (&(from, to, color), salts) in edits.iter().zip(fresh_salts.chunks(2)) {
+            self.original_graph.overwrite_edge(from, to, color);
+            let (i, j) = (position[from as usize], position[to as usize]);
+            let was_blank = self.permuted_graph.get_edge(i, j) == Color::Blank;
+            self.permuted_graph.overwrite_edge(i, j, color);
+            let index = graph_tree
+                .edge_index(i, j)
+                .expect("edge lies inside the graph");
+            self.color_salts[index] = salts[0];
+            self.blank_salts[index] = salts[1];
+
+            let is_blank = color == Color::Blank;
+            graph_tree.update_edge(i, j, color, &salts[0], &salts[1], &self.hasher);
+            blank_tree.update_leaf(
+                index,
+                &salted_leaf(&encode_blank_leaf(index as u64, is_blank as u8), &salts[1]),
+                &self.hasher,
+            );
+            blanks_changed |= was_blank != is_blank;
+        }
+
+        let mut commitments = self
+            .commitments
+            .clone()
+            .expect("commit before recommitting");
+        commitments.graph_root = graph_tree.root();
+        commitments.blank_root = blank_tree.root();
+        if blanks_changed || self.stark_parameters.zero_knowledge {
+            commitments.blank_trace_root = self.prove_blank_budget(self.build_blank_vector())?;
+        }
+        self.commitments = Some(commitments.clone());
+        Ok(commitments)
+    }
+
+    /// Commits to the blank-count trace of `blank_vector` and proves the blank budget over
+    /// it under [`Self::stark_parameters`], returning the trace root. Like
+    /// [`generate_blank_count_proof`], it refuses insecure parameters and blank vectors
+    /// over the budget instead of proving them.
+    ///
+    /// [`generate_blank_count_proof`]: crate::stark::prover::generate_blank_count_proof
+    fn prove_blank_budget(&mut self, blank_vector: Vec<u8>) -> Result<[u8; 32], BlankProofError> {
+        let params = &self.stark_parameters;
+        let polynomial = BlankPolynomial::new(blank_vector);
+        let constraints = self.blank_constraints();
+        check_air_security(&constraints, params).map_err(BlankProofError::Parameters)?;
+        constraints
+            .check(&polynomial)
+            .map_err(BlankProofError::Unsatisfied)?;
+        // The trace is kept so blank rounds can open its value column at challenged edges.
+        let trace = TraceCommitment::new(
+            &constraints,
+            &constraints.build_trace(&polynomial),
+            params,
+            &self.hasher,
+        );
+        let trace_root = trace.root();
+        let proof = BlankCountProof {
+            expected_sum: constraints.expected_sum,
+            bound: constraints.bound,
+            proof: prove_committed(&constraints, &trace, params, &self.hasher),
+        };
+
+        self.blank_polynomial = Some(polynomial);
+        self.stark_proof = Some(proof);
+        self.blank_trace = Some(trace);
+        Ok(trace_root)
     }
 
+    /// Overrides [`ProverConfig::batch_openings`] for the responses that follow, without
+    /// touching the commitments.
+    pub fn set_batch_openings(&mut self, batch_openings: bool) {
+        self.batch_openings = batch_openings;
+    }
+
+    /// Proof-cache lookups across the graph and blank trees of the current commitment.
+    pub fn proof_cache_stats(&self) -> CacheStats {
+        let graph = self.graph_tree.as_ref().map(GraphMerkleTree::cache_stats);
+        let blank = self.blank_tree.as_ref().map(ChunkedMerkleTree::cache_stats);
+        graph.unwrap_or_default() + blank.unwrap_or_default()
+    }
+
+    /// Whether the current commitment may answer `challenge`. A commitment either answers
+    /// spot and blank rounds or opens its permutation, never both, so the labels a
+    /// permutation round reveals never relabel an opened spot or edge. Spots must name
+    /// three distinct nodes of the graph and blank checks edge slots below `n^2`, at most
+    /// `MAX_CHECKS_PER_ROUND` of either. The `respond_to_*` methods panic on challenges
+    /// this refuses, so call it first on challenges from an untrusted verifier.
+    pub fn check_challenge(&self, challenge: &Challenge) -> Result<(), ChallengeError> {
+        if self.commitments.is_none() {
+            return Err(ChallengeError::NotCommitted);
+        }
+        match (challenge, self.exposure.load(Ordering::Acquire)) {
+            (Challenge::Permutation(_), COMMITMENT_ANSWERED) => {
+                return Err(ChallengeError::CommitmentInUse)
+            }
+            (Challenge::Spot(_) | Challenge::Blank(_), COMMITMENT_REVEALED) => {
+                return Err(ChallengeError::PermutationRevealed)
+            }
+            _ => {}
+        }
+        let nodes = self.permuted_graph.n;
+        let requested = match challenge {
+            Challenge::Spot(ch) => ch.spots.len(),
+            Challenge::Blank(ch) => ch.edge_indices.len(),
+            Challenge::Permutation(_) => 0,
+        };
+        if requested > MAX_CHECKS_PER_ROUND as usize {
+            return Err(ChallengeError::TooManyChecks {
+                requested,
+                limit: MAX_CHECKS_PER_ROUND,
+            });
+        }
+        match challenge {
+            Challenge::Spot(ch) => {
+                for spot in &ch.spots {
+                    if let Some(&node) = spot.iter().find(|&&node| node >= nodes) {
+                        return Err(ChallengeError::NodeOutOfRange { node, nodes });
+                    }
+                    if spot[0] == spot[1] || spot[0] == spot[2] || spot[1] == spot[2] {
+                        return Err(ChallengeError::RepeatedSpotNode { spot: *spot });
+                    }
+                }
+            }
+            Challenge::Blank(ch) => {
+                let edges = nodes as u64 * nodes as u64;
+                if let Some(&edge_index) = ch.edge_indices.iter().find(|&&idx| idx >= edges) {
+                    return Err(ChallengeError::EdgeOutOfRange { edge_index, edges });
+                }
+            }
+            Challenge::Permutation(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Records that the current commitment opens `kind` (answered rounds or its revealed
+    /// permutation), panicking if it already opened the other.
+    fn expose(&self, kind: u8) {
+        let recorded = self
+            .exposure
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                (current == COMMITMENT_FRESH || current == kind).then_some(kind)
+            });
+        if let Err(current) = recorded {
+            let refusal = if current == COMMITMENT_REVEALED {
+                ChallengeError::PermutationRevealed
+            } else {
+                ChallengeError::CommitmentInUse
+            };
+            panic!("{refusal}");
+        }
+    }
+
+    /// Opens all nine edges of every challenged spot.
+    ///
+    /// Panics if the commitment's permutation was opened; see [`Self::check_challenge`].
     pub fn respond_to_spot_challenge(&self, challenge: &SpotChallenge) -> SpotChallengeResponse {
         let graph_tree = self
             .graph_tree
             .as_ref()
             .expect("commitments must be generated before responding to challenges");
+        self.expose(COMMITMENT_ANSWERED);
         let mut responses = Vec::new();
         for nodes in &challenge.spots {
             let mut edges = Vec::new();
             for &a in nodes.iter() {
                 for &b in nodes.iter() {
                     let color = self.permuted_graph.get_edge(a, b);
-                    let proof = graph_tree
-                        .get_edge_proof(a, b)
-                        .expect("edge proof must exist inside graph commitment");
+                    let leaf_index = self.edge_index(a, b);
+                    let color_salt = self.color_salts[leaf_index];
+                    let blank_salt = self.blank_salts[leaf_index];
+                    let proof = (!self.batch_openings).then(|| {
+                        graph_tree
+                            .get_edge_proof(a, b)
+                            .expect("edge proof must exist inside graph commitment")
+                    });
                     #[cfg(debug_assertions)]
-                    {
-                        let mut bytes = Vec::with_capacity(9);
-                        bytes.extend_from_slice(&a.to_be_bytes());
-                        bytes.extend_from_slice(&b.to_be_bytes());
-                        bytes.push(color.to_u8());
-                        let expected = self.hasher.hash(&bytes);
+                    if let Some(proof) = &proof {
+                        use crate::crypto::merkle::{encode_edge_leaf, leaf_digest};
+                        let color_digest =
+                            edge_field_digest(color.to_u8(), &color_salt, &self.hasher);
+                        let blank_digest = edge_field_digest(
+                            (color == Color::Blank) as u8,
+                            &blank_salt,
+                            &self.hasher,
+                        );
+                        let expected = leaf_digest(
+                            MerkleDomain::Graph,
+                            &encode_edge_leaf(a, b, &color_digest, &blank_digest),
+                            &self.hasher,
+                        );
                         debug_assert_eq!(
                             proof.leaf_proof.leaf_hash, expected,
                             "edge proof hash mismatch for ({}, {})",
@@ -134,6 +556,8 @@ impl ProverState {
                         from: a,
                         to: b,
                         color,
+                        color_salt,
+                        blank_salt,
                         proof,
                     });
                 }
@@ -144,9 +568,27 @@ impl ProverState {
             });
         }
 
-        SpotChallengeResponse { responses }
+        let multiproof = self.batch_openings.then(|| {
+            let edges: Vec<(u32, u32)> = responses
+                .iter()
+                .flat_map(|spot| spot.edges.iter().map(|edge| (edge.from, edge.to)))
+                .collect();
+            graph_tree
+                .get_edges_multiproof(&edges)
+                .expect("spot edges lie inside graph commitment")
+        });
+
+        SpotChallengeResponse {
+            responses,
+            multiproof,
+        }
     }
 
+    /// Opens the challenged blank edges and proves their bits against the blank trace.
+    ///
+    /// Panics if a zero-knowledge commitment answers more blank rounds than
+    /// [`ProverConfig::rounds_per_commitment`] allowed for when it was made, or if the
+    /// commitment's permutation was opened (see [`Self::check_challenge`]).
     pub fn respond_to_blank_challenge(&self, challenge: &BlankChallenge) -> BlankChallengeResponse {
         let graph_tree = self
             .graph_tree
@@ -156,30 +598,26 @@ impl ProverState {
             .blank_tree
             .as_ref()
             .expect("blank tree available after commitment");
-        let n = self.permuted_graph.n as u64;
-        let mut edges = Vec::new();
-        for &idx in &challenge.edge_indices {
-            let i = (idx / n) as u32;
-            let j = (idx % n) as u32;
-            let color = self.permuted_graph.get_edge(i, j);
-            let is_blank = color == Color::Blank;
-            let color_proof = graph_tree
-                .get_edge_proof(i, j)
-                .expect("color proof exists for committed edge");
-            let leaf_index = usize::try_from(idx).expect("edge index fits usize on target");
-            let blank_proof = blank_tree
-                .get_proof(leaf_index)
-                .expect("blank vector proof exists for committed edge");
-            edges.push(BlankEdgeOpening {
-                edge_index: idx,
-                from: i,
-                to: j,
-                color,
-                is_blank,
-                color_proof,
-                blank_proof,
-            });
-        }
+        let blank_trace = self
+            .blank_trace
+            .as_ref()
+            .expect("blank trace available after commitment");
+        self.expose(COMMITMENT_ANSWERED);
+        let edges: Vec<BlankEdgeOpening> = challenge
+            .edge_indices
+            .iter()
+            .map(|&idx| self.open_blank_edge(idx, graph_tree, blank_tree))
+            .collect();
+        let claims: Vec<(usize, StarkField)> =
+            edges.iter().map(BlankEdgeOpening::value_claim).collect();
+        let trace_values = blank_trace
+            .prove_column_values(
+                BlankCountConstraints::<StarkField>::VALUE_COLUMN,
+                &claims,
+                &self.stark_parameters,
+                &self.hasher,
+            )
+            .expect("commitment schedule keeps blank rounds within the zero-knowledge budget");
 
         let stark = self
             .stark_proof
@@ -187,21 +625,119 @@ impl ProverState {
             .expect("stark proof generated during commitment")
             .clone();
 
+        let multiproofs = self.batch_openings.then(|| {
+            let endpoints: Vec<(u32, u32)> = edges
+                .iter()
+                .map(|opening| (opening.from, opening.to))
+                .collect();
+            let indices: Vec<usize> = edges
+                .iter()
+                .map(|opening| opening.edge_index as usize)
+                .collect();
+            BlankMultiproofs {
+                edges: graph_tree
+                    .get_edges_multiproof(&endpoints)
+                    .expect("challenged edges lie inside graph commitment"),
+                blanks: blank_tree
+                    .get_multiproof(&indices)
+                    .expect("challenged edges lie inside blank commitment"),
+            }
+        });
+
         BlankChallengeResponse {
             edges,
             stark_proof: stark,
+            trace_values,
+            multiproofs,
         }
     }
 
-    fn build_blank_vector(&self) -> Vec<u8> {
-        let n = self.permuted_graph.n;
-        let mut vector = Vec::with_capacity((n * n) as usize);
-        for i in 0..n {
-            for j in 0..n {
-                let is_blank = self.permuted_graph.get_edge(i, j) == Color::Blank;
-                vector.push(if is_blank { 1 } else { 0 });
-            }
+    /// Opens the blank bit of edge `idx`, proving it against both trees unless openings
+    /// are batched.
+    fn open_blank_edge(
+        &self,
+        idx: u64,
+        graph_tree: &GraphMerkleTree,
+        blank_tree: &ChunkedMerkleTree,
+    ) -> BlankEdgeOpening {
+        let n = self.permuted_graph.n as u64;
+        let i = (idx / n) as u32;
+        let j = (idx % n) as u32;
+        let color = self.permuted_graph.get_edge(i, j);
+        let leaf_index = usize::try_from(idx).expect("edge index fits usize on target");
+        let (edge_proof, blank_proof) = if self.batch_openings {
+            (None, None)
+        } else {
+            (
+                Some(
+                    graph_tree
+                        .get_edge_proof(i, j)
+                        .expect("edge proof exists for committed edge"),
+                ),
+                Some(
+                    blank_tree
+                        .get_proof(leaf_index)
+                        .expect("blank vector proof exists for committed edge"),
+                ),
+            )
+        };
+        BlankEdgeOpening {
+            edge_index: idx,
+            from: i,
+            to: j,
+            is_blank: color == Color::Blank,
+            color_digest: edge_field_digest(
+                color.to_u8(),
+                &self.color_salts[leaf_index],
+                &self.hasher,
+            ),
+            blank_salt: self.blank_salts[leaf_index],
+            edge_proof,
+            blank_proof,
         }
-        vector
+    }
+
+    /// Opens every salted entry of the permutation behind `permutation_root`.
+    ///
+    /// Panics if the commitment already answered a spot or blank round; see
+    /// [`Self::check_challenge`].
+    pub fn respond_to_permutation_challenge(
+        &self,
+        _challenge: &PermutationChallenge,
+    ) -> PermutationChallengeResponse {
+        let permutation_tree = self
+            .permutation_tree
+            .as_ref()
+            .expect("permutation tree available after commitment");
+        self.expose(COMMITMENT_REVEALED);
+        let entries = self
+            .permutation
+            .iter()
+            .zip(&self.permutation_salts)
+            .enumerate()
+            .map(|(index, (&value, salt))| PermutationOpening {
+                index: index as u32,
+                value,
+                salt: *salt,
+                proof: permutation_tree
+                    .get_proof(index)
+                    .expect("permutation proof exists for committed entry"),
+            })
+            .collect();
+
+        PermutationChallengeResponse { entries }
+    }
+
+    fn edge_index(&self, from: u32, to: u32) -> usize {
+        let n = self.permuted_graph.n as usize;
+        from as usize * n + to as usize
+    }
+
+    fn build_blank_vector(&self) -> Vec<u8> {
+        self.permuted_graph
+            .adjacency
+            .par_iter()
+            .flat_map_iter(|row| row.iter().map(|&color| (color == Color::Blank) as u8))
+            .collect()
     }
 }
@@ -0,0 +1,169 @@
+use crate::graph::ColorationSet;
+use crate::protocol::verifier::VerifierConfig;
+use serde::Serialize;
+use std::fmt;
+
+/// Red, Green, Yellow and Blank.
+const COLOR_COUNT: u64 = 4;
+/// Upper bound on the rounds [`VerifierConfig::for_security_bits`] settles for before it
+/// packs more checks into each round instead, and on the rounds a served prover answers.
+pub(crate) const MAX_ROUNDS: u32 = 1 << 20;
+/// Upper bound on the spots, and on the blank checks, [`VerifierConfig::for_security_bits`]
+/// packs into one round, and on those a prover answers in one challenge.
+pub(crate) const MAX_CHECKS_PER_ROUND: u32 = 1 << 16;
+
+/// [`VerifierConfig::for_security_bits`] cannot reach `requested` bits within
+/// `MAX_ROUNDS` rounds of at most `MAX_CHECKS_PER_ROUND` spots and blank checks each.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnreachableSecurity {
+    pub requested: u32,
+    /// Soundness the largest configuration reaches.
+    pub achievable_bits: f64,
+}
+
+impl fmt::Display for UnreachableSecurity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bits of soundness are out of reach; at most {:.1} bits are achievable",
+            self.requested, self.achievable_bits
+        )
+    }
+}
+
+impl std::error::Error for UnreachableSecurity {}
+
+/// Escape probability of a cheating prover against one [`VerifierConfig`].
+///
+/// A prover without a valid coloring must either commit a graph with a triad whose
+/// pattern is not in the coloration set, which only spot rounds can catch, or exceed the
+/// blank budget by flipping at least one blank bit between the graph and the blank
+/// vector, which only blank rounds can catch. Each round the prover escapes with the
+/// larger of the two probabilities. The worst case assumes a single bad triad; the
+/// "random" figures assume an unstructured coloring, whose triads land in the
+/// coloration set with probability `pattern_coverage`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SoundnessEstimate {
+    pub triads: u64,
+    /// Share of all canonical spot patterns that the coloration set allows.
+    pub pattern_coverage: f64,
+    /// Probability that one spot catches a single bad triad.
+    pub spot_detection: f64,
+    /// Probability that one spot catches an unstructured coloring.
+    pub random_spot_detection: f64,
+    /// Probability that one blank check catches a single flipped blank bit.
+    pub blank_detection: f64,
+    pub round_escape: f64,
+    pub random_round_escape: f64,
+    /// Probability that a worst-case cheater survives every round.
+    pub soundness_error: f64,
+    pub security_bits: f64,
+    pub random_security_bits: f64,
+}
+
+impl SoundnessEstimate {
+    pub fn new(coloration: &ColorationSet, config: &VerifierConfig) -> Self {
+        let model = DetectionModel::new(coloration);
+        let spot_share = config.spot_probability.clamp(0.0, 1.0);
+        let blank_share = (1.0 - spot_share - config.permutation_probability).max(0.0);
+        let blank_round = blank_share * detection(model.blank, config.blank_checks_per_round);
+        let spot_round = spot_share * detection(model.spot, config.spots_per_round);
+        // A random coloring breaks pattern constraints everywhere, so only spot rounds
+        // matter against it.
+        let random_spot_round = spot_share * detection(model.random_spot, config.spots_per_round);
+        let round_escape = 1.0 - spot_round.min(blank_round);
+        let random_round_escape = 1.0 - random_spot_round;
+        let security_bits = bits_for(round_escape, config.rounds);
+
+        SoundnessEstimate {
+            triads: model.triads,
+            pattern_coverage: model.pattern_coverage,
+            spot_detection: model.spot,
+            random_spot_detection: model.random_spot,
+            blank_detection: model.blank,
+            round_escape,
+            random_round_escape,
+            soundness_error: (-security_bits).exp2(),
+            security_bits,
+            random_security_bits: bits_for(random_round_escape, config.rounds),
+        }
+    }
+}
+
+/// Per-check detection probabilities for one coloration set.
+struct DetectionModel {
+    triads: u64,
+    pattern_coverage: f64,
+    spot: f64,
+    random_spot: f64,
+    blank: f64,
+}
+
+impl DetectionModel {
+    fn new(coloration: &ColorationSet) -> Self {
+        let n = coloration.graph_size() as u64;
+        let triads = (n * n.saturating_sub(1) * n.saturating_sub(2) / 6).max(1);
+        let pattern_coverage =
+            (coloration.pattern_count() as f64 / spot_pattern_count(COLOR_COUNT) as f64).min(1.0);
+        DetectionModel {
+            triads,
+            pattern_coverage,
+            spot: 1.0 / triads as f64,
+            random_spot: 1.0 - pattern_coverage,
+            blank: 1.0 / (n * n).max(1) as f64,
+        }
+    }
+}
+
+/// Number of spot patterns up to relabelling the three nodes, for `colors` edge colors:
+/// Burnside over the symmetric group acting on the three loops and six arcs.
+pub fn spot_pattern_count(colors: u64) -> u64 {
+    (colors.pow(9) + 3 * colors.pow(5) + 2 * colors.pow(3)) / 6
+}
+
+/// Worst-case parameters reaching `bits` of soundness for `coloration`: starts from the
+/// default spots and blank checks per round, splits rounds between spot and blank
+/// challenges so both cheating strategies are caught equally often, and adds rounds
+/// until the target is met. Past `MAX_ROUNDS` it doubles whichever of the spots and
+/// blank checks per round detects less, rebalancing the challenge mix each time.
+pub(crate) fn config_for_security_bits(
+    coloration: &ColorationSet,
+    bits: u32,
+) -> Result<VerifierConfig, UnreachableSecurity> {
+    let mut config = VerifierConfig::default();
+    let model = DetectionModel::new(coloration);
+    loop {
+        let spot_round = detection(model.spot, config.spots_per_round);
+        let blank_round = detection(model.blank, config.blank_checks_per_round);
+        config.spot_probability = blank_round / (spot_round + blank_round);
+        let per_round = -(-spot_round * config.spot_probability).ln_1p() / std::f64::consts::LN_2;
+        let rounds = (bits as f64 / per_round).ceil().max(1.0);
+        if rounds <= MAX_ROUNDS as f64 {
+            config.rounds = rounds as u32;
+            return Ok(config);
+        }
+        let spots_full = config.spots_per_round >= MAX_CHECKS_PER_ROUND;
+        let blanks_full = config.blank_checks_per_round >= MAX_CHECKS_PER_ROUND;
+        if spots_full && blanks_full {
+            return Err(UnreachableSecurity {
+                requested: bits,
+                achievable_bits: per_round * MAX_ROUNDS as f64,
+            });
+        }
+        if blanks_full || (!spots_full && spot_round <= blank_round) {
+            config.spots_per_round = (config.spots_per_round * 2).min(MAX_CHECKS_PER_ROUND);
+        } else {
+            config.blank_checks_per_round =
+                (config.blank_checks_per_round * 2).min(MAX_CHECKS_PER_ROUND);
+        }
+    }
+}
+
+/// Probability that at least one of `samples` independent checks fires.
+fn detection(per_sample: f64, samples: u32) -> f64 {
+    -(samples as f64 * (-per_sample).ln_1p()).exp_m1()
+}
+
+fn bits_for(round_escape: f64, rounds: u32) -> f64 {
+    -(rounds as f64) * (-(1.0 - round_escape)).ln_1p() / std::f64::consts::LN_2
+}
@@ -1,16 +1,25 @@
-use crate::crypto::hash::{default_quantum_hash, Blake3QuantumHash, QuantumHash};
-use crate::crypto::merkle::ChunkedMerkleProof;
+use crate::crypto::hash::{salted_leaf, HashBackend, QuantumHash};
+use crate::crypto::merkle::{
+    edge_field_digest, encode_blank_leaf, encode_edge_leaf, encode_permutation_leaf, leaf_digest,
+    ChunkedMerkleMultiProof, ChunkedMerkleProof, MerkleDomain, MerkleError, MerkleLevel,
+};
 use crate::graph::{Color, ColorationSet, Spot};
+use crate::protocol::error::VerificationError;
 use crate::protocol::messages::{
-    BlankChallenge, BlankChallengeResponse, BlankEdgeOpening, Challenge, Commitments,
-    SpotChallenge, SpotChallengeResponse,
+    statement_digest, BlankChallenge, BlankChallengeResponse, BlankEdgeOpening, Challenge,
+    Commitments, PermutationChallenge, PermutationChallengeResponse, SessionNonce, SpotChallenge,
+    SpotChallengeResponse,
+};
+use crate::protocol::soundness::{
+    config_for_security_bits, SoundnessEstimate, UnreachableSecurity,
 };
 use crate::stark::constraints::BlankCountConstraints;
+use crate::stark::prover::StarkParameters;
+use crate::stark::verifier::verify_column_values;
 use crate::stark::StarkField;
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::env;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerifierConfig {
@@ -18,6 +27,13 @@ pub struct VerifierConfig {
     pub spots_per_round: u32,
     pub blank_checks_per_round: u32,
     pub spot_probability: f64,
+    /// Probability of a permutation-opening round. Spot rounds take the first
+    /// `spot_probability` of the unit interval, permutation rounds the next slice, and
+    /// blank rounds whatever remains. An opened permutation relabels everything else
+    /// opened under its commitment, so these rounds need a prover that recommits before
+    /// every round ([`VerifierConfig::check_schedule`]).
+    #[serde(default)]
+    pub permutation_probability: f64,
 }
 
 impl Default for VerifierConfig {
@@ -27,54 +43,194 @@ impl Default for VerifierConfig {
             spots_per_round: 4,
             blank_checks_per_round: 2,
             spot_probability: 0.8,
+            permutation_probability: 0.0,
         }
     }
 }
 
+impl VerifierConfig {
+    /// Smallest round count (with spot and blank rounds balanced) that keeps a cheating
+    /// prover's worst-case escape probability below `2^-bits` for `coloration`. See
+    /// [`SoundnessEstimate`] for the model. Packs more spots or blank checks into each
+    /// round when the rounds alone would grow too many, and fails if even that falls short.
+    pub fn for_security_bits(
+        coloration: &ColorationSet,
+        bits: u32,
+    ) -> Result<Self, UnreachableSecurity> {
+        config_for_security_bits(coloration, bits)
+    }
+
+    /// Rejects a prover recommitting every `rounds_per_commitment` rounds if this
+    /// configuration issues permutation rounds and the prover does not recommit before
+    /// every round.
+    pub fn check_schedule(
+        &self,
+        rounds_per_commitment: Option<u32>,
+    ) -> Result<(), VerificationError> {
+        if self.permutation_probability > 0.0 && rounds_per_commitment != Some(1) {
+            return Err(VerificationError::SharedPermutationCommitment {
+                rounds_per_commitment,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn soundness(&self, coloration: &ColorationSet) -> SoundnessEstimate {
+        SoundnessEstimate::new(coloration, self)
+    }
+}
+
 pub struct Verifier {
     configuration: VerifierConfig,
     coloration_set: ColorationSet,
     commitments: Option<Commitments>,
+    session: SessionNonce,
+    stark: StarkParameters,
+    statement_digest: [u8; 32],
+    /// Private randomness behind interactive challenges, which the prover must not be able
+    /// to predict from the nonce and its own commitments.
+    secret: [u8; 32],
     rng: StdRng,
-    hasher: Blake3QuantumHash,
+    hasher: HashBackend,
 }
 
 impl Verifier {
+    /// Starts a session under a fresh random [`SessionNonce`].
     pub fn new(coloration_set: ColorationSet, configuration: VerifierConfig) -> Self {
+        let hasher = HashBackend::default();
+        let session = SessionNonce::random(None);
+        let stark = StarkParameters::default();
+        let secret = rand::rng().random();
         Verifier {
+            statement_digest: statement_digest(&coloration_set, &stark, &hasher),
             configuration,
             coloration_set,
             commitments: None,
-            rng: StdRng::seed_from_u64(0xB10C_cafe),
-            hasher: default_quantum_hash(),
+            rng: session_rng(&secret, &session, &hasher),
+            secret,
+            session,
+            stark,
+            hasher,
         }
     }
 
+    /// Replaces the session nonce, e.g. with one issued earlier to a prover that answers
+    /// offline. Also reseeds the challenge-type draws, which derive from the nonce and the
+    /// verifier's private randomness.
+    pub fn with_session(mut self, session: SessionNonce) -> Self {
+        self.rng = session_rng(&self.secret, &session, &self.hasher);
+        self.session = session;
+        self
+    }
+
+    pub fn configuration(&self) -> &VerifierConfig {
+        &self.configuration
+    }
+
+    /// The nonce to send the prover before it commits.
+    pub fn session(&self) -> &SessionNonce {
+        &self.session
+    }
+
+    /// Rejects a transcript recorded under any session other than this verifier's.
+    pub fn check_session(&self, session: &SessionNonce) -> Result<(), VerificationError> {
+        if session != &self.session {
+            return Err(VerificationError::SessionMismatch);
+        }
+        Ok(())
+    }
+
+    /// Overrides the STARK parameters (blowup factor, query count) that blank-count proofs
+    /// must have been generated with. They have to match the prover's `ProverConfig::stark`.
+    pub fn with_stark_parameters(mut self, stark: StarkParameters) -> Self {
+        self.statement_digest = statement_digest(&self.coloration_set, &stark, &self.hasher);
+        self.stark = stark;
+        self
+    }
+
+    /// Switches the hash backend, which has to match the prover's `ProverConfig::hash`.
+    pub fn with_hash(mut self, hash: HashBackend) -> Self {
+        self.statement_digest = statement_digest(&self.coloration_set, &self.stark, &hash);
+        self.rng = session_rng(&self.secret, &self.session, &hash);
+        self.hasher = hash;
+        self
+    }
+
+    pub fn hash(&self) -> HashBackend {
+        self.hasher
+    }
+
+    /// Digest of the statement this verifier checks, which commitments must carry.
+    pub fn statement_digest(&self) -> [u8; 32] {
+        self.statement_digest
+    }
+
+    /// Rejects commitments made for another statement, coloration set or parameter set.
+    pub fn check_statement(&self, commitments: &Commitments) -> Result<(), VerificationError> {
+        if commitments.hash != self.hasher {
+            return Err(VerificationError::HashMismatch {
+                expected: self.hasher,
+                actual: commitments.hash,
+            });
+        }
+        if commitments.statement_digest != self.statement_digest {
+            return Err(VerificationError::StatementMismatch);
+        }
+        Ok(())
+    }
+
+    /// Installs the commitments that subsequent challenges are derived from. May be called
+    /// again between rounds when the prover recommits under a fresh permutation.
     pub fn receive_commitments(&mut self, commitments: Commitments) {
         self.commitments = Some(commitments);
     }
 
+    /// Interactive challenge for `round`. It mixes the verifier's private randomness into
+    /// the nonce and commitments, so a prover cannot compute it before committing.
     pub fn generate_challenge(&mut self, round: u32) -> Challenge {
-        if self.rng.random::<f64>() < self.configuration.spot_probability {
-            Challenge::Spot(self.generate_spot_challenge(round))
+        let draw = self.rng.random::<f64>();
+        self.challenge_for_draw(draw, |label| self.challenge_seed(round, label))
+    }
+
+    /// Fiat–Shamir counterpart of [`Verifier::generate_challenge`]: the challenge type and
+    /// its contents are derived from `transcript_seed` alone, so the prover can compute
+    /// them offline and any verifier can recompute them from the transcript.
+    pub fn derive_challenge(&self, transcript_seed: &[u8; 32]) -> Challenge {
+        let draw = StdRng::from_seed(*transcript_seed).random::<f64>();
+        self.challenge_for_draw(draw, |label| {
+            self.hasher.hash_with_salt(label, transcript_seed)
+        })
+    }
+
+    fn challenge_for_draw<S>(&self, draw: f64, seed_for: S) -> Challenge
+    where
+        S: Fn(&[u8]) -> [u8; 32],
+    {
+        let spot_cutoff = self.configuration.spot_probability;
+        let permutation_cutoff = spot_cutoff + self.configuration.permutation_probability;
+        if draw < spot_cutoff {
+            Challenge::Spot(self.spot_challenge_from_seed(seed_for(b"spot")))
+        } else if draw < permutation_cutoff {
+            Challenge::Permutation(PermutationChallenge {
+                seed: seed_for(b"permutation"),
+            })
         } else {
-            Challenge::Blank(self.generate_blank_challenge(round))
+            Challenge::Blank(self.blank_challenge_from_seed(seed_for(b"blank")))
         }
     }
 
-    fn generate_spot_challenge(&mut self, round: u32) -> SpotChallenge {
+    fn spot_challenge_from_seed(&self, seed: [u8; 32]) -> SpotChallenge {
         let n = self.coloration_set.graph_size().max(3);
-        let seed = self.challenge_seed(round, b"spot");
         let mut seeded_rng = StdRng::from_seed(seed);
         let mut spots = Vec::new();
         for _ in 0..self.configuration.spots_per_round {
             let mut nodes = [0u32; 3];
             let mut used = Vec::new();
-            for idx in 0..3 {
+            for slot in nodes.iter_mut() {
                 loop {
                     let candidate = seeded_rng.random_range(0..n);
                     if !used.contains(&candidate) {
-                        nodes[idx] = candidate;
+                        *slot = candidate;
                         used.push(candidate);
                         break;
                     }
@@ -85,9 +241,8 @@ impl Verifier {
         SpotChallenge { spots, seed }
     }
 
-    fn generate_blank_challenge(&mut self, round: u32) -> BlankChallenge {
+    fn blank_challenge_from_seed(&self, seed: [u8; 32]) -> BlankChallenge {
         let n = self.coloration_set.graph_size().max(2) as u64;
-        let seed = self.challenge_seed(round, b"blank");
         let mut seeded_rng = StdRng::from_seed(seed);
         let mut edge_indices = Vec::new();
         for _ in 0..self.configuration.blank_checks_per_round {
@@ -101,10 +256,13 @@ impl Verifier {
             .commitments
             .as_ref()
             .expect("commitments must be set before generating challenges");
-        let mut data = Vec::new();
+        let mut data = self.secret.to_vec();
+        data.extend_from_slice(&self.session.encode());
+        data.extend_from_slice(&self.statement_digest);
         data.extend_from_slice(&commitments.graph_root);
         data.extend_from_slice(&commitments.permutation_root);
         data.extend_from_slice(&commitments.blank_root);
+        data.extend_from_slice(&commitments.blank_trace_root);
         data.extend_from_slice(&round.to_be_bytes());
         data.extend_from_slice(label);
         self.hasher.hash(&data)
@@ -114,189 +272,373 @@ impl Verifier {
         &self,
         challenge: &SpotChallenge,
         response: &SpotChallengeResponse,
-    ) -> bool {
-        let commitments = match &self.commitments {
-            Some(c) => c,
-            None => return false,
-        };
+    ) -> Result<(), VerificationError> {
+        let commitments = self.commitments()?;
         if challenge.spots.len() != response.responses.len() {
-            Self::debug_log("spot response rejected: response count mismatch");
-            return false;
+            return Err(VerificationError::ResponseCountMismatch {
+                expected: challenge.spots.len(),
+                actual: response.responses.len(),
+            });
         }
 
+        let n = self.coloration_set.graph_size() as u64;
+        let mut spots = Vec::with_capacity(response.responses.len());
+        let mut batched = BTreeMap::new();
         for (spot_nodes, resp) in challenge.spots.iter().zip(&response.responses) {
             if spot_nodes != &resp.nodes {
-                Self::debug_log("spot response rejected: node ordering mismatch");
-                return false;
+                return Err(VerificationError::SpotNodesMismatch {
+                    expected: *spot_nodes,
+                    actual: resp.nodes,
+                });
             }
+            // Missing edges would read as blank in the pattern, so the spot must open all
+            // nine pairs of its nodes, once each and in the prover's order.
+            if resp.edges.len() != 9 {
+                return Err(VerificationError::ResponseCountMismatch {
+                    expected: 9,
+                    actual: resp.edges.len(),
+                });
+            }
+            let pairs = resp
+                .nodes
+                .iter()
+                .flat_map(|&a| resp.nodes.iter().map(move |&b| (a, b)));
 
             let mut edges = HashMap::new();
-            for edge in &resp.edges {
-                if !self.verify_graph_leaf(
-                    edge.from,
-                    edge.to,
-                    edge.color,
-                    &edge.proof,
-                    &commitments.graph_root,
-                ) {
-                    Self::debug_log(&format!(
-                        "spot response rejected: merkle proof mismatch for edge ({}, {})",
-                        edge.from, edge.to
-                    ));
-                    return false;
+            for (expected, edge) in pairs.zip(&resp.edges) {
+                if (edge.from, edge.to) != expected {
+                    return Err(VerificationError::SpotEdgeMismatch {
+                        nodes: resp.nodes,
+                        expected,
+                        actual: (edge.from, edge.to),
+                    });
                 }
+                let color_digest =
+                    edge_field_digest(edge.color.to_u8(), &edge.color_salt, &self.hasher);
+                let blank_bit = (edge.color == Color::Blank) as u8;
+                let blank_digest = edge_field_digest(blank_bit, &edge.blank_salt, &self.hasher);
+                let leaf_hash = leaf_digest(
+                    MerkleDomain::Graph,
+                    &encode_edge_leaf(edge.from, edge.to, &color_digest, &blank_digest),
+                    &self.hasher,
+                );
+                let index = edge.from as u64 * n + edge.to as u64;
+                let opened = match &response.multiproof {
+                    Some(_) => batch_leaf(&mut batched, index, leaf_hash),
+                    None => self.verify_opening(
+                        MerkleDomain::Graph,
+                        leaf_hash,
+                        index,
+                        edge.proof.as_ref(),
+                        &commitments.graph_root,
+                    ),
+                };
+                opened.map_err(|source| VerificationError::SpotEdgeOpening {
+                    nodes: resp.nodes,
+                    edge: (edge.from, edge.to),
+                    source,
+                })?;
                 edges.insert((edge.from, edge.to), edge.color);
             }
-
-            let spot = Spot {
+            spots.push(Spot {
                 nodes: resp.nodes,
                 edges,
-            };
+            });
+        }
 
+        if let Some(multiproof) = &response.multiproof {
+            self.verify_multiproof(
+                MerkleDomain::Graph,
+                multiproof,
+                batched,
+                &commitments.graph_root,
+            )
+            .map_err(|source| VerificationError::SpotMultiproof { source })?;
+        }
+        // Patterns are only meaningful once every opened color is authenticated.
+        for spot in &spots {
             if !spot.is_valid(&self.coloration_set) {
-                let mut details: Vec<String> = Vec::new();
-                if env::var("ZKP_DEBUG_SPOT").is_ok() {
-                    let mut entries: Vec<_> = spot.edges.iter().collect();
-                    entries.sort_by_key(|(&(a, b), _)| (a, b));
-                    for ((a, b), color) in entries {
-                        details.push(format!("({},{})={:?}", a, b, color));
-                    }
-                }
-                Self::debug_log(&format!(
-                    "spot response rejected: pattern not in coloration set for nodes {:?} -> {}",
-                    spot.nodes,
-                    details.join(", ")
-                ));
-                return false;
+                return Err(VerificationError::PatternNotAllowed {
+                    nodes: spot.nodes,
+                    pattern: ColorationSet::spot_to_key(spot),
+                });
             }
         }
-        true
+        Ok(())
     }
 
-    fn debug_log(msg: &str) {
-        if env::var("ZKP_DEBUG_SPOT").is_ok() {
-            eprintln!("{}", msg);
-        }
+    /// The current commitments, provided they were made for this verifier's statement.
+    fn commitments(&self) -> Result<&Commitments, VerificationError> {
+        let commitments = self
+            .commitments
+            .as_ref()
+            .ok_or(VerificationError::MissingCommitments)?;
+        self.check_statement(commitments)?;
+        Ok(commitments)
     }
 
     pub fn verify_blank_response(
         &self,
         challenge: &BlankChallenge,
         response: &BlankChallengeResponse,
-    ) -> bool {
-        let commitments = match &self.commitments {
-            Some(c) => c,
-            None => return false,
-        };
+    ) -> Result<(), VerificationError> {
+        let commitments = self.commitments()?;
 
-        let openings_by_index: HashMap<u64, &BlankEdgeOpening> = response
-            .edges
-            .iter()
-            .map(|edge| (edge.edge_index, edge))
-            .collect();
+        let n = self.coloration_set.graph_size() as u64;
+        let expected = &challenge.edge_indices;
+        if response.edges.len() != expected.len() {
+            return Err(VerificationError::ResponseCountMismatch {
+                expected: expected.len(),
+                actual: response.edges.len(),
+            });
+        }
 
-        for edge_idx in &challenge.edge_indices {
-            let opening = match openings_by_index.get(edge_idx) {
-                Some(opening) => opening,
-                None => {
-                    Self::debug_log(&format!(
-                        "blank response rejected: missing opening for edge {}",
-                        edge_idx
-                    ));
-                    return false;
-                }
-            };
-            if !self.verify_graph_leaf(
-                opening.from,
-                opening.to,
-                opening.color,
-                &opening.color_proof,
-                &commitments.graph_root,
-            ) {
-                Self::debug_log(&format!(
-                    "blank response rejected: color proof mismatch for edge {}",
-                    edge_idx
-                ));
-                return false;
+        let mut batched_edges = BTreeMap::new();
+        let mut batched_blanks = BTreeMap::new();
+        // Openings must follow the expected order one-to-one, so a repeated index cannot
+        // hide an unchecked opening behind a valid one.
+        for (edge_idx, opening) in expected.iter().zip(&response.edges) {
+            if opening.edge_index != *edge_idx {
+                return Err(VerificationError::BlankEdgeIndexMismatch {
+                    expected: *edge_idx,
+                    actual: opening.edge_index,
+                });
             }
-            if !self.verify_blank_opening(opening, &commitments.blank_root) {
-                Self::debug_log(&format!(
-                    "blank response rejected: blank proof mismatch for edge {}",
-                    edge_idx
-                ));
-                return false;
-            }
-            if (opening.color == Color::Blank) != opening.is_blank {
-                Self::debug_log(&format!(
-                    "blank response rejected: blank flag mismatch for edge {}",
-                    edge_idx
-                ));
-                return false;
-            }
-            let n = self.coloration_set.graph_size() as u64;
             if opening.edge_index >= n * n {
-                Self::debug_log(&format!(
-                    "blank response rejected: edge index {} out of bounds",
-                    edge_idx
-                ));
-                return false;
+                return Err(VerificationError::BlankEdgeOutOfBounds {
+                    edge_index: *edge_idx,
+                });
+            }
+            let edge = (opening.from, opening.to);
+            if opening.from as u64 * n + opening.to as u64 != opening.edge_index {
+                return Err(VerificationError::BlankEndpointsMismatch {
+                    edge_index: *edge_idx,
+                    edge,
+                });
             }
+            // The graph leaf and the blank-tree leaf both commit to the blank bit under the
+            // same salt, which links the openings while the color stays behind
+            // `color_digest`.
+            let blank_bit = opening.is_blank as u8;
+            let blank_digest = edge_field_digest(blank_bit, &opening.blank_salt, &self.hasher);
+            let edge_leaf = leaf_digest(
+                MerkleDomain::Graph,
+                &encode_edge_leaf(
+                    opening.from,
+                    opening.to,
+                    &opening.color_digest,
+                    &blank_digest,
+                ),
+                &self.hasher,
+            );
+            let blank_leaf = leaf_digest(
+                MerkleDomain::Blank,
+                &salted_leaf(
+                    &encode_blank_leaf(opening.edge_index, blank_bit),
+                    &opening.blank_salt,
+                ),
+                &self.hasher,
+            );
+            let (edge_opened, blank_opened) = match &response.multiproofs {
+                Some(_) => (
+                    batch_leaf(&mut batched_edges, opening.edge_index, edge_leaf),
+                    batch_leaf(&mut batched_blanks, opening.edge_index, blank_leaf),
+                ),
+                None => (
+                    self.verify_opening(
+                        MerkleDomain::Graph,
+                        edge_leaf,
+                        opening.edge_index,
+                        opening.edge_proof.as_ref(),
+                        &commitments.graph_root,
+                    ),
+                    self.verify_opening(
+                        MerkleDomain::Blank,
+                        blank_leaf,
+                        opening.edge_index,
+                        opening.blank_proof.as_ref(),
+                        &commitments.blank_root,
+                    ),
+                ),
+            };
+            edge_opened.map_err(|source| VerificationError::BlankEdgeOpening {
+                edge_index: *edge_idx,
+                edge,
+                source,
+            })?;
+            blank_opened.map_err(|source| VerificationError::BlankBitOpening {
+                edge_index: *edge_idx,
+                source,
+            })?;
+        }
+        if let Some(multiproofs) = &response.multiproofs {
+            self.verify_multiproof(
+                MerkleDomain::Graph,
+                &multiproofs.edges,
+                batched_edges,
+                &commitments.graph_root,
+            )
+            .map_err(|source| VerificationError::BlankEdgeMultiproof { source })?;
+            self.verify_multiproof(
+                MerkleDomain::Blank,
+                &multiproofs.blanks,
+                batched_blanks,
+                &commitments.blank_root,
+            )
+            .map_err(|source| VerificationError::BlankBitMultiproof { source })?;
         }
 
-        let constraints = BlankCountConstraints::<StarkField>::new(
+        // The budget is public: a prover cannot raise it by committing a larger one.
+        if commitments.blank_limit != self.coloration_set.blank_limit() {
+            return Err(VerificationError::BlankLimitMismatch {
+                committed: commitments.blank_limit,
+                expected: self.coloration_set.blank_limit(),
+            });
+        }
+        let constraints = BlankCountConstraints::<StarkField>::at_most(
             self.coloration_set.graph_size(),
-            commitments.blank_count as u64,
+            commitments.blank_limit as u64,
         );
-        if !response.stark_proof.verify(&constraints, &self.hasher) {
-            Self::debug_log("blank response rejected: STARK proof invalid");
-            return false;
+        let stark_proof = &response.stark_proof;
+        if stark_proof.proof.trace_root != commitments.blank_trace_root {
+            return Err(VerificationError::TraceRootMismatch);
         }
-        true
+        stark_proof
+            .verify(&constraints, &self.stark, &self.hasher)
+            .map_err(VerificationError::BlankCountProof)?;
+        // The STARK only speaks about its own trace; tie the counted bits to the opened
+        // ones at the challenged edges. Those are the verifier's random draw after every
+        // root is fixed, so a trace that disagrees with `blank_root` on a share of the
+        // edges is caught with that probability per check, like a flipped blank bit.
+        let claims: Vec<(usize, StarkField)> = response
+            .edges
+            .iter()
+            .map(BlankEdgeOpening::value_claim)
+            .collect();
+        verify_column_values(
+            &response.trace_values,
+            &commitments.blank_trace_root,
+            stark_proof.proof.trace_length as usize,
+            BlankCountConstraints::<StarkField>::VALUE_COLUMN,
+            &claims,
+            &self.stark,
+            &self.hasher,
+        )
+        .map_err(VerificationError::TraceValues)?;
+        Ok(())
     }
 
-    fn verify_graph_leaf(
+    /// Checks that the opened permutation is a bijection on `0..n` whose entries all sit
+    /// under `permutation_root`. Every ordered pair of nodes is an edge of the public
+    /// (uncolored) structure, so any such relabelling of it is the structure itself.
+    pub fn verify_permutation_response(
         &self,
-        from: u32,
-        to: u32,
-        color: Color,
-        proof: &ChunkedMerkleProof,
-        graph_root: &[u8; 32],
-    ) -> bool {
-        let leaf_bytes = encode_edge_leaf(from, to, color);
-        let expected = self.hasher.hash(&leaf_bytes);
-        if proof.leaf_proof.leaf_hash != expected {
-            Self::debug_log(&format!(
-                "leaf hash mismatch for edge ({}, {}): expected {:?}, proof {:?}, bytes {:?}",
-                from, to, expected, proof.leaf_proof.leaf_hash, leaf_bytes
-            ));
-            return false;
+        _challenge: &PermutationChallenge,
+        response: &PermutationChallengeResponse,
+    ) -> Result<(), VerificationError> {
+        let commitments = self.commitments()?;
+        let n = self.coloration_set.graph_size() as usize;
+        if response.entries.len() != n {
+            return Err(VerificationError::ResponseCountMismatch {
+                expected: n,
+                actual: response.entries.len(),
+            });
         }
-        if !proof.verify(graph_root, &self.hasher) {
-            Self::debug_log(&format!("merkle path mismatch for edge ({}, {})", from, to));
-            return false;
+
+        let mut seen = vec![false; n];
+        for (position, entry) in response.entries.iter().enumerate() {
+            if entry.index as usize != position
+                || entry.proof.leaf_index != position as u64
+                || entry.proof.leaf_count != n as u64
+            {
+                return Err(VerificationError::PermutationEntryOutOfOrder { position });
+            }
+            let value = entry.value as usize;
+            if value >= n || seen[value] {
+                return Err(VerificationError::PermutationValueInvalid {
+                    position,
+                    value: entry.value,
+                });
+            }
+            seen[value] = true;
+
+            let leaf_bytes = salted_leaf(
+                &encode_permutation_leaf(entry.index, entry.value),
+                &entry.salt,
+            );
+            if entry.proof.leaf_hash
+                != leaf_digest(MerkleDomain::Permutation, &leaf_bytes, &self.hasher)
+            {
+                return Err(VerificationError::PermutationOpening {
+                    position,
+                    source: MerkleError::LeafHashMismatch,
+                });
+            }
+            if !entry.proof.verify(
+                MerkleDomain::Permutation,
+                &commitments.permutation_root,
+                &self.hasher,
+            ) {
+                return Err(VerificationError::PermutationOpening {
+                    position,
+                    source: MerkleError::PathMismatch {
+                        level: MerkleLevel::Leaf,
+                    },
+                });
+            }
         }
-        true
+        Ok(())
     }
 
-    fn verify_blank_opening(&self, opening: &BlankEdgeOpening, blank_root: &[u8; 32]) -> bool {
-        let bit = if opening.is_blank { 1u8 } else { 0u8 };
-        let leaf_bytes = vec![bit];
-        if opening.blank_proof.leaf_proof.leaf_hash != self.hasher.hash(&leaf_bytes) {
-            Self::debug_log("blank opening rejected: leaf hash mismatch");
-            return false;
-        }
-        if !opening.blank_proof.verify(blank_root, &self.hasher) {
-            Self::debug_log("blank opening rejected: merkle path mismatch");
-            return false;
+    /// Checks one opening's own proof against the leaf hash its contents give and the
+    /// edge `index` it must open in one of the `n * n`-leaf edge trees.
+    fn verify_opening(
+        &self,
+        domain: MerkleDomain,
+        leaf_hash: [u8; 32],
+        index: u64,
+        proof: Option<&ChunkedMerkleProof>,
+        root: &[u8; 32],
+    ) -> Result<(), MerkleError> {
+        let proof = proof.ok_or(MerkleError::MissingProof)?;
+        if proof.leaf_proof.leaf_hash != leaf_hash {
+            return Err(MerkleError::LeafHashMismatch);
         }
-        true
+        let n = self.coloration_set.graph_size() as u64;
+        proof.verify(domain, root, n * n, index, &self.hasher)
+    }
+
+    /// Checks a batched opening of `leaves` (by edge index) in one of the `n * n`-leaf
+    /// edge trees.
+    fn verify_multiproof(
+        &self,
+        domain: MerkleDomain,
+        multiproof: &ChunkedMerkleMultiProof,
+        leaves: BTreeMap<u64, [u8; 32]>,
+        root: &[u8; 32],
+    ) -> Result<(), MerkleError> {
+        let n = self.coloration_set.graph_size() as u64;
+        let leaves: Vec<(u64, [u8; 32])> = leaves.into_iter().collect();
+        multiproof.verify(domain, root, n * n, &leaves, &self.hasher)
+    }
+}
+
+/// Queues `leaf_hash` for a batched opening of leaf `index`. An index opened twice must
+/// open the same leaf both times.
+fn batch_leaf(
+    leaves: &mut BTreeMap<u64, [u8; 32]>,
+    index: u64,
+    leaf_hash: [u8; 32],
+) -> Result<(), MerkleError> {
+    match leaves.insert(index, leaf_hash) {
+        Some(previous) if previous != leaf_hash => Err(MerkleError::LeafHashMismatch),
+        _ => Ok(()),
     }
 }
 
-fn encode_edge_leaf(from: u32, to: u32, color: Color) -> Vec<u8> {
-    let mut serialized = Vec::with_capacity(9);
-    serialized.extend_from_slice(&from.to_be_bytes());
-    serialized.extend_from_slice(&to.to_be_bytes());
-    serialized.push(color.to_u8());
-    serialized
+fn session_rng(secret: &[u8; 32], session: &SessionNonce, hasher: &dyn QuantumHash) -> StdRng {
+    let mut data = b"challenge-kind".to_vec();
+    data.extend_from_slice(secret);
+    data.extend_from_slice(&session.encode());
+    StdRng::from_seed(hasher.hash(&data))
 }
@@ -0,0 +1,337 @@
+use crate::crypto::hash::QuantumHash;
+use crate::stark::fri::field_from_digest;
+use crate::stark::prover::StarkParameters;
+use crate::stark::StarkField;
+use ark_ff::{batch_inversion, FftField, Field, Zero};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+
+const DOMAIN_TAG: &[u8] = b"zkp-c-coloring/stark/v1";
+
+/// Pins one trace cell: `trace[column][row] == value`.
+#[derive(Debug, Clone, Copy)]
+pub struct BoundaryConstraint {
+    pub column: usize,
+    pub row: usize,
+    pub value: StarkField,
+}
+
+/// Algebraic intermediate representation of a statement about an execution trace.
+///
+/// The trace is `trace_width` columns of `trace_length` rows. Transition constraints
+/// relate each row to the next and must vanish on every row except the last; boundary
+/// constraints pin single cells; periodic columns are public columns whose values repeat
+/// with a power-of-two period. [`crate::stark::prover::prove`] and
+/// [`crate::stark::verifier::verify`] work for any implementation.
+pub trait Air {
+    fn trace_width(&self) -> usize;
+
+    /// Number of trace rows. Must be a power of two.
+    fn trace_length(&self) -> usize;
+
+    fn num_transition_constraints(&self) -> usize;
+
+    /// Highest total degree of a transition constraint in the trace and periodic columns.
+    fn transition_degree(&self) -> usize;
+
+    /// Writes one value per transition constraint for the row pair `(current, next)`.
+    fn evaluate_transition(
+        &self,
+        current: &[StarkField],
+        next: &[StarkField],
+        periodic: &[StarkField],
+        result: &mut [StarkField],
+    );
+
+    fn boundary_constraints(&self) -> Vec<BoundaryConstraint>;
+
+    /// Public columns, each given by one period whose length is a power of two dividing
+    /// `trace_length`.
+    fn periodic_columns(&self) -> Vec<Vec<StarkField>> {
+        Vec::new()
+    }
+
+    /// Encoding of the public statement. It seeds every verifier challenge, so two
+    /// statements that differ here can never share a proof.
+    fn public_inputs(&self) -> Vec<u8>;
+}
+
+/// The trace domain `H` and the low-degree-extension coset `D`, which is `blowup` times
+/// larger and disjoint from `H`.
+pub(crate) fn trace_domains(
+    trace_length: usize,
+    blowup: usize,
+) -> Option<(
+    Radix2EvaluationDomain<StarkField>,
+    Radix2EvaluationDomain<StarkField>,
+)> {
+    if !trace_length.is_power_of_two() || blowup < 2 || !blowup.is_power_of_two() {
+        return None;
+    }
+    let trace_domain = Radix2EvaluationDomain::<StarkField>::new(trace_length)?;
+    let lde_domain = Radix2EvaluationDomain::<StarkField>::new(trace_length * blowup)?
+        .get_coset(StarkField::GENERATOR)?;
+    Some((trace_domain, lde_domain))
+}
+
+/// Degree of the committed columns as a multiple of the trace length: masking for zero
+/// knowledge doubles it.
+pub(crate) fn column_degree_factor(params: &StarkParameters) -> usize {
+    if params.zero_knowledge {
+        2
+    } else {
+        1
+    }
+}
+
+/// Composition degree in multiples of the trace length: transitions of degree `d` over
+/// columns of degree `q * n`, divided by the vanishing polynomial of degree `n`, and at
+/// least the raw columns themselves.
+pub(crate) fn composition_degree_factor(transition_degree: usize, zero_knowledge: bool) -> usize {
+    let q = if zero_knowledge { 2 } else { 1 };
+    (q * transition_degree.max(1) - 1)
+        .max(q)
+        .next_power_of_two()
+}
+
+/// Domains, challenges and precomputed constants shared by the generic prover and
+/// verifier for one AIR instance.
+pub(crate) struct AirContext<'a, A: Air> {
+    pub air: &'a A,
+    pub trace_domain: Radix2EvaluationDomain<StarkField>,
+    pub lde_domain: Radix2EvaluationDomain<StarkField>,
+    /// Size FRI folds down to: the LDE size divided by the composition degree bound.
+    pub remainder_size: usize,
+    /// Committed columns per row: the AIR's columns plus the blinding column, if any.
+    pub row_width: usize,
+    boundary: Vec<BoundaryConstraint>,
+    boundary_points: Vec<StarkField>,
+    periodic: Vec<PeriodicPolynomial>,
+}
+
+struct PeriodicPolynomial {
+    /// `trace_length / period`; the column is `P(x^stride)`.
+    stride: u64,
+    coefficients: Vec<StarkField>,
+}
+
+impl<'a, A: Air> AirContext<'a, A> {
+    pub fn new(air: &'a A, params: &StarkParameters) -> Option<Self> {
+        let trace_length = air.trace_length();
+        let blowup = params.blowup_factor;
+        let (trace_domain, lde_domain) = trace_domains(trace_length, blowup)?;
+        let degree_factor =
+            composition_degree_factor(air.transition_degree(), params.zero_knowledge);
+        if degree_factor >= blowup {
+            return None;
+        }
+
+        let boundary = air.boundary_constraints();
+        if boundary
+            .iter()
+            .any(|c| c.column >= air.trace_width() || c.row >= trace_length)
+        {
+            return None;
+        }
+        let boundary_points = boundary
+            .iter()
+            .map(|c| trace_domain.element(c.row))
+            .collect();
+
+        let mut periodic = Vec::new();
+        for column in air.periodic_columns() {
+            let period = column.len();
+            if !period.is_power_of_two() || !trace_length.is_multiple_of(period) {
+                return None;
+            }
+            let domain = Radix2EvaluationDomain::<StarkField>::new(period)?;
+            periodic.push(PeriodicPolynomial {
+                stride: (trace_length / period) as u64,
+                coefficients: domain.ifft(&column),
+            });
+        }
+
+        Some(AirContext {
+            air,
+            trace_domain,
+            lde_domain,
+            remainder_size: blowup / degree_factor,
+            row_width: air.trace_width() + params.zero_knowledge as usize,
+            boundary,
+            boundary_points,
+            periodic,
+        })
+    }
+
+    pub fn channel_seed(&self, trace_root: &[u8; 32], hasher: &dyn QuantumHash) -> [u8; 32] {
+        let public_inputs = self.air.public_inputs();
+        let mut data = Vec::with_capacity(DOMAIN_TAG.len() + public_inputs.len() + 48);
+        data.extend_from_slice(DOMAIN_TAG);
+        data.extend_from_slice(&(public_inputs.len() as u64).to_be_bytes());
+        data.extend_from_slice(&public_inputs);
+        data.extend_from_slice(trace_root);
+        data.extend_from_slice(&(self.air.trace_length() as u64).to_be_bytes());
+        hasher.hash(&data)
+    }
+
+    /// One random coefficient per transition constraint, boundary constraint and committed
+    /// column, in that order.
+    pub fn composition_coefficients(
+        &self,
+        seed: &[u8; 32],
+        hasher: &dyn QuantumHash,
+    ) -> Vec<StarkField> {
+        let count = self.air.num_transition_constraints() + self.boundary.len() + self.row_width;
+        (0..count)
+            .map(|term| {
+                let mut data = Vec::with_capacity(40);
+                data.extend_from_slice(seed);
+                data.extend_from_slice(&(term as u64).to_be_bytes());
+                field_from_digest(&hasher.hash(&data))
+            })
+            .collect()
+    }
+
+    pub fn fri_seed(seed: &[u8; 32], hasher: &dyn QuantumHash) -> [u8; 32] {
+        let mut data = Vec::with_capacity(35);
+        data.extend_from_slice(seed);
+        data.extend_from_slice(b"fri");
+        hasher.hash(&data)
+    }
+
+    /// Offset between a point of the LDE domain and its successor `w * x`.
+    pub fn next_row_offset(&self) -> usize {
+        self.lde_domain.size() / self.trace_domain.size()
+    }
+
+    pub fn periodic_values(&self, x: StarkField) -> Vec<StarkField> {
+        self.periodic
+            .iter()
+            .map(|column| {
+                let y = x.pow([column.stride]);
+                column
+                    .coefficients
+                    .iter()
+                    .rev()
+                    .fold(StarkField::zero(), |acc, &c| acc * y + c)
+            })
+            .collect()
+    }
+
+    /// Composition polynomial at one point `x` off the trace domain, or `None` if `x`
+    /// hits a point where the constraint quotients are undefined.
+    pub fn composition_at(
+        &self,
+        coefficients: &[StarkField],
+        x: StarkField,
+        current: &[StarkField],
+        next: &[StarkField],
+    ) -> Option<StarkField> {
+        let vanishing_inv = self
+            .trace_domain
+            .evaluate_vanishing_polynomial(x)
+            .inverse()?;
+        let boundary_inv = self
+            .boundary_points
+            .iter()
+            .map(|&point| (x - point).inverse())
+            .collect::<Option<Vec<_>>>()?;
+        let periodic = self.periodic_values(x);
+        Some(self.combine(
+            coefficients,
+            x,
+            vanishing_inv,
+            &boundary_inv,
+            current,
+            next,
+            &periodic,
+        ))
+    }
+
+    /// Composition polynomial over the whole LDE domain from the extended trace columns.
+    pub fn composition_evaluations(
+        &self,
+        coefficients: &[StarkField],
+        columns: &[Vec<StarkField>],
+    ) -> Vec<StarkField> {
+        let size = self.lde_domain.size();
+        let offset = self.next_row_offset();
+        let points: Vec<StarkField> = self.lde_domain.elements().collect();
+        let mut vanishing: Vec<StarkField> = points
+            .iter()
+            .map(|&x| self.trace_domain.evaluate_vanishing_polynomial(x))
+            .collect();
+        batch_inversion(&mut vanishing);
+        let mut boundary: Vec<Vec<StarkField>> = self
+            .boundary_points
+            .iter()
+            .map(|&point| points.iter().map(|&x| x - point).collect())
+            .collect();
+        for column in boundary.iter_mut() {
+            batch_inversion(column);
+        }
+
+        let width = self.row_width;
+        let mut current = vec![StarkField::zero(); width];
+        let mut next = vec![StarkField::zero(); width];
+        let mut boundary_inv = vec![StarkField::zero(); boundary.len()];
+        (0..size)
+            .map(|i| {
+                let successor = (i + offset) % size;
+                for (col, values) in columns.iter().enumerate() {
+                    current[col] = values[i];
+                    next[col] = values[successor];
+                }
+                for (slot, inverses) in boundary_inv.iter_mut().zip(&boundary) {
+                    *slot = inverses[i];
+                }
+                let periodic = self.periodic_values(points[i]);
+                self.combine(
+                    coefficients,
+                    points[i],
+                    vanishing[i],
+                    &boundary_inv,
+                    &current,
+                    &next,
+                    &periodic,
+                )
+            })
+            .collect()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn combine(
+        &self,
+        coefficients: &[StarkField],
+        x: StarkField,
+        vanishing_inv: StarkField,
+        boundary_inv: &[StarkField],
+        current: &[StarkField],
+        next: &[StarkField],
+        periodic: &[StarkField],
+    ) -> StarkField {
+        let transitions = self.air.num_transition_constraints();
+        let mut values = vec![StarkField::zero(); transitions];
+        self.air
+            .evaluate_transition(current, next, periodic, &mut values);
+
+        // Transition constraints are not enforced between the last and the first row.
+        let last = self.trace_domain.group_gen_inv;
+        let transition_quotient = (x - last) * vanishing_inv;
+        let mut coefficients = coefficients.iter();
+        let mut acc = StarkField::zero();
+        for value in values {
+            acc +=
+                *coefficients.next().expect("coefficient per term") * value * transition_quotient;
+        }
+        for (constraint, inverse) in self.boundary.iter().zip(boundary_inv) {
+            let term = (current[constraint.column] - constraint.value) * inverse;
+            acc += *coefficients.next().expect("coefficient per term") * term;
+        }
+        // Folding the raw columns in makes FRI bound their degree as well; with zero
+        // knowledge the random blinding column also hides the composition values.
+        for &value in current {
+            acc += *coefficients.next().expect("coefficient per term") * value;
+        }
+        acc
+    }
+}
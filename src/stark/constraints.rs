@@ -1,30 +1,80 @@
 use crate::crypto::polynomial::BlankPolynomial;
-use ark_ff::FftField;
+use crate::stark::air::{Air, BoundaryConstraint};
+use crate::stark::StarkField;
+use ark_ff::{FftField, One, Zero};
 use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ConstraintViolation {
     NonBinaryValue(usize, u8),
     InvalidSum { expected: u64, actual: u64 },
+    BudgetExceeded { limit: u64, actual: u64 },
     DomainUnavailable,
 }
 
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintViolation::NonBinaryValue(idx, value) => {
+                write!(f, "blank entry {} is {}, not a bit", idx, value)
+            }
+            ConstraintViolation::InvalidSum { expected, actual } => {
+                write!(f, "blank count {} differs from {}", actual, expected)
+            }
+            ConstraintViolation::BudgetExceeded { limit, actual } => {
+                write!(f, "blank count {} exceeds the budget {}", actual, limit)
+            }
+            ConstraintViolation::DomainUnavailable => {
+                write!(f, "no evaluation domain fits the blank vector")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConstraintViolation {}
+
+/// How the blank count is compared to the public `expected_sum`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SumBound {
+    /// The sum equals `expected_sum`.
+    Exact,
+    /// The sum is at most `expected_sum`; the slack is range-checked inside the trace.
+    AtMost,
+}
+
 #[derive(Debug, Clone)]
 pub struct BlankCountConstraints<F: FftField> {
     pub n: u32,
+    /// The exact blank count, or the budget in [`SumBound::AtMost`] mode.
     pub expected_sum: u64,
+    pub bound: SumBound,
     pub n_squared: usize,
     pub domain: Radix2EvaluationDomain<F>,
 }
 
 impl<F: FftField> BlankCountConstraints<F> {
     pub fn new(n: u32, expected_sum: u64) -> Self {
+        Self::with_bound(n, expected_sum, SumBound::Exact)
+    }
+
+    /// Constraints for "at most `limit` blanks", which keep the actual count private.
+    pub fn at_most(n: u32, limit: u64) -> Self {
+        Self::with_bound(n, limit, SumBound::AtMost)
+    }
+
+    pub fn with_bound(n: u32, expected_sum: u64, bound: SumBound) -> Self {
         let n_squared = (n as usize).saturating_mul(n as usize).max(1);
+        // One spare row past the last blank bit lets the final running sum be pinned by
+        // a plain boundary constraint. The trace must also hold one slack weight period.
+        let rows = (n_squared + 1).max(slack_period(expected_sum));
         let domain =
-            Radix2EvaluationDomain::<F>::new(n_squared.next_power_of_two()).expect("radix2 domain");
+            Radix2EvaluationDomain::<F>::new(rows.next_power_of_two()).expect("radix2 domain");
         BlankCountConstraints {
             n,
             expected_sum,
+            bound,
             n_squared,
             domain,
         }
@@ -39,13 +89,172 @@ impl<F: FftField> BlankCountConstraints<F> {
         }
 
         let actual_sum = polynomial.sum();
-        if actual_sum != self.expected_sum {
-            return Err(ConstraintViolation::InvalidSum {
-                expected: self.expected_sum,
-                actual: actual_sum,
-            });
+        match self.bound {
+            SumBound::Exact if actual_sum != self.expected_sum => {
+                Err(ConstraintViolation::InvalidSum {
+                    expected: self.expected_sum,
+                    actual: actual_sum,
+                })
+            }
+            SumBound::AtMost if actual_sum > self.expected_sum => {
+                Err(ConstraintViolation::BudgetExceeded {
+                    limit: self.expected_sum,
+                    actual: actual_sum,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Number of bits the slack `expected_sum - sum` is decomposed into.
+fn slack_bits(expected_sum: u64) -> usize {
+    (u64::BITS - expected_sum.leading_zeros()) as usize
+}
+
+/// Period of the slack weight column: at least one zero weight past the last bit, so the
+/// padding row never carries slack.
+fn slack_period(expected_sum: u64) -> usize {
+    (slack_bits(expected_sum) + 1).next_power_of_two()
+}
+
+const VALUE: usize = 0;
+const RUNNING_SUM: usize = 1;
+const SLACK_BIT: usize = 2;
+
+impl BlankCountConstraints<StarkField> {
+    /// Trace column holding the blank bit of edge `i` at row `i`.
+    pub const VALUE_COLUMN: usize = VALUE;
+
+    /// The blank bit `v` (zero-padded to the trace length) and the exclusive running sum
+    /// `s`, so `s[i + 1] = s[i] + v[i]` and `s[0] = 0`. In [`SumBound::AtMost`] mode a
+    /// third column `b` holds the bits of the slack, little-endian from row 0, and the
+    /// running sum also adds `b[i] * 2^i`, so it still ends at `expected_sum`.
+    pub fn build_trace(&self, polynomial: &BlankPolynomial) -> Vec<Vec<StarkField>> {
+        let length = self.domain.size();
+        // An over-budget vector gets no valid slack; the proof then fails to verify.
+        let slack = self.expected_sum.saturating_sub(polynomial.sum());
+        let bits = slack_bits(self.expected_sum);
+        let weights = self.slack_weights();
+        let mut values = Vec::with_capacity(length);
+        let mut running_sums = Vec::with_capacity(length);
+        let mut slack_column = Vec::with_capacity(length);
+        let mut running = StarkField::zero();
+        for idx in 0..length {
+            let value = StarkField::from(polynomial.evaluate(idx) as u64);
+            let bit = if idx < bits {
+                StarkField::from((slack >> idx) & 1)
+            } else {
+                StarkField::zero()
+            };
+            running_sums.push(running);
+            values.push(value);
+            slack_column.push(bit);
+            running += value;
+            if self.bound == SumBound::AtMost {
+                running += bit * weights[idx % weights.len()];
+            }
+        }
+        match self.bound {
+            SumBound::Exact => vec![values, running_sums],
+            SumBound::AtMost => vec![values, running_sums, slack_column],
         }
+    }
+
+    /// One period of the public weights `2^i` for the slack bits, zero past the last bit.
+    /// Repeating it over the trace only lets a cheater add more non-negative slack, and
+    /// the whole sum stays far below the field modulus, so `sum <= expected_sum` holds
+    /// over the integers.
+    fn slack_weights(&self) -> Vec<StarkField> {
+        let bits = slack_bits(self.expected_sum);
+        (0..slack_period(self.expected_sum))
+            .map(|i| {
+                if i < bits {
+                    StarkField::from(1u64 << i)
+                } else {
+                    StarkField::zero()
+                }
+            })
+            .collect()
+    }
+}
+
+impl Air for BlankCountConstraints<StarkField> {
+    fn trace_width(&self) -> usize {
+        match self.bound {
+            SumBound::Exact => 2,
+            SumBound::AtMost => 3,
+        }
+    }
+
+    fn trace_length(&self) -> usize {
+        self.domain.size()
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        self.trace_width()
+    }
+
+    fn transition_degree(&self) -> usize {
+        2
+    }
+
+    fn evaluate_transition(
+        &self,
+        current: &[StarkField],
+        next: &[StarkField],
+        periodic: &[StarkField],
+        result: &mut [StarkField],
+    ) {
+        let v = current[VALUE];
+        result[0] = v * (v - StarkField::one());
+        result[1] = next[RUNNING_SUM] - current[RUNNING_SUM] - v;
+        if self.bound == SumBound::AtMost {
+            let b = current[SLACK_BIT];
+            result[1] -= b * periodic[0];
+            result[2] = b * (b - StarkField::one());
+        }
+    }
+
+    /// The last row is padding, so pinning its bit to zero also covers the one row the
+    /// booleanity transition skips.
+    fn boundary_constraints(&self) -> Vec<BoundaryConstraint> {
+        let last = self.domain.size() - 1;
+        vec![
+            BoundaryConstraint {
+                column: RUNNING_SUM,
+                row: 0,
+                value: StarkField::zero(),
+            },
+            BoundaryConstraint {
+                column: VALUE,
+                row: last,
+                value: StarkField::zero(),
+            },
+            BoundaryConstraint {
+                column: RUNNING_SUM,
+                row: last,
+                value: StarkField::from(self.expected_sum),
+            },
+        ]
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<StarkField>> {
+        match self.bound {
+            SumBound::Exact => Vec::new(),
+            SumBound::AtMost => vec![self.slack_weights()],
+        }
+    }
 
-        Ok(())
+    fn public_inputs(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(24);
+        bytes.extend_from_slice(b"blank-count");
+        bytes.extend_from_slice(&self.n.to_be_bytes());
+        bytes.extend_from_slice(&self.expected_sum.to_be_bytes());
+        bytes.push(match self.bound {
+            SumBound::Exact => 0,
+            SumBound::AtMost => 1,
+        });
+        bytes
     }
 }
@@ -1,33 +1,327 @@
 use crate::crypto::hash::QuantumHash;
+use crate::crypto::merkle::{leaf_digest, MerkleDomain, MerkleProof, MerkleTree};
+use crate::stark::prover::StarkParameters;
+use crate::stark::StarkField;
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
+/// Little-endian canonical encoding of a field element.
+pub type FieldBytes = [u8; 32];
+
+pub fn field_to_bytes(value: &StarkField) -> FieldBytes {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&value.into_bigint().to_bytes_le());
+    bytes
+}
+
+/// Decodes a field element, rejecting encodings that are not canonical.
+pub fn field_from_bytes(bytes: &FieldBytes) -> Option<StarkField> {
+    let value = StarkField::from_le_bytes_mod_order(bytes);
+    (field_to_bytes(&value) == *bytes).then_some(value)
+}
+
+/// Maps a 32-byte transcript digest onto the field.
+pub fn field_from_digest(digest: &[u8; 32]) -> StarkField {
+    StarkField::from_le_bytes_mod_order(digest)
+}
+
+/// How a FRI instance folds and samples queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FriOptions {
+    /// Size the domain folds down to; a polynomial of degree below
+    /// `domain.size() / remainder_size` ends up constant.
+    pub remainder_size: usize,
+    pub num_queries: usize,
+    /// Points merged per folding step, a power of two of at least 2. The last step may
+    /// fold by less to land exactly on `remainder_size`.
+    pub folding_factor: usize,
+    /// Leading zero bits the proof-of-work nonce must produce before queries are drawn.
+    pub grinding_bits: u32,
+}
+
+impl FriOptions {
+    pub fn new(params: &StarkParameters, remainder_size: usize) -> Self {
+        FriOptions {
+            remainder_size,
+            num_queries: params.num_queries.max(1) as usize,
+            folding_factor: params.fri_folding_factor,
+            grinding_bits: params.grinding_bits,
+        }
+    }
+}
+
+/// Check of [`FriProof::verify`] that rejected a proof. `layer` counts folding layers
+/// from the first commitment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FriError {
+    InvalidFoldingFactor,
+    LayerCountMismatch { expected: usize, actual: usize },
+    NonCanonicalFinalValue,
+    ProofOfWork,
+    QueryPositionsMismatch,
+    QueryLayerCountMismatch { position: u64 },
+    MalformedLayerValues { position: u64, layer: usize },
+    FoldingMismatch { position: u64, layer: usize },
+    LayerMerkleMismatch { position: u64, layer: usize },
+    FinalValueMismatch { position: u64 },
+}
+
+impl fmt::Display for FriError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FriError::InvalidFoldingFactor => write!(f, "invalid folding factor"),
+            FriError::LayerCountMismatch { expected, actual } => {
+                write!(f, "expected {} folding layers, got {}", expected, actual)
+            }
+            FriError::NonCanonicalFinalValue => write!(f, "final value is not canonical"),
+            FriError::ProofOfWork => write!(f, "proof-of-work nonce rejected"),
+            FriError::QueryPositionsMismatch => {
+                write!(f, "query positions differ from the transcript")
+            }
+            FriError::QueryLayerCountMismatch { position } => {
+                write!(f, "query {} opens the wrong number of layers", position)
+            }
+            FriError::MalformedLayerValues { position, layer } => {
+                write!(
+                    f,
+                    "query {} has malformed values in layer {}",
+                    position, layer
+                )
+            }
+            FriError::FoldingMismatch { position, layer } => {
+                write!(
+                    f,
+                    "query {} does not fold consistently into layer {}",
+                    position, layer
+                )
+            }
+            FriError::LayerMerkleMismatch { position, layer } => write!(
+                f,
+                "query {} fails the Merkle check of layer {}",
+                position, layer
+            ),
+            FriError::FinalValueMismatch { position } => {
+                write!(f, "query {} does not fold to the final value", position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FriError {}
+
+/// FRI commitment to a polynomial evaluated over a (coset) evaluation domain. Layer `k`
+/// of size `N` with folding factor `F` commits, in leaf `i`, to the `F` values at
+/// positions `i + j * N / F`, i.e. at `x * z^j` for the `F`-th roots of unity `z`, so a
+/// single Merkle opening yields every point needed to fold into layer `k + 1`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FriProof {
     pub layer_roots: Vec<[u8; 32]>,
+    pub final_value: FieldBytes,
+    /// Proof-of-work nonce absorbed after `final_value`, see [`FriOptions::grinding_bits`].
+    #[serde(default)]
+    pub pow_nonce: u64,
     pub query_positions: Vec<u64>,
+    pub queries: Vec<FriQuery>,
 }
 
-pub fn derive_fri_layers(
-    polynomial_size: usize,
-    base_root: &[u8; 32],
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FriQuery {
+    pub layers: Vec<FriLayerOpening>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FriLayerOpening {
+    pub values: Vec<FieldBytes>,
+    pub proof: MerkleProof,
+}
+
+/// Runs the FRI commit phase on `evaluations` (the values of a polynomial over `domain`)
+/// until the domain has shrunk to `options.remainder_size` points, at which stage a
+/// polynomial of degree below `domain.size() / remainder_size` has folded to a constant.
+pub fn prove_fri(
+    evaluations: Vec<StarkField>,
+    domain: Radix2EvaluationDomain<StarkField>,
+    options: &FriOptions,
+    seed: &[u8; 32],
     hasher: &dyn QuantumHash,
-) -> Vec<[u8; 32]> {
-    if polynomial_size <= 1 {
-        return Vec::new();
+) -> FriProof {
+    assert_eq!(
+        evaluations.len(),
+        domain.size(),
+        "one evaluation per domain point"
+    );
+    let factors = layer_factors(domain.size(), options);
+    let mut current = evaluations;
+    let mut current_domain = domain;
+    let mut channel = *seed;
+    let mut layer_roots = Vec::new();
+    let mut layers: Vec<(Vec<StarkField>, MerkleTree)> = Vec::new();
+
+    for &factor in &factors {
+        let stride = current.len() / factor;
+        let leaves: Vec<Vec<u8>> = (0..stride)
+            .into_par_iter()
+            .map(|i| encode_coset(&coset_values(&current, i, factor)))
+            .collect();
+        let tree = MerkleTree::new(MerkleDomain::FriLayer, &leaves, hasher);
+        let root = tree.root();
+        channel = absorb(&channel, &root, hasher);
+        let mut beta = field_from_digest(&channel);
+
+        // Folding by `2^k` is `k` binary folds with challenges `beta, beta^2, beta^4, ...`.
+        let mut next = current.clone();
+        while next.len() > stride {
+            next = fold_layer(&next, &current_domain, beta);
+            current_domain = next_domain(&current_domain);
+            beta.square_in_place();
+        }
+        layer_roots.push(root);
+        layers.push((current, tree));
+        current = next;
     }
 
-    let mut layers = Vec::new();
-    let mut current = *base_root;
-    let mut domain = polynomial_size.next_power_of_two().max(2);
-    while domain > 1 {
-        let mut data = Vec::with_capacity(40);
-        data.extend_from_slice(&current);
-        data.extend_from_slice(&(domain as u64).to_be_bytes());
-        current = hasher.hash(&data);
-        layers.push(current);
-        domain /= 2;
+    let final_value = field_to_bytes(&current[0]);
+    channel = absorb(&channel, &final_value, hasher);
+    let pow_nonce = grind(&channel, options.grinding_bits, hasher);
+    channel = absorb(&channel, &pow_nonce.to_be_bytes(), hasher);
+    let query_positions = sample_fri_queries(domain.size(), &channel, options.num_queries, hasher);
+
+    let queries = query_positions
+        .iter()
+        .map(|&position| {
+            let mut index = position as usize;
+            let layers = layers
+                .iter()
+                .zip(&factors)
+                .map(|((values, tree), &factor)| {
+                    let coset = index % (values.len() / factor);
+                    index = coset;
+                    FriLayerOpening {
+                        values: coset_values(values, coset, factor)
+                            .iter()
+                            .map(field_to_bytes)
+                            .collect(),
+                        proof: tree
+                            .get_proof(coset)
+                            .expect("fri layer proof exists for folded coset"),
+                    }
+                })
+                .collect();
+            FriQuery { layers }
+        })
+        .collect();
+
+    FriProof {
+        layer_roots,
+        final_value,
+        pow_nonce,
+        query_positions,
+        queries,
+    }
+}
+
+impl FriProof {
+    /// Checks every query path through the folding layers. `first_layer` holds the values
+    /// the caller expects at `query_positions` in the first layer (for a STARK, the
+    /// composition polynomial recomputed from the opened trace rows).
+    pub fn verify(
+        &self,
+        domain: Radix2EvaluationDomain<StarkField>,
+        options: &FriOptions,
+        seed: &[u8; 32],
+        first_layer: &[StarkField],
+        hasher: &dyn QuantumHash,
+    ) -> Result<(), FriError> {
+        if !options.folding_factor.is_power_of_two() || options.folding_factor < 2 {
+            return Err(FriError::InvalidFoldingFactor);
+        }
+        let factors = layer_factors(domain.size(), options);
+        if self.layer_roots.len() != factors.len() {
+            return Err(FriError::LayerCountMismatch {
+                expected: factors.len(),
+                actual: self.layer_roots.len(),
+            });
+        }
+
+        let mut channel = *seed;
+        let mut betas = Vec::with_capacity(self.layer_roots.len());
+        for root in &self.layer_roots {
+            channel = absorb(&channel, root, hasher);
+            betas.push(field_from_digest(&channel));
+        }
+        let final_value =
+            field_from_bytes(&self.final_value).ok_or(FriError::NonCanonicalFinalValue)?;
+        channel = absorb(&channel, &self.final_value, hasher);
+        if !meets_grinding(&channel, self.pow_nonce, options.grinding_bits, hasher) {
+            return Err(FriError::ProofOfWork);
+        }
+        channel = absorb(&channel, &self.pow_nonce.to_be_bytes(), hasher);
+        let expected_positions =
+            sample_fri_queries(domain.size(), &channel, options.num_queries, hasher);
+        if expected_positions != self.query_positions
+            || self.queries.len() != self.query_positions.len()
+            || first_layer.len() != self.query_positions.len()
+        {
+            return Err(FriError::QueryPositionsMismatch);
+        }
+
+        for ((&position, query), &first) in self
+            .query_positions
+            .iter()
+            .zip(&self.queries)
+            .zip(first_layer)
+        {
+            if query.layers.len() != self.layer_roots.len() {
+                return Err(FriError::QueryLayerCountMismatch { position });
+            }
+            let mut index = position as usize;
+            let mut expected = first;
+            let mut layer_domain = domain;
+            for (layer, (((opening, root), beta), &factor)) in query
+                .layers
+                .iter()
+                .zip(&self.layer_roots)
+                .zip(&betas)
+                .zip(&factors)
+                .enumerate()
+            {
+                let stride = layer_domain.size() / factor;
+                let coset = index % stride;
+                let values = match opening
+                    .values
+                    .iter()
+                    .map(field_from_bytes)
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(values) if values.len() == factor => values,
+                    _ => {
+                        return Err(FriError::MalformedLayerValues { position, layer });
+                    }
+                };
+                if values[index / stride] != expected {
+                    return Err(FriError::FoldingMismatch { position, layer });
+                }
+                let leaf_hash = leaf_digest(MerkleDomain::FriLayer, &encode_coset(&values), hasher);
+                if opening.proof.leaf_hash != leaf_hash
+                    || !opening.proof.opens_index(coset as u64)
+                    || !opening.proof.verify(MerkleDomain::FriLayer, root, hasher)
+                {
+                    return Err(FriError::LayerMerkleMismatch { position, layer });
+                }
+                let (folded, next) = fold_coset(values, coset, layer_domain, *beta);
+                expected = folded;
+                index = coset;
+                layer_domain = next;
+            }
+            if expected != final_value {
+                return Err(FriError::FinalValueMismatch { position });
+            }
+        }
+        Ok(())
     }
-    layers
 }
 
 pub fn sample_fri_queries(
@@ -40,6 +334,7 @@ pub fn sample_fri_queries(
         return Vec::new();
     }
 
+    let num_queries = num_queries.min(polynomial_size);
     let mut positions = Vec::new();
     let mut seed = *base_root;
     let mut counter = 0u64;
@@ -59,3 +354,118 @@ pub fn sample_fri_queries(
     positions.sort_unstable();
     positions
 }
+
+/// Folding factor of every layer needed to shrink a domain of `domain_size` points down
+/// to `options.remainder_size` points.
+pub fn layer_factors(domain_size: usize, options: &FriOptions) -> Vec<usize> {
+    let mut size = domain_size;
+    let mut factors = Vec::new();
+    let remainder = options.remainder_size.max(1);
+    while size > remainder {
+        let factor = options.folding_factor.max(2).min(size / remainder);
+        size /= factor;
+        factors.push(factor);
+    }
+    factors
+}
+
+/// Number of folding layers needed to shrink a domain of `domain_size` points down to
+/// `options.remainder_size` points.
+pub fn layer_count(domain_size: usize, options: &FriOptions) -> usize {
+    layer_factors(domain_size, options).len()
+}
+
+/// Smallest nonce whose hash with `channel` starts with `bits` zero bits.
+fn grind(channel: &[u8; 32], bits: u32, hasher: &dyn QuantumHash) -> u64 {
+    (0..)
+        .find(|&nonce| meets_grinding(channel, nonce, bits, hasher))
+        .expect("a proof-of-work nonce exists")
+}
+
+fn meets_grinding(channel: &[u8; 32], nonce: u64, bits: u32, hasher: &dyn QuantumHash) -> bool {
+    if bits == 0 {
+        return true;
+    }
+    let digest = absorb(channel, &nonce.to_be_bytes(), hasher);
+    let mut word = [0u8; 16];
+    word.copy_from_slice(&digest[..16]);
+    u128::from_be_bytes(word).leading_zeros() >= bits
+}
+
+/// `f_next(x^2) = f(x) + f(-x) + beta * (f(x) - f(-x)) / x`, given `1 / x`. This is twice
+/// the textbook fold `f_even + beta * f_odd`; the constant factor keeps the degree and
+/// saves an inversion per point.
+fn fold_pair(low: StarkField, high: StarkField, x_inv: StarkField, beta: StarkField) -> StarkField {
+    low + high + beta * (low - high) * x_inv
+}
+
+/// Folds the `values` at positions `coset + j * N / F` of a layer over `domain` down to
+/// the value at `coset` of the next layer, returning it with the next layer's domain.
+fn fold_coset(
+    mut values: Vec<StarkField>,
+    coset: usize,
+    mut domain: Radix2EvaluationDomain<StarkField>,
+    mut beta: StarkField,
+) -> (StarkField, Radix2EvaluationDomain<StarkField>) {
+    let stride = domain.size() / values.len();
+    while values.len() > 1 {
+        let half = values.len() / 2;
+        values = (0..half)
+            .map(|j| {
+                let x_inv = domain.element(coset + j * stride).inverse();
+                fold_pair(
+                    values[j],
+                    values[j + half],
+                    x_inv.expect("coset domains exclude zero"),
+                    beta,
+                )
+            })
+            .collect();
+        domain = next_domain(&domain);
+        beta.square_in_place();
+    }
+    (values[0], domain)
+}
+
+fn fold_layer(
+    values: &[StarkField],
+    domain: &Radix2EvaluationDomain<StarkField>,
+    beta: StarkField,
+) -> Vec<StarkField> {
+    let half = values.len() / 2;
+    let mut x_inv = domain.coset_offset_inv();
+    let mut folded = Vec::with_capacity(half);
+    for i in 0..half {
+        folded.push(fold_pair(values[i], values[i + half], x_inv, beta));
+        x_inv *= domain.group_gen_inv;
+    }
+    folded
+}
+
+/// The image of `domain` under `x -> x^2`: half the size, squared offset.
+fn next_domain(domain: &Radix2EvaluationDomain<StarkField>) -> Radix2EvaluationDomain<StarkField> {
+    let offset = domain.coset_offset().square();
+    Radix2EvaluationDomain::<StarkField>::new(domain.size() / 2)
+        .and_then(|d| d.get_coset(offset))
+        .expect("radix2 domain halves")
+}
+
+fn coset_values(values: &[StarkField], coset: usize, factor: usize) -> Vec<StarkField> {
+    let stride = values.len() / factor;
+    (0..factor).map(|j| values[coset + j * stride]).collect()
+}
+
+fn encode_coset(values: &[StarkField]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(32 * values.len());
+    for value in values {
+        bytes.extend_from_slice(&field_to_bytes(value));
+    }
+    bytes
+}
+
+fn absorb(channel: &[u8; 32], data: &[u8], hasher: &dyn QuantumHash) -> [u8; 32] {
+    let mut buf = Vec::with_capacity(32 + data.len());
+    buf.extend_from_slice(channel);
+    buf.extend_from_slice(data);
+    hasher.hash(&buf)
+}
@@ -1,8 +1,15 @@
+pub mod air;
 pub mod constraints;
 pub mod fri;
+pub mod params;
 pub mod prover;
+pub mod verifier;
 
-pub use constraints::{BlankCountConstraints, ConstraintViolation};
-pub use prover::{BlankCountProof, StarkParameters};
+pub use air::{Air, BoundaryConstraint};
+pub use constraints::{BlankCountConstraints, ConstraintViolation, SumBound};
+pub use fri::FriError;
+pub use params::{ParameterEngine, ParameterError, SecurityEstimate};
+pub use prover::{prove, BlankCountProof, BlankProofError, StarkParameters, StarkProof};
+pub use verifier::{verify, StarkError};
 
 pub type StarkField = ark_bls12_381::Fr;
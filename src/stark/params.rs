@@ -0,0 +1,199 @@
+use crate::stark::air::composition_degree_factor;
+use crate::stark::prover::StarkParameters;
+use crate::stark::StarkField;
+use ark_ff::PrimeField;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Output size of every [`crate::crypto::hash::QuantumHash`] digest.
+pub const HASH_OUTPUT_BITS: u32 = 256;
+
+/// Largest trace the derived parameters are sized for, as a power of two.
+const MAX_TRACE_LOG: u32 = 32;
+/// FRI inverse rate the engine aims for; every query then contributes three bits.
+const TARGET_INVERSE_RATE: usize = 8;
+const FOLDING_FACTOR: usize = 4;
+const MAX_GRINDING_BITS: u32 = 20;
+
+/// Security a parameter set achieves for one proof. Conjectured bits follow the usual
+/// FRI conjecture (each query removes `log2(1 / rate)` bits); proven bits use the
+/// Johnson-bound regime, where a query is only worth half of that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecurityEstimate {
+    pub conjectured_bits: u32,
+    pub proven_bits: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParameterError {
+    /// Collision resistance caps security at half the hash output.
+    HashTooShort {
+        requested: u32,
+        hash_bits: u32,
+    },
+    FieldTooSmall {
+        requested: u32,
+        field_bits: u32,
+    },
+    InsufficientSecurity {
+        requested: u32,
+        achieved: u32,
+    },
+    /// Zero-knowledge openings would reveal at least as many evaluations of a masked
+    /// column as its mask has random coefficients.
+    ZeroKnowledgeOpenings {
+        openings: usize,
+        mask_degree: usize,
+    },
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParameterError::HashTooShort {
+                requested,
+                hash_bits,
+            } => write!(
+                f,
+                "a {}-bit hash cannot reach {} bits of security",
+                hash_bits, requested
+            ),
+            ParameterError::FieldTooSmall {
+                requested,
+                field_bits,
+            } => write!(
+                f,
+                "a {}-bit field cannot reach {} bits of security",
+                field_bits, requested
+            ),
+            ParameterError::InsufficientSecurity {
+                requested,
+                achieved,
+            } => write!(
+                f,
+                "STARK parameters reach {} bits, below the requested {}",
+                achieved, requested
+            ),
+            ParameterError::ZeroKnowledgeOpenings {
+                openings,
+                mask_degree,
+            } => write!(
+                f,
+                "{} trace openings exhaust a zero-knowledge mask of degree {}",
+                openings, mask_degree
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParameterError {}
+
+/// Derives [`StarkParameters`] (blowup factor, query count, grinding bits, FRI folding
+/// factor) for a target security level, field and hash, and estimates the security an
+/// existing parameter set achieves.
+#[derive(Debug, Clone)]
+pub struct ParameterEngine {
+    pub security_level: u32,
+    pub field_bits: u32,
+    pub hash_bits: u32,
+    /// Highest transition-constraint degree the parameters must support.
+    pub transition_degree: usize,
+    pub zero_knowledge: bool,
+}
+
+impl ParameterEngine {
+    /// Engine for this crate's field and hash and degree-2 constraints.
+    pub fn new(security_level: u32) -> Self {
+        ParameterEngine {
+            security_level,
+            field_bits: StarkField::MODULUS_BIT_SIZE,
+            hash_bits: HASH_OUTPUT_BITS,
+            transition_degree: 2,
+            zero_knowledge: false,
+        }
+    }
+
+    pub fn with_zero_knowledge(mut self, zero_knowledge: bool) -> Self {
+        self.zero_knowledge = zero_knowledge;
+        self
+    }
+
+    pub fn derive(&self) -> Result<StarkParameters, ParameterError> {
+        let requested = self.security_level;
+        if self.hash_bits / 2 < requested {
+            return Err(ParameterError::HashTooShort {
+                requested,
+                hash_bits: self.hash_bits,
+            });
+        }
+        // Blow up far enough that the composition polynomial still has rate 1/8.
+        let degree_factor = composition_degree_factor(self.transition_degree, self.zero_knowledge);
+        let blowup_factor = TARGET_INVERSE_RATE * degree_factor;
+        let lde_log = MAX_TRACE_LOG + blowup_factor.trailing_zeros();
+        if self.field_bits.saturating_sub(lde_log) < requested {
+            return Err(ParameterError::FieldTooSmall {
+                requested,
+                field_bits: self.field_bits,
+            });
+        }
+
+        // A little proof of work buys back a few queries at negligible prover cost.
+        let grinding_bits = (requested / 16).min(MAX_GRINDING_BITS);
+        let bits_per_query = TARGET_INVERSE_RATE.trailing_zeros();
+        let num_queries = (requested - grinding_bits).div_ceil(bits_per_query).max(1);
+        Ok(StarkParameters {
+            security_level: requested,
+            num_queries,
+            chunk_size: 1024,
+            blowup_factor,
+            zero_knowledge: self.zero_knowledge,
+            grinding_bits,
+            fri_folding_factor: FOLDING_FACTOR,
+        })
+    }
+
+    /// Security of a FRI instance over `lde_size` points with inverse rate
+    /// `inverse_rate` (the size the domain folds down to).
+    pub fn estimate(
+        &self,
+        params: &StarkParameters,
+        lde_size: usize,
+        inverse_rate: usize,
+    ) -> SecurityEstimate {
+        let lde_log = lde_size.max(1).ilog2();
+        let bits_per_query = inverse_rate.max(1).ilog2();
+        let hash_bits = self.hash_bits / 2;
+        let queries = params
+            .num_queries
+            .saturating_mul(bits_per_query)
+            .saturating_add(params.grinding_bits);
+        let proven_queries = (params.num_queries.saturating_mul(bits_per_query) / 2)
+            .saturating_add(params.grinding_bits);
+        SecurityEstimate {
+            conjectured_bits: queries
+                .min(self.field_bits.saturating_sub(lde_log))
+                .min(hash_bits),
+            proven_bits: proven_queries
+                .min(self.field_bits.saturating_sub(2 * lde_log + 1))
+                .min(hash_bits),
+        }
+    }
+}
+
+/// Rejects `params` if a FRI instance over `lde_size` points with inverse rate
+/// `inverse_rate` falls below `params.security_level` conjectured bits.
+pub fn check_security(
+    params: &StarkParameters,
+    lde_size: usize,
+    inverse_rate: usize,
+) -> Result<SecurityEstimate, ParameterError> {
+    let estimate =
+        ParameterEngine::new(params.security_level).estimate(params, lde_size, inverse_rate);
+    if estimate.conjectured_bits < params.security_level {
+        return Err(ParameterError::InsufficientSecurity {
+            requested: params.security_level,
+            achieved: estimate.conjectured_bits,
+        });
+    }
+    Ok(estimate)
+}
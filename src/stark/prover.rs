@@ -1,246 +1,552 @@
-use crate::crypto::hash::QuantumHash;
-use crate::crypto::merkle::{ChunkedMerkleProof, ChunkedMerkleTree};
+use crate::crypto::hash::{random_salts, salted_leaf, LeafSalt, QuantumHash};
+use crate::crypto::merkle::{leaf_digest, MerkleDomain, MerkleProof, MerkleTree};
 use crate::crypto::polynomial::BlankPolynomial;
-use crate::stark::constraints::BlankCountConstraints;
-use crate::stark::fri::{derive_fri_layers, sample_fri_queries, FriProof};
+use crate::stark::air::{
+    column_degree_factor, composition_degree_factor, trace_domains, Air, AirContext,
+};
+use crate::stark::constraints::{BlankCountConstraints, ConstraintViolation, SumBound};
+use crate::stark::fri::{
+    field_from_bytes, field_from_digest, field_to_bytes, prove_fri, FieldBytes, FriOptions,
+    FriProof,
+};
+use crate::stark::params::{check_security, ParameterEngine, ParameterError, SecurityEstimate};
+use crate::stark::verifier::{verify, StarkError};
 use crate::stark::StarkField;
+use ark_ff::{batch_inversion, PrimeField, Zero};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use rand::Rng;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+/// Use [`StarkParameters::for_security_level`] (or [`ParameterEngine`]) rather than
+/// picking query counts by hand: provers and verifiers refuse parameter sets whose
+/// conjectured security falls below `security_level`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StarkParameters {
     pub security_level: u32,
     pub num_queries: u32,
     pub chunk_size: usize,
+    /// Ratio between the low-degree-extension domain and the trace domain. Must be a
+    /// power of two of at least 2.
+    pub blowup_factor: usize,
+    /// Masks every trace column with a random multiple of the trace-domain vanishing
+    /// polynomial, adds one random blinding column of composition degree and salts the
+    /// committed rows, so openings on the extended domain reveal nothing about the trace
+    /// as long as fewer than `trace_length` of them are made (see
+    /// [`zero_knowledge_openings`]). Doubles the column degree, which needs a blowup
+    /// factor of at least 8 for degree-2 constraints.
+    #[serde(default)]
+    pub zero_knowledge: bool,
+    /// Proof-of-work bits each FRI proof grinds before its queries are drawn.
+    #[serde(default)]
+    pub grinding_bits: u32,
+    /// Points merged per FRI folding step. Must be a power of two of at least 2.
+    #[serde(default = "default_folding_factor")]
+    pub fri_folding_factor: usize,
+}
+
+fn default_folding_factor() -> usize {
+    2
+}
+
+impl StarkParameters {
+    /// Parameters for `security_level` bits with this crate's field and hash.
+    pub fn for_security_level(security_level: u32) -> Result<Self, ParameterError> {
+        ParameterEngine::new(security_level).derive()
+    }
 }
 
 impl Default for StarkParameters {
     fn default() -> Self {
-        StarkParameters {
-            security_level: 128,
-            num_queries: 32,
-            chunk_size: 1024,
-        }
+        StarkParameters::for_security_level(128)
+            .expect("128-bit parameters exist for the crate's field and hash")
     }
 }
 
+/// Proof that a committed trace satisfies some [`Air`]. The trace columns are
+/// interpolated over the trace domain `H` and extended onto a coset `D` that is
+/// `blowup_factor` times larger; the constraint quotients and the columns themselves
+/// are folded into one composition polynomial whose evaluations on `D` are checked by
+/// FRI. Every FRI query also opens the trace rows at `x` and `w * x`, from which the
+/// verifier recomputes the composition value.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BlankCountProof {
+pub struct StarkProof {
     pub trace_root: [u8; 32],
-    pub fri_proof: FriProof,
-    pub queries: Vec<BlankQuery>,
-    pub final_row: TraceRowOpening,
-    pub total_sum: u64,
     pub trace_length: u64,
+    pub queries: Vec<TraceQuery>,
+    pub fri_proof: FriProof,
 }
 
-impl BlankCountProof {
-    pub fn verify(
+/// The trace rows at a FRI query point `x` and at its successor `w * x`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceQuery {
+    pub current: TraceRowOpening,
+    pub next: TraceRowOpening,
+}
+
+/// One committed row of the low-degree extension: every trace column at a point of `D`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceRowOpening {
+    pub index: u64,
+    pub values: Vec<FieldBytes>,
+    /// Row salt, present when the trace was committed with `zero_knowledge`.
+    #[serde(default)]
+    pub salt: Option<LeafSalt>,
+    pub proof: MerkleProof,
+}
+
+impl TraceRowOpening {
+    /// Returns the decoded row if it has `width` columns and sits at `index` under `root`.
+    pub(crate) fn verify(
         &self,
-        constraints: &BlankCountConstraints<StarkField>,
+        width: usize,
+        root: &[u8; 32],
         hasher: &dyn QuantumHash,
-    ) -> bool {
-        if self.total_sum != constraints.expected_sum {
-            debug_stark("stark verify failed: total sum mismatch");
-            return false;
-        }
-
-        let expected_layers =
-            derive_fri_layers(self.trace_length as usize, &self.trace_root, hasher);
-        if expected_layers != self.fri_proof.layer_roots {
-            debug_stark("stark verify failed: layer roots mismatch");
-            return false;
+    ) -> Option<Vec<StarkField>> {
+        if self.values.len() != width {
+            return None;
         }
+        let row = self
+            .values
+            .iter()
+            .map(field_from_bytes)
+            .collect::<Option<Vec<_>>>()?;
+        let leaf = match &self.salt {
+            Some(salt) => salted_leaf(&encode_trace_row(&row), salt),
+            None => encode_trace_row(&row),
+        };
+        let valid = self.proof.leaf_hash == leaf_digest(MerkleDomain::Trace, &leaf, hasher)
+            && self.proof.opens_index(self.index)
+            && self.proof.verify(MerkleDomain::Trace, root, hasher);
+        valid.then_some(row)
+    }
+}
 
-        if self.fri_proof.query_positions.len() != self.queries.len() {
-            debug_stark("stark verify failed: query length mismatch");
-            return false;
-        }
+/// Low-degree extension of a trace together with its Merkle commitment. The prover keeps
+/// it after [`prove_committed`] so that later openings such as
+/// [`TraceCommitment::prove_column_values`] refer to the same `trace_root`.
+pub struct TraceCommitment {
+    columns: Vec<Vec<StarkField>>,
+    salts: Option<Vec<LeafSalt>>,
+    tree: MerkleTree,
+    trace_domain: Radix2EvaluationDomain<StarkField>,
+    lde_domain: Radix2EvaluationDomain<StarkField>,
+    /// Random coefficients of each column mask, present with `zero_knowledge`.
+    mask_degree: Option<usize>,
+    /// Column evaluations revealed by the proofs made so far.
+    openings: AtomicUsize,
+}
 
-        for (position, query) in self
-            .fri_proof
-            .query_positions
-            .iter()
-            .copied()
-            .zip(&self.queries)
-        {
-            if query.position != position || position >= self.trace_length {
-                debug_stark("stark verify failed: query position invalid");
-                return false;
-            }
-            if !query.current.verify(&self.trace_root, hasher) {
-                debug_stark("stark verify failed: current row merkle mismatch");
-                return false;
-            }
-            if query.current.value > 1 {
-                debug_stark("stark verify failed: query value not binary");
-                return false;
-            }
-            match &query.previous {
-                Some(prev) => {
-                    if prev.index + 1 != query.current.index {
-                        debug_stark("stark verify failed: previous index mismatch");
-                        return false;
-                    }
-                    if !prev.verify(&self.trace_root, hasher) {
-                        debug_stark("stark verify failed: previous row merkle mismatch");
-                        return false;
-                    }
-                    if query.current.running_sum != prev.running_sum + query.current.value as u64 {
-                        debug_stark("stark verify failed: running sum mismatch");
-                        return false;
+impl TraceCommitment {
+    /// Extends `trace` (one vector per column, `air.trace_length()` rows each) onto the
+    /// LDE domain and commits to it row by row. With `zero_knowledge`, each column `c`
+    /// is replaced by `c(x) + Z_H(x) * r(x)` for a random `r` of degree below the trace
+    /// length: it still agrees with the trace on `H`, while any fewer than
+    /// `trace_length` points of the coset `D` are uniformly random. The blinding column
+    /// is random up to the composition degree, so it hides the composition values FRI
+    /// opens as well.
+    pub fn new<A: Air>(
+        air: &A,
+        trace: &[Vec<StarkField>],
+        params: &StarkParameters,
+        hasher: &dyn QuantumHash,
+    ) -> Self {
+        let (trace_domain, lde_domain) = trace_domains(air.trace_length(), params.blowup_factor)
+            .expect("trace length and blowup factor are powers of two");
+        assert_eq!(
+            trace.len(),
+            air.trace_width(),
+            "one trace vector per column"
+        );
+        let length = air.trace_length();
+        let mut columns: Vec<Vec<StarkField>> = trace
+            .par_iter()
+            .map(|column| {
+                assert_eq!(column.len(), length, "full trace columns");
+                let mut coefficients = trace_domain.ifft(column);
+                if params.zero_knowledge {
+                    // Z_H(x) = x^n - 1, so adding Z_H * r shifts r up by n and subtracts it.
+                    let mask = random_field_elements(length);
+                    for (coefficient, r) in coefficients.iter_mut().zip(&mask) {
+                        *coefficient -= r;
                     }
+                    coefficients.extend(mask);
                 }
-                None => {
-                    if query.current.index != 0
-                        || query.current.running_sum != query.current.value as u64
-                    {
-                        debug_stark("stark verify failed: initial row mismatch");
-                        return false;
-                    }
+                lde_domain.fft(&coefficients)
+            })
+            .collect();
+        if params.zero_knowledge {
+            let degree = composition_degree_factor(air.transition_degree(), true) * length;
+            columns.push(lde_domain.fft(&random_field_elements(degree)));
+        }
+        let salts = params
+            .zero_knowledge
+            .then(|| random_salts(lde_domain.size()));
+        let rows: Vec<Vec<u8>> = (0..lde_domain.size())
+            .into_par_iter()
+            .map(|i| {
+                let row = encode_trace_row(&row_at(&columns, i));
+                match &salts {
+                    Some(salts) => salted_leaf(&row, &salts[i]),
+                    None => row,
                 }
-            }
+            })
+            .collect();
+        TraceCommitment {
+            tree: MerkleTree::new(MerkleDomain::Trace, &rows, hasher),
+            columns,
+            salts,
+            trace_domain,
+            lde_domain,
+            mask_degree: params.zero_knowledge.then_some(length),
+            openings: AtomicUsize::new(0),
         }
+    }
 
-        if self.final_row.index + 1 != self.trace_length {
-            debug_stark("stark verify failed: final row index mismatch");
-            return false;
-        }
-        if !self.final_row.verify(&self.trace_root, hasher) {
-            debug_stark("stark verify failed: final row merkle mismatch");
-            return false;
-        }
-        if self.final_row.running_sum != self.total_sum {
-            debug_stark("stark verify failed: final sum mismatch");
-            return false;
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    pub fn trace_length(&self) -> usize {
+        self.trace_domain.size()
+    }
+
+    /// Random coefficients masking each column, or `None` without zero knowledge. Fewer
+    /// openings than this reveal nothing about the trace.
+    pub fn mask_degree(&self) -> Option<usize> {
+        self.mask_degree
+    }
+
+    /// Column evaluations the proofs over this commitment have revealed so far, counted
+    /// as in [`zero_knowledge_openings`].
+    pub fn openings(&self) -> usize {
+        self.openings.load(Ordering::SeqCst)
+    }
+
+    /// Counts `count` more openings, refusing them if they would reach the mask degree.
+    fn reserve_openings(&self, count: usize) -> Result<(), ParameterError> {
+        let mask_degree = self.mask_degree;
+        self.openings
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |opened| {
+                let total = opened + count;
+                match mask_degree {
+                    Some(mask_degree) if total >= mask_degree => None,
+                    _ => Some(total),
+                }
+            })
+            .map(|_| ())
+            .map_err(|opened| ParameterError::ZeroKnowledgeOpenings {
+                openings: opened + count,
+                mask_degree: mask_degree.unwrap_or_default(),
+            })
+    }
+
+    /// Proves that trace column `column` takes `value` at `row` for every `(row, value)`
+    /// claim. The quotients `(c(x) - value) / (x - w^row)` are only polynomials if every
+    /// claim holds, so a random combination of them is checked by FRI on the committed LDE.
+    ///
+    /// Refuses to prove once a zero-knowledge commitment would have revealed as many
+    /// evaluations as its mask degree.
+    pub fn prove_column_values(
+        &self,
+        column: usize,
+        claims: &[(usize, StarkField)],
+        params: &StarkParameters,
+        hasher: &dyn QuantumHash,
+    ) -> Result<ColumnValueProof, ParameterError> {
+        self.reserve_openings(zero_knowledge_openings(params, 0, 1))?;
+        let seed = column_value_seed(&self.root(), column, claims, hasher);
+        let coefficients = claim_coefficients(&seed, claims.len(), hasher);
+
+        let points: Vec<StarkField> = self.lde_domain.elements().collect();
+        let mut quotient = vec![StarkField::zero(); points.len()];
+        for (&(row, value), coefficient) in claims.iter().zip(&coefficients) {
+            let point = self.trace_domain.element(row);
+            let mut denominators: Vec<StarkField> = points.iter().map(|&x| x - point).collect();
+            batch_inversion(&mut denominators);
+            for ((acc, &evaluation), inverse) in quotient
+                .iter_mut()
+                .zip(&self.columns[column])
+                .zip(&denominators)
+            {
+                *acc += *coefficient * (evaluation - value) * inverse;
+            }
         }
 
-        true
+        let remainder_size =
+            self.lde_domain.size() / self.trace_domain.size() / column_degree_factor(params);
+        let fri_proof = prove_fri(
+            quotient,
+            self.lde_domain,
+            &FriOptions::new(params, remainder_size),
+            &seed,
+            hasher,
+        );
+        let queries = fri_proof
+            .query_positions
+            .iter()
+            .map(|&position| self.row_opening(position as usize))
+            .collect();
+        Ok(ColumnValueProof { queries, fri_proof })
+    }
+
+    fn row_opening(&self, index: usize) -> TraceRowOpening {
+        TraceRowOpening {
+            index: index as u64,
+            values: row_at(&self.columns, index)
+                .iter()
+                .map(field_to_bytes)
+                .collect(),
+            salt: self.salts.as_ref().map(|salts| salts[index]),
+            proof: self
+                .tree
+                .get_proof(index)
+                .expect("row proof must exist for valid index"),
+        }
     }
 }
 
-pub fn generate_blank_count_proof(
-    polynomial: &BlankPolynomial,
-    constraints: &BlankCountConstraints<StarkField>,
+/// Opening of single trace cells against a committed `trace_root`, produced by
+/// [`TraceCommitment::prove_column_values`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnValueProof {
+    pub queries: Vec<TraceRowOpening>,
+    pub fri_proof: FriProof,
+}
+
+/// Proves that `trace` (one vector per column, `air.trace_length()` rows each) satisfies
+/// `air`. Panics if the AIR and parameters are inconsistent or, with zero knowledge, if
+/// the proof's openings reach the mask degree; an unsatisfying trace still yields a
+/// proof, which the verifier rejects.
+pub fn prove<A: Air>(
+    air: &A,
+    trace: &[Vec<StarkField>],
     params: &StarkParameters,
     hasher: &dyn QuantumHash,
-) -> BlankCountProof {
-    let _ = constraints.check(polynomial);
-
-    let trace_rows = build_trace_rows(polynomial);
-    let serialized_rows: Vec<Vec<u8>> = trace_rows.iter().map(serialize_trace_row).collect();
-    let trace_tree = ChunkedMerkleTree::new(&serialized_rows, hasher, params.chunk_size);
-    let trace_root = trace_tree.root();
-    let trace_length = trace_rows.len() as u64;
-
-    let layer_roots = derive_fri_layers(trace_rows.len(), &trace_root, hasher);
-    let query_positions = sample_fri_queries(
-        trace_rows.len(),
-        &trace_root,
-        params.num_queries.max(1) as usize,
+) -> StarkProof {
+    let committed = TraceCommitment::new(air, trace, params, hasher);
+    prove_committed(air, &committed, params, hasher)
+}
+
+/// [`prove`] for a trace that has already been committed.
+pub fn prove_committed<A: Air>(
+    air: &A,
+    trace: &TraceCommitment,
+    params: &StarkParameters,
+    hasher: &dyn QuantumHash,
+) -> StarkProof {
+    let context = AirContext::new(air, params).expect("AIR and STARK parameters are consistent");
+    trace
+        .reserve_openings(zero_knowledge_openings(params, 1, 0))
+        .expect("zero-knowledge mask exceeds the proof's openings");
+    let trace_root = trace.root();
+    let lde_size = context.lde_domain.size();
+
+    let seed = context.channel_seed(&trace_root, hasher);
+    let coefficients = context.composition_coefficients(&seed, hasher);
+    let composition = context.composition_evaluations(&coefficients, &trace.columns);
+    let fri_proof = prove_fri(
+        composition,
+        context.lde_domain,
+        &FriOptions::new(params, context.remainder_size),
+        &AirContext::<A>::fri_seed(&seed, hasher),
         hasher,
     );
-    let queries = query_positions
+
+    let offset = context.next_row_offset();
+    let queries = fri_proof
+        .query_positions
         .iter()
-        .map(|&pos| build_query(pos as usize, &trace_rows, &trace_tree))
+        .map(|&position| {
+            let index = position as usize;
+            TraceQuery {
+                current: trace.row_opening(index),
+                next: trace.row_opening((index + offset) % lde_size),
+            }
+        })
         .collect();
-    let final_row = build_row_opening(trace_rows.len() - 1, &trace_rows, &trace_tree);
 
-    BlankCountProof {
+    StarkProof {
         trace_root,
-        fri_proof: FriProof {
-            layer_roots,
-            query_positions,
-        },
+        trace_length: air.trace_length() as u64,
         queries,
-        final_row,
-        total_sum: polynomial.sum(),
-        trace_length,
+        fri_proof,
     }
 }
 
+/// STARK for "the committed blank vector is boolean and its sum meets `bound`", i.e.
+/// [`prove`] applied to the [`BlankCountConstraints`] AIR. Only the public bound is
+/// carried, so an [`SumBound::AtMost`] proof does not reveal the actual count.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TraceRowOpening {
-    pub index: u64,
-    pub value: u8,
-    pub running_sum: u64,
-    pub proof: ChunkedMerkleProof,
+pub struct BlankCountProof {
+    pub expected_sum: u64,
+    pub bound: SumBound,
+    pub proof: StarkProof,
 }
 
-impl TraceRowOpening {
-    fn verify(&self, root: &[u8; 32], hasher: &dyn QuantumHash) -> bool {
-        self.proof.verify(root, hasher)
+impl BlankCountProof {
+    pub fn verify(
+        &self,
+        constraints: &BlankCountConstraints<StarkField>,
+        params: &StarkParameters,
+        hasher: &dyn QuantumHash,
+    ) -> Result<(), StarkError> {
+        if self.expected_sum != constraints.expected_sum || self.bound != constraints.bound {
+            return Err(StarkError::StatementMismatch);
+        }
+        verify(constraints, &self.proof, params, hasher)
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BlankQuery {
-    pub position: u64,
-    pub current: TraceRowOpening,
-    pub previous: Option<TraceRowOpening>,
+/// Why [`generate_blank_count_proof`], or a [`crate::protocol::prover::ProverState`]
+/// committing to its blank budget, refused to prove.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlankProofError {
+    Parameters(ParameterError),
+    /// The blank vector breaks the constraints, so any proof of it would be rejected.
+    Unsatisfied(ConstraintViolation),
 }
 
-#[derive(Debug, Clone)]
-struct TraceRow {
-    index: u64,
-    value: u8,
-    running_sum: u64,
+impl fmt::Display for BlankProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlankProofError::Parameters(err) => write!(f, "{}", err),
+            BlankProofError::Unsatisfied(err) => write!(f, "unsatisfiable statement: {}", err),
+        }
+    }
 }
 
-fn build_trace_rows(polynomial: &BlankPolynomial) -> Vec<TraceRow> {
-    let mut rows = Vec::with_capacity(polynomial.len());
-    for (idx, &value) in polynomial.values().iter().enumerate() {
-        let running_sum = polynomial.running_sum(idx);
-        rows.push(TraceRow {
-            index: idx as u64,
-            value,
-            running_sum,
-        });
+impl std::error::Error for BlankProofError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BlankProofError::Parameters(err) => Some(err),
+            BlankProofError::Unsatisfied(err) => Some(err),
+        }
     }
-    rows
 }
 
-fn serialize_trace_row(row: &TraceRow) -> Vec<u8> {
-    let mut bytes = Vec::with_capacity(17);
-    bytes.extend_from_slice(&row.index.to_be_bytes());
-    bytes.push(row.value);
-    bytes.extend_from_slice(&row.running_sum.to_be_bytes());
-    bytes
+/// Refuses `params` that fall below their own `security_level` for this AIR, and blank
+/// vectors that do not satisfy `constraints`. Use [`prove`] to build a proof of an
+/// unsatisfying trace anyway.
+pub fn generate_blank_count_proof(
+    polynomial: &BlankPolynomial,
+    constraints: &BlankCountConstraints<StarkField>,
+    params: &StarkParameters,
+    hasher: &dyn QuantumHash,
+) -> Result<BlankCountProof, BlankProofError> {
+    check_air_security(constraints, params).map_err(BlankProofError::Parameters)?;
+    constraints
+        .check(polynomial)
+        .map_err(BlankProofError::Unsatisfied)?;
+    let trace = constraints.build_trace(polynomial);
+    Ok(BlankCountProof {
+        expected_sum: constraints.expected_sum,
+        bound: constraints.bound,
+        proof: prove(constraints, &trace, params, hasher),
+    })
+}
+
+/// Security of the composition FRI that [`prove`] runs for `air` under `params`.
+pub fn check_air_security<A: Air>(
+    air: &A,
+    params: &StarkParameters,
+) -> Result<SecurityEstimate, ParameterError> {
+    let context = AirContext::new(air, params).ok_or(ParameterError::InsufficientSecurity {
+        requested: params.security_level,
+        achieved: 0,
+    })?;
+    let estimate = check_security(params, context.lde_domain.size(), context.remainder_size)?;
+    check_zero_knowledge_openings(params, air.trace_length(), 1, 0)?;
+    Ok(estimate)
 }
 
-fn build_row_opening(
-    index: usize,
-    trace_rows: &[TraceRow],
-    tree: &ChunkedMerkleTree,
-) -> TraceRowOpening {
-    let row = &trace_rows[index];
-    let proof = tree
-        .get_proof(index)
-        .expect("row proof must exist for valid index");
-    TraceRowOpening {
-        index: row.index,
-        value: row.value,
-        running_sum: row.running_sum,
-        proof,
+/// Upper bound on the column evaluations a zero-knowledge [`TraceCommitment`] reveals
+/// through `proofs` [`prove_committed`] proofs and `column_proofs`
+/// [`TraceCommitment::prove_column_values`] proofs: two rows per composition query, and
+/// the first FRI coset of every column-value query, whose quotient values give the
+/// column away.
+pub fn zero_knowledge_openings(
+    params: &StarkParameters,
+    proofs: usize,
+    column_proofs: usize,
+) -> usize {
+    let queries = params.num_queries as usize;
+    queries * (2 * proofs + params.fri_folding_factor * column_proofs)
+}
+
+/// Refuses zero-knowledge `params` under which those proofs over a trace of
+/// `trace_length` rows would reveal as many evaluations as the column masks have random
+/// coefficients. Without zero knowledge there is nothing to protect.
+pub fn check_zero_knowledge_openings(
+    params: &StarkParameters,
+    trace_length: usize,
+    proofs: usize,
+    column_proofs: usize,
+) -> Result<(), ParameterError> {
+    let openings = zero_knowledge_openings(params, proofs, column_proofs);
+    if params.zero_knowledge && openings >= trace_length {
+        return Err(ParameterError::ZeroKnowledgeOpenings {
+            openings,
+            mask_degree: trace_length,
+        });
     }
+    Ok(())
 }
 
-fn build_query(index: usize, trace_rows: &[TraceRow], tree: &ChunkedMerkleTree) -> BlankQuery {
-    let current = build_row_opening(index, trace_rows, tree);
-    let previous = if index > 0 {
-        Some(build_row_opening(index - 1, trace_rows, tree))
-    } else {
-        None
-    };
-    BlankQuery {
-        position: current.index,
-        current,
-        previous,
+fn encode_trace_row(row: &[StarkField]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(32 * row.len());
+    for value in row {
+        bytes.extend_from_slice(&field_to_bytes(value));
     }
+    bytes
+}
+
+/// Uniform field elements; 64 random bytes per element keep the modular bias negligible.
+fn random_field_elements(count: usize) -> Vec<StarkField> {
+    let mut rng = rand::rng();
+    (0..count)
+        .map(|_| {
+            let mut bytes = [0u8; 64];
+            rng.fill(&mut bytes[..]);
+            StarkField::from_le_bytes_mod_order(&bytes)
+        })
+        .collect()
+}
+
+fn row_at(columns: &[Vec<StarkField>], index: usize) -> Vec<StarkField> {
+    columns.iter().map(|column| column[index]).collect()
 }
 
-fn debug_stark(msg: &str) {
-    if env::var("ZKP_DEBUG_SPOT").is_ok() {
-        eprintln!("{}", msg);
+pub(crate) fn column_value_seed(
+    trace_root: &[u8; 32],
+    column: usize,
+    claims: &[(usize, StarkField)],
+    hasher: &dyn QuantumHash,
+) -> [u8; 32] {
+    let mut data = Vec::with_capacity(80 + claims.len() * 40);
+    data.extend_from_slice(b"zkp-c-coloring/stark/column-values/v1");
+    data.extend_from_slice(trace_root);
+    data.extend_from_slice(&(column as u64).to_be_bytes());
+    for (row, value) in claims {
+        data.extend_from_slice(&(*row as u64).to_be_bytes());
+        data.extend_from_slice(&field_to_bytes(value));
     }
+    hasher.hash(&data)
+}
+
+pub(crate) fn claim_coefficients(
+    seed: &[u8; 32],
+    count: usize,
+    hasher: &dyn QuantumHash,
+) -> Vec<StarkField> {
+    (0..count)
+        .map(|claim| {
+            let mut data = Vec::with_capacity(40);
+            data.extend_from_slice(seed);
+            data.extend_from_slice(&(claim as u64).to_be_bytes());
+            field_from_digest(&hasher.hash(&data))
+        })
+        .collect()
 }
@@ -0,0 +1,196 @@
+use crate::crypto::hash::QuantumHash;
+use crate::stark::air::{column_degree_factor, trace_domains, Air, AirContext};
+use crate::stark::fri::{FriError, FriOptions};
+use crate::stark::params::{check_security, ParameterError};
+use crate::stark::prover::{
+    claim_coefficients, column_value_seed, ColumnValueProof, StarkParameters, StarkProof,
+};
+use crate::stark::StarkField;
+use ark_ff::{Field, Zero};
+use ark_poly::EvaluationDomain;
+use std::fmt;
+
+/// STARK check that rejected a proof. Query errors carry the position on the extended
+/// domain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StarkError {
+    /// The AIR and parameters do not describe a valid trace and extension domain.
+    InconsistentAir,
+    Parameters(ParameterError),
+    /// The proof is for a different public statement than the verifier's.
+    StatementMismatch,
+    TraceLengthMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    QueryCountMismatch,
+    MalformedClaims,
+    QueryPosition {
+        position: u64,
+    },
+    RowMerkleMismatch {
+        position: u64,
+    },
+    QueryInTraceDomain {
+        position: u64,
+    },
+    Fri(FriError),
+}
+
+impl fmt::Display for StarkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StarkError::InconsistentAir => write!(f, "AIR and parameters are inconsistent"),
+            StarkError::Parameters(err) => write!(f, "{}", err),
+            StarkError::StatementMismatch => {
+                write!(f, "proof is for a different public statement")
+            }
+            StarkError::TraceLengthMismatch { expected, actual } => write!(
+                f,
+                "trace length {} does not match the expected {}",
+                actual, expected
+            ),
+            StarkError::QueryCountMismatch => {
+                write!(f, "trace openings do not match the FRI queries")
+            }
+            StarkError::MalformedClaims => write!(f, "column claims lie outside the trace"),
+            StarkError::QueryPosition { position } => {
+                write!(
+                    f,
+                    "trace opening for query {} is at the wrong row",
+                    position
+                )
+            }
+            StarkError::RowMerkleMismatch { position } => {
+                write!(f, "trace row for query {} fails its Merkle check", position)
+            }
+            StarkError::QueryInTraceDomain { position } => {
+                write!(f, "query {} hits the trace domain", position)
+            }
+            StarkError::Fri(err) => write!(f, "FRI low-degree test rejected: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for StarkError {}
+
+impl From<ParameterError> for StarkError {
+    fn from(err: ParameterError) -> Self {
+        StarkError::Parameters(err)
+    }
+}
+
+impl From<FriError> for StarkError {
+    fn from(err: FriError) -> Self {
+        StarkError::Fri(err)
+    }
+}
+
+/// Verifies a [`StarkProof`] for `air`. The blowup factor and query count come from
+/// `params`, never from the proof.
+pub fn verify<A: Air>(
+    air: &A,
+    proof: &StarkProof,
+    params: &StarkParameters,
+    hasher: &dyn QuantumHash,
+) -> Result<(), StarkError> {
+    let context = AirContext::new(air, params).ok_or(StarkError::InconsistentAir)?;
+    check_security(params, context.lde_domain.size(), context.remainder_size)?;
+    if proof.trace_length != air.trace_length() as u64 {
+        return Err(StarkError::TraceLengthMismatch {
+            expected: air.trace_length() as u64,
+            actual: proof.trace_length,
+        });
+    }
+    if proof.queries.len() != proof.fri_proof.query_positions.len() {
+        return Err(StarkError::QueryCountMismatch);
+    }
+
+    let seed = context.channel_seed(&proof.trace_root, hasher);
+    let coefficients = context.composition_coefficients(&seed, hasher);
+    let lde_size = context.lde_domain.size() as u64;
+    let offset = context.next_row_offset() as u64;
+    let width = context.row_width;
+    let mut first_layer = Vec::with_capacity(proof.queries.len());
+
+    for (&position, query) in proof.fri_proof.query_positions.iter().zip(&proof.queries) {
+        if query.current.index != position || query.next.index != (position + offset) % lde_size {
+            return Err(StarkError::QueryPosition { position });
+        }
+        let current = query
+            .current
+            .verify(width, &proof.trace_root, hasher)
+            .ok_or(StarkError::RowMerkleMismatch { position })?;
+        let next = query
+            .next
+            .verify(width, &proof.trace_root, hasher)
+            .ok_or(StarkError::RowMerkleMismatch { position })?;
+        let x = context.lde_domain.element(position as usize);
+        let value = context
+            .composition_at(&coefficients, x, &current, &next)
+            .ok_or(StarkError::QueryInTraceDomain { position })?;
+        first_layer.push(value);
+    }
+
+    proof.fri_proof.verify(
+        context.lde_domain,
+        &FriOptions::new(params, context.remainder_size),
+        &AirContext::<A>::fri_seed(&seed, hasher),
+        &first_layer,
+        hasher,
+    )?;
+    Ok(())
+}
+
+/// Verifies that trace column `column` under `trace_root` takes `value` at `row` for
+/// every `(row, value)` claim.
+pub fn verify_column_values(
+    proof: &ColumnValueProof,
+    trace_root: &[u8; 32],
+    trace_length: usize,
+    column: usize,
+    claims: &[(usize, StarkField)],
+    params: &StarkParameters,
+    hasher: &dyn QuantumHash,
+) -> Result<(), StarkError> {
+    let (trace_domain, lde_domain) =
+        trace_domains(trace_length, params.blowup_factor).ok_or(StarkError::InconsistentAir)?;
+    let remainder_size = lde_domain.size() / trace_length / column_degree_factor(params);
+    check_security(params, lde_domain.size(), remainder_size)?;
+    if claims.iter().any(|&(row, _)| row >= trace_length) {
+        return Err(StarkError::MalformedClaims);
+    }
+    if proof.queries.len() != proof.fri_proof.query_positions.len() {
+        return Err(StarkError::QueryCountMismatch);
+    }
+
+    let seed = column_value_seed(trace_root, column, claims, hasher);
+    let coefficients = claim_coefficients(&seed, claims.len(), hasher);
+    let mut first_layer = Vec::with_capacity(proof.queries.len());
+    for (&position, opening) in proof.fri_proof.query_positions.iter().zip(&proof.queries) {
+        if opening.index != position || column >= opening.values.len() {
+            return Err(StarkError::QueryPosition { position });
+        }
+        let row = opening
+            .verify(opening.values.len(), trace_root, hasher)
+            .ok_or(StarkError::RowMerkleMismatch { position })?;
+        let x = lde_domain.element(position as usize);
+        let mut quotient = StarkField::zero();
+        for (&(claim_row, value), coefficient) in claims.iter().zip(&coefficients) {
+            let inverse = (x - trace_domain.element(claim_row))
+                .inverse()
+                .ok_or(StarkError::QueryInTraceDomain { position })?;
+            quotient += *coefficient * (row[column] - value) * inverse;
+        }
+        first_layer.push(quotient);
+    }
+
+    proof.fri_proof.verify(
+        lde_domain,
+        &FriOptions::new(params, remainder_size),
+        &seed,
+        &first_layer,
+        hasher,
+    )?;
+    Ok(())
+}
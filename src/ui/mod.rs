@@ -139,7 +139,8 @@ pub struct SpotCheckDisplay {
 pub struct EdgeHighlight {
     pub from: u32,
     pub to: u32,
-    pub color: Color,
+    /// `None` when the opening only revealed that the edge is not blank.
+    pub color: Option<Color>,
 }
 
 #[derive(Clone, Debug, Serialize, Default)]
@@ -266,8 +267,7 @@ impl Visualizer {
 
         self.terminal.draw(|frame| {
             let size = frame.size();
-            let triad_rows =
-                ((snapshot.triads.patterns.len() + TRIAD_COLUMNS - 1) / TRIAD_COLUMNS) as u16;
+            let triad_rows = snapshot.triads.patterns.len().div_ceil(TRIAD_COLUMNS) as u16;
             let triad_height = (triad_rows + 2).clamp(5, 18);
             let vertical = Layout::default()
                 .direction(Direction::Vertical)
@@ -463,9 +463,13 @@ impl Visualizer {
             if !focus.edges.is_empty() {
                 lines.push(Line::from("edges:"));
                 for edge in &focus.edges {
+                    let color = match edge.color {
+                        Some(color) => format!("{:?}", color),
+                        None => "hidden".to_string(),
+                    };
                     lines.push(Line::from(format!(
-                        "  {}→{} ({:?})",
-                        edge.from, edge.to, edge.color
+                        "  {}→{} ({})",
+                        edge.from, edge.to, color
                     )));
                 }
             }
@@ -540,7 +544,7 @@ impl Visualizer {
                     .map(|edge| (edge.from, edge.to))
                     .collect()
             })
-            .unwrap_or_else(HashSet::new);
+            .unwrap_or_default();
         let focus_nodes: HashSet<u32> = data
             .focus
             .as_ref()
@@ -553,7 +557,7 @@ impl Visualizer {
                 }
                 nodes
             })
-            .unwrap_or_else(HashSet::new);
+            .unwrap_or_default();
         let title = format!("Graph view ({} nodes shown)", layout.visualized);
         Canvas::default()
             .block(Block::default().title(title).borders(Borders::ALL))
@@ -673,9 +677,9 @@ impl WebVisualizer {
     pub fn set_commitments(&self, commitments: &Commitments) -> io::Result<()> {
         self.modify_data(|data| {
             data.commitments = Some(CommitmentSummary {
-                graph_root: hex::encode(&commitments.graph_root),
-                perm_root: hex::encode(&commitments.permutation_root),
-                blank_root: hex::encode(&commitments.blank_root),
+                graph_root: hex::encode(commitments.graph_root),
+                perm_root: hex::encode(commitments.permutation_root),
+                blank_root: hex::encode(commitments.blank_root),
             });
         })
     }
@@ -747,7 +751,7 @@ impl WebVisualizer {
         let mut guard = self
             .data
             .write()
-            .map_err(|_| io::Error::new(io::ErrorKind::Other, "web visualizer state poisoned"))?;
+            .map_err(|_| io::Error::other("web visualizer state poisoned"))?;
         mutator(&mut guard);
         Ok(())
     }
@@ -810,7 +814,7 @@ fn spawn_web_server(
 
     let address = ready_rx
         .recv()
-        .map_err(|_| io::Error::new(io::ErrorKind::Other, "web visualizer failed to start"))?;
+        .map_err(|_| io::Error::other("web visualizer failed to start"))?;
 
     Ok((handle, shutdown_tx, address))
 }
@@ -937,11 +941,10 @@ pub struct SelfLoopSegment {
 }
 
 impl GraphLayout {
-    const MAX_NODES: u32 = u32::MAX;
     const MAX_EDGES: usize = 96;
 
     fn build(graph: &Graph) -> Self {
-        let visualized = graph.n.min(Self::MAX_NODES).max(1);
+        let visualized = graph.n.max(1);
         let mut nodes = Vec::with_capacity(visualized as usize);
         for idx in 0..visualized {
             let angle = 2.0 * PI * (idx as f64) / (visualized as f64);
@@ -1016,7 +1019,7 @@ fn short_hash(hex_string: &str, label: &str) -> String {
 fn pattern_rows_from_key(key: &[u8; 9]) -> (String, [String; 3]) {
     let mut rows = [String::new(), String::new(), String::new()];
     let mut compact = Vec::with_capacity(3);
-    for row in 0..3 {
+    for (row, slot) in rows.iter_mut().enumerate() {
         let mut row_pretty = String::new();
         let mut row_compact = String::new();
         for col in 0..3 {
@@ -1029,7 +1032,7 @@ fn pattern_rows_from_key(key: &[u8; 9]) -> (String, [String; 3]) {
             row_pretty.push(symbol);
             row_compact.push(symbol);
         }
-        rows[row] = row_pretty;
+        *slot = row_pretty;
         compact.push(row_compact);
     }
     (compact.join("|"), rows)
@@ -1099,7 +1102,7 @@ pub fn focus_from_spot_response(
             edges.push(EdgeHighlight {
                 from: edge.from,
                 to: edge.to,
-                color: edge.color,
+                color: Some(edge.color),
             });
         }
     }
@@ -1121,7 +1124,7 @@ pub fn focus_from_blank_response(
         edges.push(EdgeHighlight {
             from: opening.from,
             to: opening.to,
-            color: opening.color,
+            color: opening.is_blank.then_some(Color::Blank),
         });
     }
     ChallengeFocus {
@@ -1146,7 +1149,11 @@ pub fn spot_checks_from_response(
             let mut edges: Vec<EdgeHighlight> = spot
                 .edges
                 .iter()
-                .map(|(&(from, to), &color)| EdgeHighlight { from, to, color })
+                .map(|(&(from, to), &color)| EdgeHighlight {
+                    from,
+                    to,
+                    color: Some(color),
+                })
                 .collect();
             edges.sort_by_key(|edge| (edge.from, edge.to));
             SpotCheckDisplay {
@@ -1176,16 +1183,24 @@ fn steps_from_merkle_proof(proof: &crate::crypto::merkle::MerkleProof) -> Vec<Me
         direction: "leaf".to_string(),
         hash: hex::encode(proof.leaf_hash),
     });
-    for (level, (sibling, is_right)) in proof.path.iter().enumerate() {
+    let arity = proof.arity.children();
+    let mut index = proof.leaf_index as usize;
+    for (level, siblings) in proof.path.chunks(arity - 1).enumerate() {
+        let position = index % arity;
         steps.push(MerkleStep {
             level: level + 1,
-            direction: if *is_right {
-                "sibling-right".to_string()
-            } else {
-                "sibling-left".to_string()
+            direction: match (arity, position) {
+                (2, 0) => "sibling-right".to_string(),
+                (2, _) => "sibling-left".to_string(),
+                _ => format!("child {} of {}", position, arity),
             },
-            hash: hex::encode(sibling),
+            hash: siblings
+                .iter()
+                .map(hex::encode)
+                .collect::<Vec<_>>()
+                .join(" "),
         });
+        index /= arity;
     }
     steps
 }
@@ -1,7 +1,10 @@
+use crate::crypto::hash::{HashBackend, QuantumHash};
 use crate::graph::{ColorationSet, Graph};
 use crate::protocol::messages::{
-    BlankChallengeResponse, Challenge, Commitments, SpotChallengeResponse,
+    BlankChallengeResponse, Challenge, Commitments, PermutationChallengeResponse, SessionNonce,
+    SpotChallengeResponse,
 };
+use crate::stark::prover::StarkParameters;
 use crate::utils::random_graph::InstanceParameters;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -35,41 +38,224 @@ impl GraphInstance {
             metadata: Some(metadata),
         }
     }
+
+    /// The public half of the instance, committed to the crate's default hash and STARK
+    /// parameters.
+    pub fn statement(&self) -> PublicStatement {
+        PublicStatement::new(
+            self.coloration.clone(),
+            StarkParameters::default().security_level,
+        )
+    }
+
+    pub fn witness(&self) -> Witness {
+        Witness {
+            graph: self.graph.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+const STATEMENT_MAGIC: &[u8; 4] = b"ZKPS";
+const WITNESS_MAGIC: &[u8; 4] = b"ZKPW";
+
+/// What the verifier knows about an instance. The committed graph is complete (every
+/// ordered pair of the `n` nodes carries a color, possibly blank), so there is no public
+/// edge structure beyond `n`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicStatement {
+    pub n: u32,
+    pub blank_limit: u32,
+    pub coloration: ColorationSet,
+    /// [`QuantumHash::id`] of the hash the prover must commit with.
+    pub hash_id: String,
+    /// Security level the blank-count STARK parameters are derived from.
+    pub security_level: u32,
+}
+
+impl PublicStatement {
+    pub fn new(coloration: ColorationSet, security_level: u32) -> Self {
+        PublicStatement {
+            n: coloration.graph_size(),
+            blank_limit: coloration.blank_limit(),
+            coloration,
+            hash_id: HashBackend::default().id().to_string(),
+            security_level,
+        }
+    }
+
+    pub fn with_hash(mut self, hash: HashBackend) -> Self {
+        self.hash_id = hash.id().to_string();
+        self
+    }
+
+    /// The backend named by `hash_id`.
+    pub fn hash(&self) -> io::Result<HashBackend> {
+        self.hash_id
+            .parse()
+            .map_err(|err: String| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// STARK parameters blank-count proofs for this statement must use.
+    pub fn stark_parameters(&self) -> Result<StarkParameters, crate::stark::ParameterError> {
+        StarkParameters::for_security_level(self.security_level)
+    }
+
+    fn check(&self) -> io::Result<()> {
+        self.hash()?;
+        if self.n != self.coloration.graph_size()
+            || self.blank_limit != self.coloration.blank_limit()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "statement size or blank limit disagrees with its coloration set",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The prover's secret: the colored graph behind a [`PublicStatement`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Witness {
+    pub graph: Graph,
+    pub metadata: Option<InstanceParameters>,
+}
+
+impl Witness {
+    /// Rejoins the witness with its statement for the prover.
+    pub fn into_instance(self, statement: &PublicStatement) -> io::Result<GraphInstance> {
+        if self.graph.n != statement.n {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "witness has {} nodes, statement expects {}",
+                    self.graph.n, statement.n
+                ),
+            ));
+        }
+        Ok(GraphInstance {
+            graph: self.graph,
+            coloration: statement.coloration.clone(),
+            metadata: self.metadata,
+        })
+    }
 }
 
 pub fn save_graph_instance<P: AsRef<Path>>(path: P, instance: &GraphInstance) -> io::Result<()> {
     let bytes = bincode::serialize(instance)
-        .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("serialize graph: {err}")))?;
+        .map_err(|err| io::Error::other(format!("serialize graph: {err}")))?;
     fs::write(path, bytes)
 }
 
 pub fn load_graph_instance<P: AsRef<Path>>(path: P) -> io::Result<GraphInstance> {
     let bytes = fs::read(path)?;
     bincode::deserialize(&bytes)
-        .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("deserialize graph: {err}")))
+        .map_err(|err| io::Error::other(format!("deserialize graph: {err}")))
+}
+
+pub fn save_public_statement<P: AsRef<Path>>(
+    path: P,
+    statement: &PublicStatement,
+) -> io::Result<()> {
+    save_tagged(path, STATEMENT_MAGIC, statement, "statement")
+}
+
+pub fn load_public_statement<P: AsRef<Path>>(path: P) -> io::Result<PublicStatement> {
+    let statement: PublicStatement = load_tagged(path, STATEMENT_MAGIC, "statement")?;
+    statement.check()?;
+    Ok(statement)
+}
+
+pub fn save_witness<P: AsRef<Path>>(path: P, witness: &Witness) -> io::Result<()> {
+    save_tagged(path, WITNESS_MAGIC, witness, "witness")
 }
 
+pub fn load_witness<P: AsRef<Path>>(path: P) -> io::Result<Witness> {
+    load_tagged(path, WITNESS_MAGIC, "witness")
+}
+
+/// Writes `magic` followed by the bincode encoding of `value`, so statement and witness
+/// files cannot be mistaken for one another or for a `GraphInstance`.
+fn save_tagged<P: AsRef<Path>, T: Serialize>(
+    path: P,
+    magic: &[u8; 4],
+    value: &T,
+    what: &str,
+) -> io::Result<()> {
+    let mut bytes = magic.to_vec();
+    bincode::serialize_into(&mut bytes, value)
+        .map_err(|err| io::Error::other(format!("serialize {what}: {err}")))?;
+    fs::write(path, bytes)
+}
+
+fn load_tagged<P: AsRef<Path>, T: for<'de> Deserialize<'de>>(
+    path: P,
+    magic: &[u8; 4],
+    what: &str,
+) -> io::Result<T> {
+    let bytes = fs::read(path)?;
+    let body = bytes
+        .strip_prefix(magic.as_slice())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("not a {what} file")))?;
+    bincode::deserialize(body).map_err(|err| io::Error::other(format!("deserialize {what}: {err}")))
+}
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TranscriptResponse {
     Spot(SpotChallengeResponse),
     Blank(BlankChallengeResponse),
+    Permutation(PermutationChallengeResponse),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptRound {
+    /// Fresh commitments the prover issued before this round, if it recommitted.
+    pub recommitment: Option<Commitments>,
     pub challenge: Challenge,
     pub response: TranscriptResponse,
 }
 
+/// How the challenges stored in a transcript were produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TranscriptMode {
+    /// Challenges were issued by a live verifier and are taken as recorded.
+    #[default]
+    Interactive,
+    /// Challenges were derived from a running hash of the transcript
+    /// ([`crate::protocol::fiat_shamir::FiatShamirTranscript`]) and must be re-derived on
+    /// verification.
+    FiatShamir,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProofTranscript {
+    pub mode: TranscriptMode,
+    /// Session the verifier opened; transcripts from before nonces existed carry zeros.
+    #[serde(default)]
+    pub session: SessionNonce,
+    /// Hash backend of every commitment and Fiat–Shamir seed in the transcript.
+    #[serde(default)]
+    pub hash: HashBackend,
     pub commitments: Commitments,
     pub rounds: Vec<TranscriptRound>,
 }
 
+impl ProofTranscript {
+    /// Number of distinct commitments (initial plus recommitments) in the transcript.
+    pub fn commitment_count(&self) -> usize {
+        1 + self
+            .rounds
+            .iter()
+            .filter(|round| round.recommitment.is_some())
+            .count()
+    }
+}
+
 pub fn save_proof<P: AsRef<Path>>(path: P, transcript: &ProofTranscript) -> io::Result<()> {
     let bytes = bincode::serialize(transcript)
-        .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("serialize proof: {err}")))?;
+        .map_err(|err| io::Error::other(format!("serialize proof: {err}")))?;
     let mut file = fs::File::create(path)?;
     file.write_all(&bytes)
 }
@@ -77,5 +263,5 @@ pub fn save_proof<P: AsRef<Path>>(path: P, transcript: &ProofTranscript) -> io::
 pub fn load_proof<P: AsRef<Path>>(path: P) -> io::Result<ProofTranscript> {
     let bytes = fs::read(path)?;
     bincode::deserialize(&bytes)
-        .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("deserialize proof: {err}")))
+        .map_err(|err| io::Error::other(format!("deserialize proof: {err}")))
 }
@@ -1,7 +1,37 @@
-use zkp_c_coloring::protocol::messages::Challenge;
+use ark_ff::{batch_inversion, FftField};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, Stdio};
+use zkp_c_coloring::crypto::hash::{default_quantum_hash, random_salts, HashBackend, QuantumHash};
+use zkp_c_coloring::crypto::merkle::{
+    leaf_digest, ChunkedMerkleTree, GraphMerkleTree, MerkleArity, MerkleDomain, MerkleError,
+    MerkleLevel, MerkleTree,
+};
+use zkp_c_coloring::crypto::merkle_storage::MerkleStorage;
+use zkp_c_coloring::crypto::polynomial::BlankPolynomial;
+use zkp_c_coloring::crypto::proof_cache::ProofCache;
+use zkp_c_coloring::graph::Color;
+use zkp_c_coloring::protocol::error::{ChallengeError, VerificationError};
+use zkp_c_coloring::protocol::fiat_shamir::FiatShamirTranscript;
+use zkp_c_coloring::protocol::messages::{BlankChallenge, Challenge, SessionNonce, SpotChallenge};
 use zkp_c_coloring::protocol::prover::{ProverConfig, ProverState};
+use zkp_c_coloring::protocol::soundness::spot_pattern_count;
 use zkp_c_coloring::protocol::verifier::{Verifier, VerifierConfig};
+use zkp_c_coloring::stark::fri::{field_from_bytes, prove_fri, FriOptions};
+use zkp_c_coloring::stark::prover::{
+    check_air_security, generate_blank_count_proof, prove, prove_committed, StarkParameters,
+    TraceCommitment, TraceRowOpening,
+};
+use zkp_c_coloring::stark::verifier::{verify, verify_column_values};
+use zkp_c_coloring::stark::{
+    Air, BlankCountConstraints, BlankCountProof, BlankProofError, BoundaryConstraint,
+    ConstraintViolation, FriError, ParameterEngine, ParameterError, StarkError, StarkField,
+};
 use zkp_c_coloring::utils::random_graph::generate_hard_instance;
+use zkp_c_coloring::utils::serialization::{
+    load_proof, load_public_statement, load_witness, save_graph_instance, save_public_statement,
+    save_witness, GraphInstance,
+};
 
 #[test]
 fn graph_generator_tracks_blank_edges() {
@@ -25,6 +55,7 @@ fn protocol_round_trip_accepts_transcript() {
             spots_per_round: 3,
             blank_checks_per_round: 2,
             spot_probability: 0.7,
+            permutation_probability: 0.0,
         },
     );
 
@@ -37,20 +68,2005 @@ fn protocol_round_trip_accepts_transcript() {
         match challenge {
             Challenge::Spot(ch) => {
                 let response = prover.respond_to_spot_challenge(&ch);
-                assert!(
-                    verifier.verify_spot_response(&ch, &response),
-                    "spot response rejected in round {}",
-                    round
-                );
+                verifier
+                    .verify_spot_response(&ch, &response)
+                    .unwrap_or_else(|err| panic!("{}", err.in_round(round)));
             }
             Challenge::Blank(ch) => {
                 let response = prover.respond_to_blank_challenge(&ch);
-                assert!(
-                    verifier.verify_blank_response(&ch, &response),
-                    "blank response rejected in round {}",
-                    round
+                verifier
+                    .verify_blank_response(&ch, &response)
+                    .unwrap_or_else(|err| panic!("{}", err.in_round(round)));
+            }
+            Challenge::Permutation(ch) => {
+                let response = prover.respond_to_permutation_challenge(&ch);
+                verifier
+                    .verify_permutation_response(&ch, &response)
+                    .unwrap_or_else(|err| panic!("{}", err.in_round(round)));
+            }
+        }
+    }
+}
+
+#[test]
+fn every_hash_backend_runs_the_protocol_end_to_end() {
+    let (graph, coloration, _params) = generate_hard_instance(10);
+    let verifier_cfg = VerifierConfig {
+        rounds: 4,
+        spots_per_round: 2,
+        blank_checks_per_round: 2,
+        spot_probability: 0.5,
+        permutation_probability: 0.2,
+    };
+    let mut digests = Vec::new();
+    for hash in HashBackend::ALL {
+        let mut prover = ProverState::new(graph.clone(), coloration.clone());
+        let prover_cfg = ProverConfig {
+            hash,
+            rounds_per_commitment: Some(1),
+            ..ProverConfig::default()
+        };
+        assert_eq!(
+            verifier_cfg.check_schedule(prover_cfg.rounds_per_commitment),
+            Ok(())
+        );
+        let commitments = prover.commit(&prover_cfg);
+        assert_eq!(commitments.hash, hash);
+        assert_eq!(hash.id().parse::<HashBackend>(), Ok(hash));
+        digests.push(commitments.statement_digest);
+
+        let blake3 = Verifier::new(coloration.clone(), verifier_cfg.clone());
+        if hash != HashBackend::Blake3 {
+            assert_eq!(
+                blake3.check_statement(&commitments),
+                Err(VerificationError::HashMismatch {
+                    expected: HashBackend::Blake3,
+                    actual: hash,
+                })
+            );
+        }
+
+        let mut verifier = Verifier::new(coloration.clone(), verifier_cfg.clone()).with_hash(hash);
+        verifier.receive_commitments(commitments);
+        for round in 0..verifier_cfg.rounds {
+            if prover_cfg.recommits_before(round) {
+                verifier.receive_commitments(prover.commit(&prover_cfg));
+            }
+            let verified = match verifier.generate_challenge(round) {
+                Challenge::Spot(ch) => {
+                    verifier.verify_spot_response(&ch, &prover.respond_to_spot_challenge(&ch))
+                }
+                Challenge::Blank(ch) => {
+                    verifier.verify_blank_response(&ch, &prover.respond_to_blank_challenge(&ch))
+                }
+                Challenge::Permutation(ch) => verifier.verify_permutation_response(
+                    &ch,
+                    &prover.respond_to_permutation_challenge(&ch),
+                ),
+            };
+            verified.unwrap_or_else(|err| panic!("{}: {}", hash, err.in_round(round)));
+        }
+    }
+    digests.dedup();
+    assert_eq!(digests.len(), HashBackend::ALL.len());
+}
+
+#[test]
+fn commitments_do_not_depend_on_the_thread_count() {
+    let hasher = default_quantum_hash();
+    let nodes = 24;
+    let (graph, coloration, _params) = generate_hard_instance(nodes);
+    let edges = (nodes * nodes) as usize;
+    let (color_salts, blank_salts) = (random_salts(edges), random_salts(edges));
+    let permutation: Vec<u32> = (0..nodes).rev().collect();
+    let params = StarkParameters::default();
+    let constraints =
+        BlankCountConstraints::<StarkField>::at_most(nodes, coloration.blank_limit() as u64);
+
+    let commit_with = |threads| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("thread pool");
+        pool.install(|| {
+            let permuted = graph.apply_permutation(&permutation);
+            let graph_root =
+                GraphMerkleTree::from_graph(&permuted, &color_salts, &blank_salts, &hasher).root();
+            let bits: Vec<Vec<u8>> = permuted
+                .adjacency
+                .iter()
+                .flatten()
+                .map(|&color| vec![(color == Color::Blank) as u8])
+                .collect();
+            let blank_root = ChunkedMerkleTree::new(MerkleDomain::Blank, &bits, &hasher, 64).root();
+            let stark = generate_blank_count_proof(
+                &BlankPolynomial::new(bits.concat()),
+                &constraints,
+                &params,
+                &hasher,
+            )
+            .expect("parameters meet their security level");
+            (
+                bincode::serialize(&permuted).expect("graph serializes"),
+                graph_root,
+                blank_root,
+                bincode::serialize(&stark).expect("proof serializes"),
+            )
+        })
+    };
+    assert!(commit_with(1) == commit_with(4));
+
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let mut verifier = Verifier::new(coloration, VerifierConfig::default());
+    let config = ProverConfig {
+        threads: Some(2),
+        ..ProverConfig::default()
+    };
+    prover.commit(&config);
+    // The pool is started once and shared by later commits and every config asking for
+    // the same thread count.
+    let pool = config.thread_pool().expect("two worker threads");
+    assert_eq!(pool.current_num_threads(), 2);
+    let reused = ProverConfig {
+        threads: Some(2),
+        ..ProverConfig::default()
+    }
+    .thread_pool()
+    .expect("two worker threads");
+    assert!(std::sync::Arc::ptr_eq(&pool, &reused));
+    assert!(ProverConfig::default().thread_pool().is_none());
+    verifier.receive_commitments(prover.commit(&config));
+    for round in 0..4 {
+        let verified = match verifier.generate_challenge(round) {
+            Challenge::Spot(ch) => {
+                verifier.verify_spot_response(&ch, &prover.respond_to_spot_challenge(&ch))
+            }
+            Challenge::Blank(ch) => {
+                verifier.verify_blank_response(&ch, &prover.respond_to_blank_challenge(&ch))
+            }
+            Challenge::Permutation(ch) => verifier
+                .verify_permutation_response(&ch, &prover.respond_to_permutation_challenge(&ch)),
+        };
+        assert_eq!(verified, Ok(()));
+    }
+}
+
+#[test]
+fn one_prover_answers_challenges_from_many_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ProverState>();
+
+    let (graph, coloration, _params) = generate_hard_instance(12);
+    let mut prover = ProverState::new(graph.clone(), coloration.clone());
+    let mut verifier = Verifier::new(
+        coloration.clone(),
+        VerifierConfig {
+            spot_probability: 0.5,
+            permutation_probability: 0.0,
+            ..VerifierConfig::default()
+        },
+    );
+    verifier.receive_commitments(prover.commit(&ProverConfig::default()));
+    let challenges: Vec<Challenge> = (0..8)
+        .map(|round| verifier.generate_challenge(round))
+        .collect();
+    let answer = |prover: &ProverState, verifier: &Verifier| {
+        for challenge in &challenges {
+            let verified = match challenge {
+                Challenge::Spot(ch) => {
+                    verifier.verify_spot_response(ch, &prover.respond_to_spot_challenge(ch))
+                }
+                Challenge::Blank(ch) => {
+                    verifier.verify_blank_response(ch, &prover.respond_to_blank_challenge(ch))
+                }
+                Challenge::Permutation(_) => unreachable!("permutation probability is 0"),
+            };
+            assert_eq!(verified, Ok(()));
+        }
+    };
+
+    // One warm-up pass caches every opened edge; the threads then only hit the cache.
+    answer(&prover, &verifier);
+    let warm = prover.proof_cache_stats();
+    let lookups = warm.hits + warm.misses;
+    assert!(lookups > 0);
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| answer(&prover, &verifier));
+        }
+    });
+    let stats = prover.proof_cache_stats();
+    assert_eq!(stats.misses, warm.misses);
+    assert_eq!(stats.hits, warm.hits + 8 * lookups);
+    assert_eq!(stats.entries, warm.entries);
+
+    let mut uncached = ProverState::new(graph, coloration);
+    verifier.receive_commitments(uncached.commit(&ProverConfig {
+        proof_cache_capacity: Some(0),
+        ..ProverConfig::default()
+    }));
+    answer(&uncached, &verifier);
+    let first = uncached.proof_cache_stats();
+    answer(&uncached, &verifier);
+    let stats = uncached.proof_cache_stats();
+    assert_eq!((stats.hits, stats.entries), (0, 0));
+    assert!(first.misses > 0);
+    assert_eq!(stats.misses, 2 * first.misses);
+}
+
+#[test]
+fn proof_cache_capacity_bounds_every_shard_together() {
+    let hasher = default_quantum_hash();
+    let leaves: Vec<Vec<u8>> = (0u8..64).map(|i| vec![i; 3]).collect();
+    let tree = ChunkedMerkleTree::new(MerkleDomain::Blank, &leaves, &hasher, 4);
+    for capacity in [1, 3, 20] {
+        let cache = ProofCache::new(capacity);
+        for index in 0..leaves.len() {
+            cache.insert(index, tree.get_proof(index).expect("leaf in range"));
+            assert!(cache.stats().entries <= capacity);
+        }
+        assert_eq!(cache.stats().entries, capacity);
+        // The most recent proof replaced the oldest of its own shard.
+        assert!(cache.get(leaves.len() - 1).is_some());
+        cache.clear();
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
+
+#[test]
+fn salted_edge_opening_rejects_tampered_salt() {
+    let (graph, coloration, _params) = generate_hard_instance(12);
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let mut verifier = Verifier::new(
+        coloration,
+        VerifierConfig {
+            rounds: 1,
+            spots_per_round: 2,
+            blank_checks_per_round: 2,
+            spot_probability: 1.0,
+            permutation_probability: 0.0,
+        },
+    );
+
+    let commitments = prover.commit(&ProverConfig::default());
+    verifier.receive_commitments(commitments);
+
+    let challenge = match verifier.generate_challenge(0) {
+        Challenge::Spot(ch) => ch,
+        _ => panic!("spot probability 1.0 must yield a spot challenge"),
+    };
+    let mut response = prover.respond_to_spot_challenge(&challenge);
+    assert!(verifier.verify_spot_response(&challenge, &response).is_ok());
+
+    response.responses[0].edges[0].color_salt[0] ^= 0xFF;
+    assert!(matches!(
+        verifier.verify_spot_response(&challenge, &response),
+        Err(VerificationError::SpotEdgeOpening {
+            source: MerkleError::LeafHashMismatch,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn spot_response_must_open_all_nine_edges_once() {
+    let (graph, coloration, _params) = generate_hard_instance(12);
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let mut verifier = Verifier::new(
+        coloration,
+        VerifierConfig {
+            rounds: 1,
+            spots_per_round: 1,
+            blank_checks_per_round: 1,
+            spot_probability: 1.0,
+            permutation_probability: 0.0,
+        },
+    );
+    verifier.receive_commitments(prover.commit(&ProverConfig::default()));
+    let challenge = match verifier.generate_challenge(0) {
+        Challenge::Spot(ch) => ch,
+        _ => panic!("spot probability 1.0 must yield a spot challenge"),
+    };
+    let response = prover.respond_to_spot_challenge(&challenge);
+    assert_eq!(verifier.verify_spot_response(&challenge, &response), Ok(()));
+
+    // Dropped edges would otherwise count as blank in the pattern.
+    let mut missing = response.clone();
+    missing.responses[0].edges.pop();
+    assert_eq!(
+        verifier.verify_spot_response(&challenge, &missing),
+        Err(VerificationError::ResponseCountMismatch {
+            expected: 9,
+            actual: 8
+        })
+    );
+
+    // A valid opening repeated in place of another edge still hides that edge.
+    let mut repeated = response.clone();
+    repeated.responses[0].edges[8] = repeated.responses[0].edges[0].clone();
+    let [a, _, c] = challenge.spots[0];
+    assert_eq!(
+        verifier.verify_spot_response(&challenge, &repeated),
+        Err(VerificationError::SpotEdgeMismatch {
+            nodes: challenge.spots[0],
+            expected: (c, c),
+            actual: (a, a),
+        })
+    );
+}
+
+#[test]
+fn spot_rejection_reports_nodes_edge_and_merkle_level() {
+    let (graph, coloration, _params) = generate_hard_instance(12);
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let mut verifier = Verifier::new(
+        coloration,
+        VerifierConfig {
+            rounds: 1,
+            spots_per_round: 1,
+            blank_checks_per_round: 1,
+            spot_probability: 1.0,
+            permutation_probability: 0.0,
+        },
+    );
+    verifier.receive_commitments(prover.commit(&ProverConfig::default()));
+
+    let challenge = match verifier.generate_challenge(0) {
+        Challenge::Spot(ch) => ch,
+        _ => panic!("spot probability 1.0 must yield a spot challenge"),
+    };
+    let mut response = prover.respond_to_spot_challenge(&challenge);
+    let edge = &mut response.responses[0].edges[1];
+    let expected_edge = (edge.from, edge.to);
+    edge.proof.as_mut().expect("per-edge proof").leaf_proof.path[0][0] ^= 1;
+
+    let err = verifier
+        .verify_spot_response(&challenge, &response)
+        .expect_err("tampered leaf path must be rejected")
+        .in_round(3);
+    assert_eq!(err.round(), Some(3));
+    match &err {
+        VerificationError::Round { source, .. } => assert_eq!(
+            **source,
+            VerificationError::SpotEdgeOpening {
+                nodes: challenge.spots[0],
+                edge: expected_edge,
+                source: MerkleError::PathMismatch {
+                    level: MerkleLevel::Leaf
+                },
+            }
+        ),
+        other => panic!("unexpected error {other:?}"),
+    }
+    assert!(err.to_string().starts_with("round 3: spot"));
+}
+
+#[test]
+fn merkle_proofs_enforce_domain_and_tree_shape() {
+    let hasher = default_quantum_hash();
+    let leaves: Vec<Vec<u8>> = (0u8..5).map(|i| vec![i; 4]).collect();
+    let tree = MerkleTree::new(MerkleDomain::Permutation, &leaves, &hasher);
+    let root = tree.root();
+    let proof = tree.get_proof(4).expect("leaf 4");
+    assert_eq!(
+        proof.leaf_hash,
+        leaf_digest(MerkleDomain::Permutation, &leaves[4], &hasher)
+    );
+    assert!(proof.verify(MerkleDomain::Permutation, &root, &hasher));
+    assert!(!proof.verify(MerkleDomain::Graph, &root, &hasher));
+    assert_ne!(
+        root,
+        MerkleTree::new(MerkleDomain::Graph, &leaves, &hasher).root()
+    );
+
+    // The same leaves without the odd one out, or padded by hand, commit differently.
+    assert_ne!(
+        root,
+        MerkleTree::new(MerkleDomain::Permutation, &leaves[..4], &hasher).root()
+    );
+    let mut padded = leaves.clone();
+    padded.extend(std::iter::repeat_n(Vec::new(), 3));
+    assert_ne!(
+        root,
+        MerkleTree::new(MerkleDomain::Permutation, &padded, &hasher).root()
+    );
+
+    // Paths must have the depth and leaf count the root commits to, so an internal
+    // node cannot be opened as a leaf of a shorter path.
+    let mut lifted = tree.get_proof(0).expect("leaf 0");
+    let sibling = lifted.path.remove(0);
+    lifted.leaf_hash = hasher.hash(&[lifted.leaf_hash, sibling].concat());
+    assert!(!lifted.verify(MerkleDomain::Permutation, &root, &hasher));
+    let mut shrunk = tree.get_proof(1).expect("leaf 1");
+    shrunk.leaf_count = 1;
+    assert!(!shrunk.verify(MerkleDomain::Permutation, &root, &hasher));
+    // A leaf count past 2^63 has no padded size and is refused instead of overflowing.
+    shrunk.leaf_count = u64::MAX;
+    assert!(!shrunk.verify(MerkleDomain::Permutation, &root, &hasher));
+}
+
+#[test]
+fn multiproofs_share_siblings_and_open_exactly_their_leaves() {
+    let hasher = default_quantum_hash();
+    let leaves: Vec<Vec<u8>> = (0u8..20).map(|i| vec![i; 3]).collect();
+    let tree = ChunkedMerkleTree::new(MerkleDomain::Blank, &leaves, &hasher, 4);
+    let root = tree.root();
+    let opened = |indices: &[u64]| -> Vec<(u64, [u8; 32])> {
+        indices
+            .iter()
+            .map(|&i| {
+                (
+                    i,
+                    leaf_digest(MerkleDomain::Blank, &leaves[i as usize], &hasher),
+                )
+            })
+            .collect()
+    };
+
+    let multiproof = tree
+        .get_multiproof(&[17, 1, 3, 1, 2])
+        .expect("leaves in range");
+    let single_paths: usize = [1, 2, 3, 17]
+        .iter()
+        .map(|&i| {
+            let proof = tree.get_proof(i).expect("leaf in range");
+            proof.leaf_proof.path.len() + proof.chunk_proof.path.len()
+        })
+        .sum();
+    assert!(multiproof.hash_count() < single_paths);
+    assert_eq!(
+        multiproof.verify(
+            MerkleDomain::Blank,
+            &root,
+            20,
+            &opened(&[1, 2, 3, 17]),
+            &hasher
+        ),
+        Ok(())
+    );
+
+    // Dropping, moving or relabelling a leaf, or misstating the tree, fails.
+    assert!(multiproof
+        .verify(
+            MerkleDomain::Blank,
+            &root,
+            20,
+            &opened(&[1, 2, 17]),
+            &hasher
+        )
+        .is_err());
+    assert!(multiproof
+        .verify(
+            MerkleDomain::Blank,
+            &root,
+            20,
+            &opened(&[1, 2, 3, 16]),
+            &hasher
+        )
+        .is_err());
+    let mut swapped = opened(&[1, 2, 3, 17]);
+    swapped[0].1 = swapped[1].1;
+    assert!(multiproof
+        .verify(MerkleDomain::Blank, &root, 20, &swapped, &hasher)
+        .is_err());
+    assert!(multiproof
+        .verify(
+            MerkleDomain::Graph,
+            &root,
+            20,
+            &opened(&[1, 2, 3, 17]),
+            &hasher
+        )
+        .is_err());
+    assert!(multiproof
+        .verify(
+            MerkleDomain::Blank,
+            &root,
+            24,
+            &opened(&[1, 2, 3, 17]),
+            &hasher
+        )
+        .is_err());
+    let mut oversized = multiproof.top_proof.clone();
+    oversized.leaf_count = u64::MAX;
+    let chunk_roots = vec![[0; 32]; oversized.leaf_indices.len()];
+    assert!(!oversized.verify(MerkleDomain::Blank, &root, &chunk_roots, &hasher));
+    assert!(tree.get_multiproof(&[]).is_none());
+    assert!(tree.get_multiproof(&[20]).is_none());
+}
+
+#[test]
+fn merkle_trees_of_every_arity_open_single_and_batched_leaves() {
+    let hasher = default_quantum_hash();
+    let leaves: Vec<Vec<u8>> = (0u8..37).map(|i| vec![i; 2]).collect();
+    let opened = |indices: &[u64]| -> Vec<(u64, [u8; 32])> {
+        indices
+            .iter()
+            .map(|&i| {
+                (
+                    i,
+                    leaf_digest(MerkleDomain::Blank, &leaves[i as usize], &hasher),
+                )
+            })
+            .collect()
+    };
+
+    let mut roots = Vec::new();
+    for arity in MerkleArity::ALL {
+        assert_eq!(arity.to_string().parse::<MerkleArity>(), Ok(arity));
+        let tree = ChunkedMerkleTree::with_arity(MerkleDomain::Blank, &leaves, &hasher, 9, arity);
+        let root = tree.root();
+        roots.push(root);
+        for index in [0, 8, 9, 20, 36] {
+            let proof = tree.get_proof(index).expect("leaf in range");
+            assert_eq!(proof.leaf_proof.path.len() % (arity.children() - 1), 0);
+            assert_eq!(
+                proof.verify(MerkleDomain::Blank, &root, 37, index as u64, &hasher),
+                Ok(())
+            );
+        }
+        // A valid proof only opens its own position of a tree of the committed size.
+        let proof = tree.get_proof(20).expect("leaf in range");
+        for (leaf_count, index, level) in [
+            (37, 21, MerkleLevel::Leaf),
+            (37, 29, MerkleLevel::Chunk),
+            (36, 20, MerkleLevel::Chunk),
+            (46, 20, MerkleLevel::Chunk),
+        ] {
+            assert_eq!(
+                proof.verify(MerkleDomain::Blank, &root, leaf_count, index, &hasher),
+                Err(MerkleError::PathMismatch { level })
+            );
+        }
+        let mut resized = proof.clone();
+        resized.chunk_size = 10;
+        assert!(resized
+            .verify(MerkleDomain::Blank, &root, 37, 20, &hasher)
+            .is_err());
+        let multiproof = tree
+            .get_multiproof(&[36, 0, 1, 20, 8])
+            .expect("leaves in range");
+        assert_eq!(
+            multiproof.verify(
+                MerkleDomain::Blank,
+                &root,
+                37,
+                &opened(&[0, 1, 8, 20, 36]),
+                &hasher
+            ),
+            Ok(())
+        );
+        assert!(multiproof
+            .verify(
+                MerkleDomain::Blank,
+                &root,
+                37,
+                &opened(&[0, 2, 8, 20, 36]),
+                &hasher
+            )
+            .is_err());
+
+        // The root commits to the arity, so a proof cannot be reread under another one.
+        let mut relabelled = tree.get_proof(20).expect("leaf in range");
+        relabelled.leaf_proof.arity = if arity == MerkleArity::Two {
+            MerkleArity::Four
+        } else {
+            MerkleArity::Two
+        };
+        assert!(relabelled
+            .verify(MerkleDomain::Blank, &root, 37, 20, &hasher)
+            .is_err());
+    }
+    roots.dedup();
+    assert_eq!(roots.len(), MerkleArity::ALL.len());
+    assert!("3".parse::<MerkleArity>().is_err());
+}
+
+#[test]
+fn updated_merkle_leaves_match_a_rebuilt_tree() {
+    let hasher = default_quantum_hash();
+    let leaves: Vec<Vec<u8>> = (0u8..37).map(|i| vec![i; 2]).collect();
+    let mut edited = leaves.clone();
+    let edits = [
+        (0, vec![0xaa]),
+        (20, vec![0xbb; 3]),
+        (36, vec![]),
+        (20, vec![0xcc]),
+    ];
+    for (index, data) in &edits {
+        edited[*index] = data.clone();
+    }
+    let dir = std::env::temp_dir().join(format!("zkp-merkle-updates-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for arity in MerkleArity::ALL {
+        let mut flat = MerkleTree::with_arity(MerkleDomain::Permutation, &leaves, &hasher, arity);
+        let mut chunked =
+            ChunkedMerkleTree::with_arity(MerkleDomain::Blank, &leaves, &hasher, 9, arity);
+        let mut disk = ChunkedMerkleTree::from_fn(
+            MerkleDomain::Blank,
+            leaves.len(),
+            |index| &leaves[index],
+            &hasher,
+            9,
+            arity,
+            &MerkleStorage::Disk(dir.clone()),
+        )
+        .expect("storage is writable");
+        let snapshot = chunked.clone();
+        let stale = chunked.get_proof(20).expect("leaf in range");
+
+        for (index, data) in &edits {
+            flat.update_leaf(*index, data, &hasher)
+                .expect("leaf in range");
+            chunked
+                .update_leaf(*index, data, &hasher)
+                .expect("leaf in range");
+            disk.update_leaf(*index, data, &hasher)
+                .expect("leaf in range");
+        }
+        let rebuilt =
+            ChunkedMerkleTree::with_arity(MerkleDomain::Blank, &edited, &hasher, 9, arity);
+        assert_eq!(
+            flat.root(),
+            MerkleTree::with_arity(MerkleDomain::Permutation, &edited, &hasher, arity).root()
+        );
+        assert_eq!(chunked.root(), rebuilt.root());
+        assert_eq!(disk.root(), rebuilt.root());
+        // Clones share levels until one is updated; the other keeps its commitment.
+        assert_eq!(snapshot.root(), snapshot.clone().root());
+        assert_ne!(snapshot.root(), chunked.root());
+        assert_eq!(
+            snapshot.get_proof(20).unwrap().verify(
+                MerkleDomain::Blank,
+                &snapshot.root(),
+                37,
+                20,
+                &hasher
+            ),
+            Ok(())
+        );
+
+        // Proofs cached before the update are dropped rather than served stale.
+        assert!(stale
+            .verify(MerkleDomain::Blank, &chunked.root(), 37, 20, &hasher)
+            .is_err());
+        for tree in [&chunked, &disk] {
+            for index in [0, 1, 20, 36] {
+                let proof = tree.get_proof(index).expect("leaf in range");
+                assert_eq!(
+                    proof.verify(MerkleDomain::Blank, &tree.root(), 37, index as u64, &hasher),
+                    Ok(())
                 );
             }
         }
+        let proof = flat.get_proof(36).expect("leaf in range");
+        assert!(proof.verify(MerkleDomain::Permutation, &flat.root(), &hasher));
+
+        assert!(flat.update_leaf(37, b"", &hasher).is_none());
+        assert!(chunked.update_leaf(37, b"", &hasher).is_none());
+    }
+    std::fs::remove_dir(&dir).expect("no level files are left behind");
+}
+
+#[test]
+fn recommit_with_edits_updates_commitments_in_place() {
+    let nodes = 12;
+    let (graph, coloration, _params) = generate_hard_instance(nodes);
+    let mut prover = ProverState::new(graph.clone(), coloration.clone());
+    let first = prover.commit(&ProverConfig {
+        batch_openings: true,
+        ..ProverConfig::default()
+    });
+    let edges = (0..nodes).flat_map(|from| (0..nodes).map(move |to| (from, to)));
+    let colored = edges
+        .clone()
+        .find(|&(from, to)| graph.get_edge(from, to) != Color::Blank)
+        .expect("graph has a colored edge");
+    let blank = edges
+        .clone()
+        .find(|&(from, to)| graph.get_edge(from, to) == Color::Blank)
+        .expect("graph has a blank edge");
+    let recolor = match graph.get_edge(colored.0, colored.1) {
+        Color::Red => Color::Green,
+        _ => Color::Red,
+    };
+
+    // Recoloring keeps every blank bit, so the trace and its proof are reused.
+    let recolored = prover
+        .recommit_with_edits(&[(colored.0, colored.1, recolor)])
+        .expect("recoloring keeps the blank budget");
+    assert_ne!(recolored.graph_root, first.graph_root);
+    assert_ne!(
+        recolored.blank_root, first.blank_root,
+        "edited edges get fresh salts"
+    );
+    assert_eq!(recolored.blank_trace_root, first.blank_trace_root);
+    assert_eq!(recolored.permutation_root, first.permutation_root);
+    assert_eq!(
+        prover.original_graph.get_edge(colored.0, colored.1),
+        recolor
+    );
+
+    let filled = prover
+        .recommit_with_edits(&[(blank.0, blank.1, Color::Yellow)])
+        .expect("filling a blank keeps the blank budget");
+    assert_ne!(filled.blank_trace_root, recolored.blank_trace_root);
+    assert_eq!(filled.permutation_root, first.permutation_root);
+
+    // Undoing both edits restores a valid instance under the same permutation.
+    let restored = prover
+        .recommit_with_edits(&[
+            (colored.0, colored.1, graph.get_edge(colored.0, colored.1)),
+            (blank.0, blank.1, Color::Blank),
+        ])
+        .expect("restoring the witness keeps the blank budget");
+    assert_eq!(prover.original_graph.adjacency, graph.adjacency);
+
+    // The generated witness uses its whole budget, so one more blank is refused before
+    // anything is applied.
+    assert_eq!(
+        prover
+            .recommit_with_edits(&[(colored.0, colored.1, Color::Blank)])
+            .err(),
+        Some(BlankProofError::Unsatisfied(
+            ConstraintViolation::BudgetExceeded {
+                limit: graph.blank_count() as u64,
+                actual: graph.blank_count() as u64 + 1,
+            }
+        ))
+    );
+    assert_eq!(prover.original_graph.adjacency, graph.adjacency);
+    let mut over_budget = graph.clone();
+    over_budget.overwrite_edge(colored.0, colored.1, Color::Blank);
+    let err = ProverState::new(over_budget, coloration.clone())
+        .try_commit(&ProverConfig::default())
+        .expect_err("a witness over its blank budget is not committed");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert_ne!(restored.graph_root, first.graph_root);
+
+    let mut verifier = Verifier::new(coloration, VerifierConfig::default());
+    verifier.receive_commitments(restored);
+    for round in 0..8 {
+        let verified = match verifier.generate_challenge(round) {
+            Challenge::Spot(ch) => {
+                verifier.verify_spot_response(&ch, &prover.respond_to_spot_challenge(&ch))
+            }
+            Challenge::Blank(ch) => {
+                verifier.verify_blank_response(&ch, &prover.respond_to_blank_challenge(&ch))
+            }
+            Challenge::Permutation(ch) => verifier
+                .verify_permutation_response(&ch, &prover.respond_to_permutation_challenge(&ch)),
+        };
+        verified.unwrap_or_else(|err| panic!("{}", err.in_round(round)));
+    }
+}
+
+#[test]
+fn row_per_chunk_graph_trees_keep_each_spot_row_in_one_chunk() {
+    let nodes = 12;
+    let (graph, coloration, _params) = generate_hard_instance(nodes);
+    let edges = (nodes * nodes) as usize;
+    let tree = GraphMerkleTree::with_layout(
+        &graph,
+        &random_salts(edges),
+        &random_salts(edges),
+        &default_quantum_hash(),
+        nodes as usize,
+        MerkleArity::Four,
+    );
+    for (from, to) in [(0, 0), (0, 11), (5, 3), (11, 11)] {
+        let proof = tree.get_edge_proof(from, to).expect("edge exists");
+        assert_eq!(proof.chunk_index, from as u64);
+        assert_eq!(proof.leaf_index_within_chunk, to as u64);
+    }
+    // A spot's nine edges sit in three rows, so its multiproof touches three chunks.
+    let spot = [
+        (2, 7),
+        (2, 9),
+        (7, 2),
+        (7, 9),
+        (9, 2),
+        (9, 7),
+        (2, 2),
+        (7, 7),
+        (9, 9),
+    ];
+    let multiproof = tree.get_edges_multiproof(&spot).expect("edges exist");
+    assert_eq!(multiproof.chunk_proofs.len(), 3);
+
+    for batch_openings in [false, true] {
+        let mut prover = ProverState::new(graph.clone(), coloration.clone());
+        let mut verifier = Verifier::new(coloration.clone(), VerifierConfig::default());
+        verifier.receive_commitments(prover.commit(&ProverConfig {
+            merkle_arity: MerkleArity::Eight,
+            row_per_chunk: true,
+            batch_openings,
+            ..ProverConfig::default()
+        }));
+        for round in 0..6 {
+            let verified = match verifier.generate_challenge(round) {
+                Challenge::Spot(ch) => {
+                    verifier.verify_spot_response(&ch, &prover.respond_to_spot_challenge(&ch))
+                }
+                Challenge::Blank(ch) => {
+                    verifier.verify_blank_response(&ch, &prover.respond_to_blank_challenge(&ch))
+                }
+                Challenge::Permutation(ch) => verifier.verify_permutation_response(
+                    &ch,
+                    &prover.respond_to_permutation_challenge(&ch),
+                ),
+            };
+            verified.unwrap_or_else(|err| panic!("{}", err.in_round(round)));
+        }
+    }
+}
+
+#[test]
+fn disk_backed_graph_trees_match_memory_and_clean_up() {
+    let nodes = 20;
+    let (graph, coloration, _params) = generate_hard_instance(nodes);
+    let edges = (nodes * nodes) as usize;
+    let (color_salts, blank_salts) = (random_salts(edges), random_salts(edges));
+    let hasher = default_quantum_hash();
+    let dir = std::env::temp_dir().join(format!("zkp-merkle-levels-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let files = || std::fs::read_dir(&dir).unwrap().count();
+
+    for (chunk_size, arity) in [(64, MerkleArity::Two), (nodes as usize, MerkleArity::Four)] {
+        let layout = |storage| {
+            GraphMerkleTree::with_storage(
+                &graph,
+                &color_salts,
+                &blank_salts,
+                &hasher,
+                chunk_size,
+                arity,
+                storage,
+            )
+            .expect("storage is writable")
+        };
+        let memory = layout(&MerkleStorage::Memory);
+        let disk = layout(&MerkleStorage::Disk(dir.clone()));
+        assert_eq!(files(), 1);
+        assert_eq!(memory.root(), disk.root());
+        for (from, to) in [(0, 0), (3, 17), (19, 19)] {
+            let proof = disk.get_edge_proof(from, to).expect("edge exists");
+            assert_eq!(
+                bincode::serialize(&proof).unwrap(),
+                bincode::serialize(&memory.get_edge_proof(from, to).unwrap()).unwrap()
+            );
+            assert_eq!(
+                disk.edge_index(from, to),
+                Some((from * nodes + to) as usize)
+            );
+        }
+        let spot = [(1, 2), (2, 1), (1, 1), (2, 2), (12, 1)];
+        assert_eq!(
+            bincode::serialize(&disk.get_edges_multiproof(&spot).unwrap()).unwrap(),
+            bincode::serialize(&memory.get_edges_multiproof(&spot).unwrap()).unwrap()
+        );
+        assert!(disk.get_edge_proof(nodes, 0).is_none());
+        assert!(disk.get_edges_multiproof(&[(0, 0), (0, nodes)]).is_none());
+        drop(disk);
+        assert_eq!(files(), 0, "dropping the tree removes its level file");
+    }
+
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let mut verifier = Verifier::new(coloration, VerifierConfig::default());
+    verifier.receive_commitments(prover.commit(&ProverConfig {
+        batch_openings: true,
+        merkle_storage: MerkleStorage::Disk(dir.clone()),
+        ..ProverConfig::default()
+    }));
+    assert_eq!(files(), 2, "graph and blank trees are disk-backed");
+    for round in 0..4 {
+        let verified = match verifier.generate_challenge(round) {
+            Challenge::Spot(ch) => {
+                verifier.verify_spot_response(&ch, &prover.respond_to_spot_challenge(&ch))
+            }
+            Challenge::Blank(ch) => {
+                verifier.verify_blank_response(&ch, &prover.respond_to_blank_challenge(&ch))
+            }
+            Challenge::Permutation(ch) => verifier
+                .verify_permutation_response(&ch, &prover.respond_to_permutation_challenge(&ch)),
+        };
+        verified.unwrap_or_else(|err| panic!("{}", err.in_round(round)));
+    }
+    drop(prover);
+    std::fs::remove_dir(&dir).expect("no level files are left behind");
+}
+
+#[test]
+fn batched_spot_and_blank_openings_verify_and_reject_tampering() {
+    let (graph, coloration, _params) = generate_hard_instance(12);
+    let mut prover = ProverState::new(graph, coloration.clone());
+    // One spot per round, so no edge is opened twice and tampering shows up in the proof.
+    let config = |spot_probability| VerifierConfig {
+        rounds: 1,
+        spots_per_round: 1,
+        blank_checks_per_round: 4,
+        spot_probability,
+        permutation_probability: 0.0,
+    };
+    let commitments = prover.commit(&ProverConfig {
+        batch_openings: true,
+        ..ProverConfig::default()
+    });
+
+    let mut verifier = Verifier::new(coloration.clone(), config(1.0));
+    verifier.receive_commitments(commitments.clone());
+    let challenge = match verifier.generate_challenge(0) {
+        Challenge::Spot(ch) => ch,
+        _ => panic!("spot probability 1.0 must yield a spot challenge"),
+    };
+    let mut response = prover.respond_to_spot_challenge(&challenge);
+    assert!(response
+        .responses
+        .iter()
+        .flat_map(|spot| &spot.edges)
+        .all(|edge| edge.proof.is_none()));
+    assert_eq!(verifier.verify_spot_response(&challenge, &response), Ok(()));
+    response.responses[0].edges[0].color_salt[0] ^= 0xFF;
+    assert!(matches!(
+        verifier.verify_spot_response(&challenge, &response),
+        Err(VerificationError::SpotMultiproof { .. })
+    ));
+    response.multiproof = None;
+    assert!(matches!(
+        verifier.verify_spot_response(&challenge, &response),
+        Err(VerificationError::SpotEdgeOpening {
+            source: MerkleError::MissingProof,
+            ..
+        })
+    ));
+
+    let mut verifier = Verifier::new(coloration, config(0.0));
+    verifier.receive_commitments(commitments);
+    let challenge = match verifier.generate_challenge(0) {
+        Challenge::Blank(ch) => ch,
+        _ => panic!("spot probability 0.0 must yield a blank challenge"),
+    };
+    let mut response = prover.respond_to_blank_challenge(&challenge);
+    assert_eq!(
+        verifier.verify_blank_response(&challenge, &response),
+        Ok(())
+    );
+    // The edge may also be bound by the trace queries; flip every opening of it so only
+    // the multiproof can catch the change.
+    let flipped = response.edges[0].edge_index;
+    for edge in response
+        .edges
+        .iter_mut()
+        .filter(|edge| edge.edge_index == flipped)
+    {
+        edge.is_blank = !edge.is_blank;
+    }
+    assert!(matches!(
+        verifier.verify_blank_response(&challenge, &response),
+        Err(VerificationError::BlankEdgeMultiproof { .. })
+    ));
+
+    // The verifier still accepts per-edge proofs from the same commitment.
+    prover.set_batch_openings(false);
+    let response = prover.respond_to_blank_challenge(&challenge);
+    assert!(response.multiproofs.is_none());
+    assert_eq!(
+        verifier.verify_blank_response(&challenge, &response),
+        Ok(())
+    );
+}
+
+#[test]
+fn blank_opening_rejects_flipped_blank_bit() {
+    let (graph, coloration, _params) = generate_hard_instance(12);
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let mut verifier = Verifier::new(
+        coloration,
+        VerifierConfig {
+            rounds: 1,
+            spots_per_round: 2,
+            blank_checks_per_round: 4,
+            spot_probability: 0.0,
+            permutation_probability: 0.0,
+        },
+    );
+
+    let commitments = prover.commit(&ProverConfig::default());
+    verifier.receive_commitments(commitments);
+
+    let challenge = match verifier.generate_challenge(0) {
+        Challenge::Blank(ch) => ch,
+        _ => panic!("spot probability 0.0 must yield a blank challenge"),
+    };
+    let mut response = prover.respond_to_blank_challenge(&challenge);
+    assert!(verifier
+        .verify_blank_response(&challenge, &response)
+        .is_ok());
+
+    // The response opens exactly the challenged edges.
+    assert_eq!(response.edges.len(), challenge.edge_indices.len());
+    let mut truncated = response.clone();
+    truncated.edges.pop();
+    assert!(matches!(
+        verifier.verify_blank_response(&challenge, &truncated),
+        Err(VerificationError::ResponseCountMismatch { .. })
+    ));
+
+    response.edges[0].is_blank = !response.edges[0].is_blank;
+    assert!(matches!(
+        verifier.verify_blank_response(&challenge, &response),
+        Err(VerificationError::BlankEdgeOpening {
+            source: MerkleError::LeafHashMismatch,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn per_round_recommitment_uses_fresh_roots() {
+    let rounds = 4;
+    let (graph, coloration, _params) = generate_hard_instance(12);
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let mut verifier = Verifier::new(
+        coloration,
+        VerifierConfig {
+            rounds,
+            spots_per_round: 2,
+            blank_checks_per_round: 2,
+            spot_probability: 0.5,
+            permutation_probability: 0.2,
+        },
+    );
+    let config = ProverConfig {
+        rounds_per_commitment: Some(1),
+        ..ProverConfig::default()
+    };
+
+    let mut roots = vec![{
+        let commitments = prover.commit(&config);
+        let root = commitments.graph_root;
+        verifier.receive_commitments(commitments);
+        root
+    }];
+
+    for round in 0..rounds {
+        if config.recommits_before(round) {
+            let commitments = prover.commit(&config);
+            roots.push(commitments.graph_root);
+            verifier.receive_commitments(commitments);
+        }
+        match verifier.generate_challenge(round) {
+            Challenge::Spot(ch) => {
+                let response = prover.respond_to_spot_challenge(&ch);
+                assert!(verifier.verify_spot_response(&ch, &response).is_ok());
+            }
+            Challenge::Blank(ch) => {
+                let response = prover.respond_to_blank_challenge(&ch);
+                assert!(verifier.verify_blank_response(&ch, &response).is_ok());
+            }
+            Challenge::Permutation(ch) => {
+                let response = prover.respond_to_permutation_challenge(&ch);
+                assert!(verifier.verify_permutation_response(&ch, &response).is_ok());
+            }
+        }
+    }
+
+    assert_eq!(roots.len(), rounds as usize);
+    for (idx, root) in roots.iter().enumerate() {
+        assert!(!roots[idx + 1..].contains(root), "graph root reused");
+    }
+}
+
+#[test]
+fn permutation_challenge_opens_committed_bijection() {
+    let (graph, coloration, _params) = generate_hard_instance(10);
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let mut verifier = Verifier::new(
+        coloration,
+        VerifierConfig {
+            rounds: 1,
+            spots_per_round: 1,
+            blank_checks_per_round: 1,
+            spot_probability: 0.0,
+            permutation_probability: 1.0,
+        },
+    );
+
+    let commitments = prover.commit(&ProverConfig::default());
+    verifier.receive_commitments(commitments);
+
+    let challenge = match verifier.generate_challenge(0) {
+        Challenge::Permutation(ch) => ch,
+        _ => panic!("permutation probability 1.0 must yield a permutation challenge"),
+    };
+    let response = prover.respond_to_permutation_challenge(&challenge);
+    assert!(verifier
+        .verify_permutation_response(&challenge, &response)
+        .is_ok());
+
+    let mut duplicated = response.clone();
+    duplicated.entries[1].value = duplicated.entries[0].value;
+    assert!(matches!(
+        verifier.verify_permutation_response(&challenge, &duplicated),
+        Err(VerificationError::PermutationValueInvalid { position: 1, .. })
+    ));
+
+    let mut truncated = response;
+    truncated.entries.pop();
+    assert!(matches!(
+        verifier.verify_permutation_response(&challenge, &truncated),
+        Err(VerificationError::ResponseCountMismatch { .. })
+    ));
+
+    // Opened labels would relabel every other opening under the same commitment, so a
+    // commitment answers either its permutation round or other rounds, never both.
+    let spot = Challenge::Spot(SpotChallenge {
+        spots: vec![[0, 1, 2]],
+        seed: [0; 32],
+    });
+    let permutation = Challenge::Permutation(challenge);
+    assert_eq!(
+        prover.check_challenge(&spot),
+        Err(ChallengeError::PermutationRevealed)
+    );
+    verifier.receive_commitments(prover.commit(&ProverConfig::default()));
+    assert_eq!(prover.check_challenge(&permutation), Ok(()));
+    if let Challenge::Spot(ch) = &spot {
+        let response = prover.respond_to_spot_challenge(ch);
+        assert_eq!(verifier.verify_spot_response(ch, &response), Ok(()));
+    }
+    assert_eq!(
+        prover.check_challenge(&permutation),
+        Err(ChallengeError::CommitmentInUse)
+    );
+
+    let config = VerifierConfig {
+        permutation_probability: 0.1,
+        ..VerifierConfig::default()
+    };
+    assert_eq!(config.check_schedule(Some(1)), Ok(()));
+    for rounds_per_commitment in [None, Some(2)] {
+        assert_eq!(
+            config.check_schedule(rounds_per_commitment),
+            Err(VerificationError::SharedPermutationCommitment {
+                rounds_per_commitment
+            })
+        );
+    }
+}
+
+#[test]
+fn prover_refuses_challenges_outside_the_graph() {
+    let (graph, coloration, _params) = generate_hard_instance(10);
+    let n = graph.n;
+    let mut prover = ProverState::new(graph, coloration);
+    let spot = |spots: Vec<[u32; 3]>| {
+        Challenge::Spot(SpotChallenge {
+            spots,
+            seed: [0; 32],
+        })
+    };
+    let blank = |edge_indices: Vec<u64>| {
+        Challenge::Blank(BlankChallenge {
+            edge_indices,
+            seed: [0; 32],
+        })
+    };
+    assert_eq!(
+        prover.check_challenge(&spot(vec![[0, 1, 2]])),
+        Err(ChallengeError::NotCommitted)
+    );
+    prover.commit(&ProverConfig::default());
+
+    assert_eq!(prover.check_challenge(&spot(vec![[0, 1, n - 1]])), Ok(()));
+    assert_eq!(
+        prover.check_challenge(&spot(vec![[0, 1, 2], [3, n, 4]])),
+        Err(ChallengeError::NodeOutOfRange { node: n, nodes: n })
+    );
+    assert_eq!(
+        prover.check_challenge(&spot(vec![[5, 2, 5]])),
+        Err(ChallengeError::RepeatedSpotNode { spot: [5, 2, 5] })
+    );
+    let edges = n as u64 * n as u64;
+    assert_eq!(prover.check_challenge(&blank(vec![0, edges - 1])), Ok(()));
+    assert_eq!(
+        prover.check_challenge(&blank(vec![0, u64::MAX])),
+        Err(ChallengeError::EdgeOutOfRange {
+            edge_index: u64::MAX,
+            edges
+        })
+    );
+    assert!(matches!(
+        prover.check_challenge(&blank(vec![0; (1 << 16) + 1])),
+        Err(ChallengeError::TooManyChecks { .. })
+    ));
+}
+
+#[test]
+fn fiat_shamir_challenges_are_bound_to_earlier_responses() {
+    let (graph, coloration, _params) = generate_hard_instance(12);
+    let hasher = default_quantum_hash();
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let verifier = Verifier::new(
+        coloration,
+        VerifierConfig {
+            rounds: 2,
+            spots_per_round: 2,
+            blank_checks_per_round: 2,
+            spot_probability: 1.0,
+            permutation_probability: 0.0,
+        },
+    );
+
+    let commitments = prover.commit(&ProverConfig::default());
+    let mut transcript = FiatShamirTranscript::new(verifier.session(), &commitments, &hasher);
+    let first = verifier.derive_challenge(&transcript.challenge_seed(0, &hasher));
+    assert_eq!(
+        first,
+        verifier.derive_challenge(&transcript.challenge_seed(0, &hasher)),
+        "derivation must be deterministic"
+    );
+
+    let response = match &first {
+        Challenge::Spot(ch) => prover.respond_to_spot_challenge(ch),
+        _ => panic!("spot probability 1.0 must yield a spot challenge"),
+    };
+    let mut tampered = transcript.clone();
+    transcript.absorb_response(&response, &hasher);
+
+    let mut altered = response.clone();
+    altered.responses[0].edges[0].color_salt[0] ^= 1;
+    tampered.absorb_response(&altered, &hasher);
+
+    assert_ne!(
+        verifier.derive_challenge(&transcript.challenge_seed(1, &hasher)),
+        verifier.derive_challenge(&tampered.challenge_seed(1, &hasher)),
+    );
+}
+
+#[test]
+fn challenges_are_bound_to_the_session_nonce() {
+    let (graph, coloration, _params) = generate_hard_instance(12);
+    let hasher = default_quantum_hash();
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let commitments = prover.commit(&ProverConfig::default());
+    let ours = SessionNonce::random(Some("session test".into()));
+    let other = SessionNonce {
+        context: None,
+        ..ours.clone()
+    };
+
+    let verifier =
+        Verifier::new(coloration.clone(), VerifierConfig::default()).with_session(ours.clone());
+    assert!(verifier.check_session(&ours).is_ok());
+    assert_eq!(
+        verifier.check_session(&other),
+        Err(VerificationError::SessionMismatch)
+    );
+    assert_eq!(
+        verifier.check_session(&SessionNonce::random(None)),
+        Err(VerificationError::SessionMismatch)
+    );
+
+    let seed = FiatShamirTranscript::new(&ours, &commitments, &hasher).challenge_seed(0, &hasher);
+    assert_eq!(
+        seed,
+        FiatShamirTranscript::new(&ours, &commitments, &hasher).challenge_seed(0, &hasher)
+    );
+    assert_ne!(
+        seed,
+        FiatShamirTranscript::new(&other, &commitments, &hasher).challenge_seed(0, &hasher),
+        "the context string must feed the challenge seed"
+    );
+
+    // Interactive challenges also depend on randomness each verifier keeps to itself, so
+    // the nonce and commitments alone do not let a prover predict them.
+    let interactive = || {
+        let mut verifier =
+            Verifier::new(coloration.clone(), VerifierConfig::default()).with_session(ours.clone());
+        verifier.receive_commitments(commitments.clone());
+        (0..4)
+            .map(|round| verifier.generate_challenge(round))
+            .collect::<Vec<_>>()
+    };
+    assert_ne!(interactive(), interactive());
+}
+
+#[test]
+fn commitments_are_bound_to_their_statement() {
+    let (graph, coloration, _params) = generate_hard_instance(8);
+    let (_other_graph, other_coloration, _other_params) = generate_hard_instance(8);
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let commitments = prover.commit(&ProverConfig::default());
+
+    let verifier = Verifier::new(coloration.clone(), VerifierConfig::default());
+    assert_eq!(commitments.statement_digest, verifier.statement_digest());
+    assert!(verifier.check_statement(&commitments).is_ok());
+
+    let mut impostor = Verifier::new(other_coloration, VerifierConfig::default());
+    assert_eq!(
+        impostor.check_statement(&commitments),
+        Err(VerificationError::StatementMismatch)
+    );
+    impostor.receive_commitments(commitments.clone());
+    let challenge = SpotChallenge {
+        spots: vec![[0, 1, 2]],
+        seed: [0; 32],
+    };
+    assert_eq!(
+        impostor.verify_spot_response(&challenge, &prover.respond_to_spot_challenge(&challenge)),
+        Err(VerificationError::StatementMismatch)
+    );
+
+    let stronger = Verifier::new(coloration, VerifierConfig::default()).with_stark_parameters(
+        StarkParameters::for_security_level(100).expect("100-bit parameters"),
+    );
+    assert_eq!(
+        stronger.check_statement(&commitments),
+        Err(VerificationError::StatementMismatch)
+    );
+}
+
+#[test]
+fn folding_fri_rejects_high_degree_codewords_and_wrong_grinding_nonce() {
+    let hasher = default_quantum_hash();
+    let domain = Radix2EvaluationDomain::<StarkField>::new(64).expect("power-of-two domain");
+    let options = FriOptions {
+        remainder_size: 4,
+        num_queries: 16,
+        folding_factor: 4,
+        grinding_bits: 8,
+    };
+    let seed = [7u8; 32];
+    // Degree bound is 64 / 4 = 16 coefficients.
+    let codeword = |degree: u64| {
+        let coefficients: Vec<StarkField> = (1..=degree + 1).map(StarkField::from).collect();
+        domain.fft(&coefficients)
+    };
+    let first_layer = |evaluations: &[StarkField], positions: &[u64]| -> Vec<StarkField> {
+        positions.iter().map(|&p| evaluations[p as usize]).collect()
+    };
+
+    let honest = codeword(15);
+    let proof = prove_fri(honest.clone(), domain, &options, &seed, &hasher);
+    let opened = first_layer(&honest, &proof.query_positions);
+    assert_eq!(
+        proof.verify(domain, &options, &seed, &opened, &hasher),
+        Ok(())
+    );
+
+    let mut wrong_nonce = proof.clone();
+    wrong_nonce.pow_nonce += 1;
+    assert_eq!(
+        wrong_nonce.verify(domain, &options, &seed, &opened, &hasher),
+        Err(FriError::ProofOfWork)
+    );
+
+    let too_high = codeword(16);
+    let proof = prove_fri(too_high.clone(), domain, &options, &seed, &hasher);
+    let opened = first_layer(&too_high, &proof.query_positions);
+    assert!(matches!(
+        proof.verify(domain, &options, &seed, &opened, &hasher),
+        Err(FriError::FinalValueMismatch { .. })
+    ));
+}
+
+#[test]
+fn blank_count_stark_rejects_non_boolean_trace() {
+    let hasher = default_quantum_hash();
+    let params = StarkParameters::default();
+    let constraints = BlankCountConstraints::<StarkField>::new(6, 2);
+
+    let mut honest = vec![0u8; 36];
+    honest[3] = 1;
+    honest[17] = 1;
+    let proof = generate_blank_count_proof(
+        &BlankPolynomial::new(honest),
+        &constraints,
+        &params,
+        &hasher,
+    )
+    .expect("parameters meet their security level");
+    assert!(proof.verify(&constraints, &params, &hasher).is_ok());
+
+    // Same total, but one entry is 2: only the low-degree test can catch this.
+    let mut cheating = vec![0u8; 36];
+    cheating[3] = 2;
+    assert_eq!(
+        generate_blank_count_proof(
+            &BlankPolynomial::new(cheating.clone()),
+            &constraints,
+            &params,
+            &hasher,
+        )
+        .map(|_| ()),
+        Err(BlankProofError::Unsatisfied(
+            ConstraintViolation::NonBinaryValue(3, 2)
+        ))
+    );
+    let proof = unchecked_blank_count_proof(cheating, &constraints, &params);
+    assert!(matches!(
+        proof.verify(&constraints, &params, &hasher),
+        Err(StarkError::Fri(_))
+    ));
+}
+
+#[test]
+fn blank_trace_values_must_match_opened_bits() {
+    let hasher = default_quantum_hash();
+    let params = StarkParameters::default();
+    let constraints = BlankCountConstraints::<StarkField>::new(6, 1);
+    let mut bits = vec![0u8; 36];
+    bits[7] = 1;
+    let trace = TraceCommitment::new(
+        &constraints,
+        &constraints.build_trace(&BlankPolynomial::new(bits)),
+        &params,
+        &hasher,
+    );
+    let column = BlankCountConstraints::<StarkField>::VALUE_COLUMN;
+    let check = |claims: &[(usize, StarkField)]| {
+        let proof = trace
+            .prove_column_values(column, claims, &params, &hasher)
+            .expect("no opening budget without zero knowledge");
+        verify_column_values(
+            &proof,
+            &trace.root(),
+            trace.trace_length(),
+            column,
+            claims,
+            &params,
+            &hasher,
+        )
+    };
+
+    let one = StarkField::from(1u64);
+    let zero = StarkField::from(0u64);
+    assert!(check(&[(7, one), (3, zero), (35, zero)]).is_ok());
+    // Claiming a bit the trace does not hold is rejected even though the other claims hold.
+    assert!(check(&[(7, zero), (3, zero)]).is_err());
+    assert!(check(&[(3, one)]).is_err());
+}
+
+#[test]
+fn blank_response_rejects_stark_proof_from_another_commitment() {
+    let (graph, coloration, _params) = generate_hard_instance(12);
+    let mut prover = ProverState::new(graph, coloration.clone());
+    let mut verifier = Verifier::new(
+        coloration,
+        VerifierConfig {
+            rounds: 1,
+            spots_per_round: 2,
+            blank_checks_per_round: 3,
+            spot_probability: 0.0,
+            permutation_probability: 0.0,
+        },
+    );
+
+    let config = ProverConfig::default();
+    prover.commit(&config);
+    let challenge = BlankChallenge {
+        edge_indices: vec![0, 13, 77],
+        seed: [0u8; 32],
+    };
+    let stale = prover.respond_to_blank_challenge(&challenge);
+
+    verifier.receive_commitments(prover.commit(&config));
+    let mut response = prover.respond_to_blank_challenge(&challenge);
+    assert!(verifier
+        .verify_blank_response(&challenge, &response)
+        .is_ok());
+
+    response.stark_proof = stale.stark_proof;
+    assert_eq!(
+        verifier.verify_blank_response(&challenge, &response),
+        Err(VerificationError::TraceRootMismatch)
+    );
+}
+
+#[test]
+fn blank_budget_stark_accepts_slack_and_rejects_overrun() {
+    let hasher = default_quantum_hash();
+    let params = StarkParameters::default();
+    let constraints = BlankCountConstraints::<StarkField>::at_most(6, 5);
+
+    let mut under = vec![0u8; 36];
+    under[4] = 1;
+    under[20] = 1;
+    let polynomial = BlankPolynomial::new(under);
+    assert!(constraints.check(&polynomial).is_ok());
+    let proof = generate_blank_count_proof(&polynomial, &constraints, &params, &hasher)
+        .expect("parameters meet their security level");
+    assert!(proof.verify(&constraints, &params, &hasher).is_ok());
+    // The same trace does not satisfy the exact-count statement for the budget.
+    let exact = BlankCountConstraints::<StarkField>::new(6, 5);
+    assert_eq!(
+        proof.verify(&exact, &params, &hasher),
+        Err(StarkError::StatementMismatch)
+    );
+
+    let mut over = vec![0u8; 36];
+    for bit in over.iter_mut().take(6) {
+        *bit = 1;
+    }
+    let polynomial = BlankPolynomial::new(over);
+    assert!(matches!(
+        constraints.check(&polynomial),
+        Err(ConstraintViolation::BudgetExceeded {
+            limit: 5,
+            actual: 6
+        })
+    ));
+    assert!(matches!(
+        generate_blank_count_proof(&polynomial, &constraints, &params, &hasher),
+        Err(BlankProofError::Unsatisfied(_))
+    ));
+    let proof = unchecked_blank_count_proof(polynomial.values().to_vec(), &constraints, &params);
+    assert!(matches!(
+        proof.verify(&constraints, &params, &hasher),
+        Err(StarkError::Fri(_))
+    ));
+}
+
+/// Proves `bits` without checking them against `constraints` first, as a cheating prover
+/// would.
+fn unchecked_blank_count_proof(
+    bits: Vec<u8>,
+    constraints: &BlankCountConstraints<StarkField>,
+    params: &StarkParameters,
+) -> BlankCountProof {
+    let trace = constraints.build_trace(&BlankPolynomial::new(bits));
+    BlankCountProof {
+        expected_sum: constraints.expected_sum,
+        bound: constraints.bound,
+        proof: prove(constraints, &trace, params, &default_quantum_hash()),
+    }
+}
+
+#[test]
+fn stark_parameters_follow_security_level() {
+    let hasher = default_quantum_hash();
+    let constraints = BlankCountConstraints::<StarkField>::at_most(6, 3);
+    let params = StarkParameters::for_security_level(100).expect("100-bit parameters");
+    let estimate = check_air_security(&constraints, &params).expect("derived parameters");
+    assert!(estimate.conjectured_bits >= 100);
+    assert!(estimate.proven_bits < estimate.conjectured_bits);
+    assert!(matches!(
+        StarkParameters::for_security_level(192),
+        Err(ParameterError::HashTooShort { .. })
+    ));
+
+    let polynomial = BlankPolynomial::new(vec![0u8; 36]);
+    let trace = constraints.build_trace(&polynomial);
+    let octal = StarkParameters {
+        fri_folding_factor: 8,
+        ..params.clone()
+    };
+    let proof = prove(&constraints, &trace, &octal, &hasher);
+    assert!(verify(&constraints, &proof, &octal, &hasher).is_ok());
+
+    let weak = StarkParameters {
+        num_queries: 8,
+        ..params
+    };
+    assert!(matches!(
+        generate_blank_count_proof(&polynomial, &constraints, &weak, &hasher),
+        Err(BlankProofError::Parameters(
+            ParameterError::InsufficientSecurity { .. }
+        ))
+    ));
+    let proof = prove(&constraints, &trace, &weak, &hasher);
+    assert!(matches!(
+        verify(&constraints, &proof, &weak, &hasher),
+        Err(StarkError::Parameters(
+            ParameterError::InsufficientSecurity { .. }
+        ))
+    ));
+    let honest_about_it = StarkParameters {
+        security_level: 24,
+        ..weak
+    };
+    let proof = prove(&constraints, &trace, &honest_about_it, &hasher);
+    assert!(verify(&constraints, &proof, &honest_about_it, &hasher).is_ok());
+}
+
+#[test]
+fn verifier_config_reaches_requested_soundness() {
+    assert_eq!(spot_pattern_count(4), 44224);
+    let (_graph, coloration, _params) = generate_hard_instance(16);
+
+    let default_estimate = VerifierConfig::default().soundness(&coloration);
+    assert!(default_estimate.security_bits < 1.0);
+    assert!(default_estimate.random_security_bits > default_estimate.security_bits);
+
+    let config = VerifierConfig::for_security_bits(&coloration, 40).expect("40 bits reachable");
+    let estimate = config.soundness(&coloration);
+    assert!(estimate.security_bits >= 40.0);
+    assert!(estimate.soundness_error <= 2f64.powi(-40));
+    let fewer = VerifierConfig {
+        rounds: config.rounds - 1,
+        ..config.clone()
+    };
+    assert!(fewer.soundness(&coloration).security_bits < 40.0);
+    assert!(config.spot_probability > 0.0 && config.spot_probability < 1.0);
+
+    // Large graphs need more checks per round on both sides, not just more spots.
+    let (_graph, large, _params) = generate_hard_instance(64);
+    let config = VerifierConfig::for_security_bits(&large, 512).expect("512 bits reachable");
+    assert!(config.rounds <= 1 << 20, "{} rounds", config.rounds);
+    assert!(config.blank_checks_per_round > VerifierConfig::default().blank_checks_per_round);
+    assert!(config.soundness(&large).security_bits >= 512.0);
+
+    let unreachable = VerifierConfig::for_security_bits(&coloration, u32::MAX).unwrap_err();
+    assert_eq!(unreachable.requested, u32::MAX);
+    assert!(unreachable.achievable_bits < u32::MAX as f64);
+}
+
+/// Interpolates the value column through every distinct opened row and reads the blank
+/// bits back off the trace domain, as a verifier trying to learn the witness would.
+fn interpolate_opened_bits(
+    rows: &[&TraceRowOpening],
+    trace_length: usize,
+    params: &StarkParameters,
+) -> Vec<StarkField> {
+    let column = BlankCountConstraints::<StarkField>::VALUE_COLUMN;
+    let lde_domain = Radix2EvaluationDomain::<StarkField>::new(trace_length * params.blowup_factor)
+        .and_then(|domain| domain.get_coset(StarkField::GENERATOR))
+        .expect("LDE coset");
+    let mut points: Vec<(u64, StarkField)> = rows
+        .iter()
+        .map(|row| {
+            let value = field_from_bytes(&row.values[column]).expect("canonical field element");
+            (row.index, value)
+        })
+        .collect();
+    points.sort_by_key(|&(index, _)| index);
+    points.dedup_by_key(|&mut (index, _)| index);
+    let xs: Vec<StarkField> = points
+        .iter()
+        .map(|&(index, _)| lde_domain.element(index as usize))
+        .collect();
+
+    // Barycentric weights 1 / prod_{j != i} (x_i - x_j).
+    let mut weights: Vec<StarkField> = xs
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            xs.iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, &other)| x - other)
+                .product()
+        })
+        .collect();
+    batch_inversion(&mut weights);
+
+    let trace_domain = Radix2EvaluationDomain::<StarkField>::new(trace_length).expect("domain");
+    trace_domain
+        .elements()
+        .map(|h| {
+            let mut differences: Vec<StarkField> = xs.iter().map(|&x| h - x).collect();
+            let vanishing: StarkField = differences.iter().product();
+            batch_inversion(&mut differences);
+            let sum: StarkField = points
+                .iter()
+                .zip(&weights)
+                .zip(&differences)
+                .map(|((&(_, y), &w), &inverse)| y * w * inverse)
+                .sum();
+            vanishing * sum
+        })
+        .collect()
+}
+
+#[test]
+fn zero_knowledge_openings_stay_below_the_mask_degree() {
+    let hasher = default_quantum_hash();
+    let column = BlankCountConstraints::<StarkField>::VALUE_COLUMN;
+    let bit = |b: u8| StarkField::from(b as u64);
+
+    // Without masking the value column has degree below the trace length, and one proof
+    // of a small graph opens more rows than that, so the openings pin down every bit.
+    let plain = StarkParameters::default();
+    let small = BlankCountConstraints::<StarkField>::at_most(4, 3);
+    let mut small_bits = vec![0u8; 16];
+    small_bits[2] = 1;
+    small_bits[9] = 1;
+    let trace = TraceCommitment::new(
+        &small,
+        &small.build_trace(&BlankPolynomial::new(small_bits.clone())),
+        &plain,
+        &hasher,
+    );
+    let proof = prove_committed(&small, &trace, &plain, &hasher);
+    assert!(trace.mask_degree().is_none());
+    let rows: Vec<&TraceRowOpening> = proof
+        .queries
+        .iter()
+        .flat_map(|query| [&query.current, &query.next])
+        .collect();
+    let recovered = interpolate_opened_bits(&rows, trace.trace_length(), &plain);
+    assert!(small_bits
+        .iter()
+        .zip(&recovered)
+        .all(|(&b, &value)| bit(b) == value));
+
+    // Zero-knowledge parameters that would open a small trace past its mask are refused.
+    let masked = ParameterEngine::new(128)
+        .with_zero_knowledge(true)
+        .derive()
+        .expect("zero-knowledge parameters for 128 bits");
+    assert!(matches!(
+        check_air_security(&small, &masked),
+        Err(ParameterError::ZeroKnowledgeOpenings { .. })
+    ));
+
+    // A larger trace leaves room for the composition proof and two column-value proofs.
+    let constraints = BlankCountConstraints::<StarkField>::at_most(16, 40);
+    check_air_security(&constraints, &masked).expect("one proof fits the mask");
+    let mut bits = vec![0u8; 256];
+    for index in [3, 17, 64, 65, 200] {
+        bits[index] = 1;
+    }
+    let trace = TraceCommitment::new(
+        &constraints,
+        &constraints.build_trace(&BlankPolynomial::new(bits.clone())),
+        &masked,
+        &hasher,
+    );
+    let mask_degree = trace.mask_degree().expect("masked trace");
+    let proof = prove_committed(&constraints, &trace, &masked, &hasher);
+    let claims: Vec<(usize, StarkField)> = [3, 4, 200].iter().map(|&i| (i, bit(bits[i]))).collect();
+    let values: Vec<_> = (0..2)
+        .map(|_| {
+            trace
+                .prove_column_values(column, &claims, &masked, &hasher)
+                .expect("within the opening budget")
+        })
+        .collect();
+    assert!(trace.openings() < mask_degree);
+    assert!(matches!(
+        trace.prove_column_values(column, &claims, &masked, &hasher),
+        Err(ParameterError::ZeroKnowledgeOpenings { .. })
+    ));
+
+    let rows: Vec<&TraceRowOpening> = proof
+        .queries
+        .iter()
+        .flat_map(|query| [&query.current, &query.next])
+        .chain(values.iter().flat_map(|proof| &proof.queries))
+        .collect();
+    assert!(rows.len() < mask_degree);
+    let recovered = interpolate_opened_bits(&rows, trace.trace_length(), &masked);
+    let matching = bits
+        .iter()
+        .zip(&recovered)
+        .filter(|&(&b, &value)| bit(b) == value)
+        .count();
+    assert_eq!(
+        matching, 0,
+        "openings below the mask degree reveal no blank bit"
+    );
+
+    // Every round of a commitment may be a blank round opening the trace once more, so
+    // the prover refuses schedules that could exhaust the mask.
+    let (graph, coloration, _params) = generate_hard_instance(16);
+    let mut prover = ProverState::new(graph, coloration);
+    for (rounds_per_commitment, fits) in [
+        (None, false),
+        (Some(3), false),
+        (Some(2), true),
+        (Some(1), true),
+    ] {
+        let config = ProverConfig {
+            stark: masked.clone(),
+            rounds_per_commitment,
+            ..ProverConfig::default()
+        };
+        match prover.try_commit(&config) {
+            Ok(_) => assert!(fits),
+            Err(err) => {
+                assert!(!fits);
+                assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+            }
+        }
+    }
+
+    let mut over = vec![0u8; 256];
+    over[..41].fill(1);
+    let proof = unchecked_blank_count_proof(over, &constraints, &masked);
+    assert!(proof.verify(&constraints, &masked, &hasher).is_err());
+}
+
+/// `a[i + 1] = a[i] + step[i]` where `step` is the periodic column `[1, 2]`.
+struct AlternatingCounter {
+    rows: usize,
+    final_value: u64,
+}
+
+impl Air for AlternatingCounter {
+    fn trace_width(&self) -> usize {
+        1
+    }
+
+    fn trace_length(&self) -> usize {
+        self.rows
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        1
+    }
+
+    fn transition_degree(&self) -> usize {
+        1
+    }
+
+    fn evaluate_transition(
+        &self,
+        current: &[StarkField],
+        next: &[StarkField],
+        periodic: &[StarkField],
+        result: &mut [StarkField],
+    ) {
+        result[0] = next[0] - current[0] - periodic[0];
+    }
+
+    fn boundary_constraints(&self) -> Vec<BoundaryConstraint> {
+        vec![
+            BoundaryConstraint {
+                column: 0,
+                row: 0,
+                value: StarkField::from(0u64),
+            },
+            BoundaryConstraint {
+                column: 0,
+                row: self.rows - 1,
+                value: StarkField::from(self.final_value),
+            },
+        ]
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<StarkField>> {
+        vec![vec![StarkField::from(1u64), StarkField::from(2u64)]]
+    }
+
+    fn public_inputs(&self) -> Vec<u8> {
+        self.final_value.to_be_bytes().to_vec()
+    }
+}
+
+#[test]
+fn generic_air_with_periodic_column_round_trips() {
+    let hasher = default_quantum_hash();
+    let params = StarkParameters::default();
+    let rows = 64;
+    let trace: Vec<StarkField> = (0..rows as u64)
+        .map(|i| StarkField::from(i / 2 * 3 + i % 2))
+        .collect();
+    // Row 63 is reached after 32 steps of 1 and 31 steps of 2.
+    let air = AlternatingCounter {
+        rows,
+        final_value: 94,
+    };
+    let proof = prove(&air, std::slice::from_ref(&trace), &params, &hasher);
+    assert!(verify(&air, &proof, &params, &hasher).is_ok());
+
+    let wrong = AlternatingCounter {
+        rows,
+        final_value: 95,
+    };
+    let proof = prove(&wrong, &[trace], &params, &hasher);
+    assert!(verify(&wrong, &proof, &params, &hasher).is_err());
+}
+
+#[test]
+fn statement_files_carry_no_witness() {
+    let dir = std::env::temp_dir().join(format!("zkp-split-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("temp dir");
+    let (graph, coloration, params) = generate_hard_instance(8);
+    let instance = GraphInstance::with_metadata(graph, coloration, params);
+    let statement_path = dir.join("graph.statement");
+    let witness_path = dir.join("graph.witness");
+    save_public_statement(&statement_path, &instance.statement()).expect("save statement");
+    save_witness(&witness_path, &instance.witness()).expect("save witness");
+
+    let statement = load_public_statement(&statement_path).expect("load statement");
+    assert_eq!(statement.n, 8);
+    assert_eq!(statement.blank_limit, instance.coloration.blank_limit());
+    assert!(load_public_statement(&witness_path).is_err());
+    assert!(load_witness(&statement_path).is_err());
+
+    let rejoined = load_witness(&witness_path)
+        .expect("load witness")
+        .into_instance(&statement)
+        .expect("witness matches statement");
+    assert_eq!(rejoined.graph.adjacency, instance.graph.adjacency);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn prover_and_verifier_processes_run_the_protocol_over_tcp() {
+    let binary = env!("CARGO_BIN_EXE_zkp_c_coloring");
+    let dir = std::env::temp_dir().join(format!("zkp-tcp-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("temp dir");
+    let instance_path = dir.join("graph.bin");
+    let (graph, coloration, params) = generate_hard_instance(8);
+    save_graph_instance(
+        &instance_path,
+        &GraphInstance::with_metadata(graph, coloration, params),
+    )
+    .expect("save instance");
+    let statement_path = dir.join("graph.statement");
+    let witness_path = dir.join("graph.witness");
+    let split = Command::new(binary)
+        .arg("split")
+        .arg("--instance")
+        .arg(&instance_path)
+        .arg("--statement")
+        .arg(&statement_path)
+        .arg("--witness")
+        .arg(&witness_path)
+        .output()
+        .expect("split instance");
+    assert!(split.status.success());
+
+    let mut server = Command::new(binary)
+        .arg("serve-prover")
+        .arg("--statement")
+        .arg(&statement_path)
+        .arg("--witness")
+        .arg(&witness_path)
+        .args(["--listen", "127.0.0.1:0", "--sessions", "1"])
+        .args(["--rounds-per-commitment", "2", "--timeout-secs", "20"])
+        .arg("--transcript-dir")
+        .arg(&dir)
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("start prover");
+    // Keep reading the prover's stdout until it exits so its later log lines have a reader.
+    let mut prover_log = BufReader::new(server.stdout.take().expect("prover stdout"));
+    let mut banner = String::new();
+    prover_log.read_line(&mut banner).expect("prover banner");
+    let address = banner
+        .trim()
+        .strip_prefix("Prover listening on ")
+        .expect("listening banner")
+        .to_string();
+
+    let verifier_transcript = dir.join("verifier.transcript");
+    let status = Command::new(binary)
+        .arg("verify-remote")
+        .arg("--statement")
+        .arg(&statement_path)
+        .args([
+            "--connect",
+            &address,
+            "--rounds",
+            "5",
+            "--timeout-secs",
+            "20",
+        ])
+        .arg("--transcript")
+        .arg(&verifier_transcript)
+        .status()
+        .expect("run verifier");
+    assert!(status.success());
+    let mut rest = String::new();
+    prover_log.read_to_string(&mut rest).expect("prover log");
+    assert!(server.wait().expect("prover exit").success());
+    assert!(rest.contains("Session 1"), "prover log: {rest}");
+
+    let verifier_side = load_proof(&verifier_transcript).expect("verifier transcript");
+    let prover_side = load_proof(dir.join("session-1.transcript")).expect("prover transcript");
+    assert_eq!(verifier_side.rounds.len(), 5);
+    assert_eq!(verifier_side.commitment_count(), 3);
+    assert_eq!(
+        verifier_side.commitments.graph_root,
+        prover_side.commitments.graph_root
+    );
+    for (ours, theirs) in verifier_side.rounds.iter().zip(&prover_side.rounds) {
+        assert_eq!(ours.challenge, theirs.challenge);
     }
+    std::fs::remove_dir_all(&dir).ok();
 }
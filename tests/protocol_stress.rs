@@ -25,6 +25,7 @@ fn run_round_trip_with_instance(
             spots_per_round: spots,
             blank_checks_per_round: blanks,
             spot_probability: spot_prob,
+            permutation_probability: 0.0,
         },
     );
 
@@ -36,17 +37,21 @@ fn run_round_trip_with_instance(
         match verifier.generate_challenge(round) {
             Challenge::Spot(challenge) => {
                 let response = prover.respond_to_spot_challenge(&challenge);
-                assert!(
-                    verifier.verify_spot_response(&challenge, &response),
-                    "spot response rejected in round {round}"
-                );
+                verifier
+                    .verify_spot_response(&challenge, &response)
+                    .unwrap_or_else(|err| panic!("{}", err.in_round(round)));
             }
             Challenge::Blank(challenge) => {
                 let response = prover.respond_to_blank_challenge(&challenge);
-                assert!(
-                    verifier.verify_blank_response(&challenge, &response),
-                    "blank response rejected in round {round}"
-                );
+                verifier
+                    .verify_blank_response(&challenge, &response)
+                    .unwrap_or_else(|err| panic!("{}", err.in_round(round)));
+            }
+            Challenge::Permutation(challenge) => {
+                let response = prover.respond_to_permutation_challenge(&challenge);
+                verifier
+                    .verify_permutation_response(&challenge, &response)
+                    .unwrap_or_else(|err| panic!("{}", err.in_round(round)));
             }
         }
     }